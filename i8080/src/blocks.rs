@@ -0,0 +1,261 @@
+use super::cpu::{Address, CpuError, CPU};
+use super::decode::{self, Op};
+use super::disassembler::is_undocumented;
+use super::i8085::Variant;
+use super::io::IO;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/* A block-caching engine, for when the interpreter isn't fast enough. The first time the PC
+   reaches an address, the straight-line run of instructions from there to the next jump, call,
+   return or RST is decoded into a block and cached by its address. Running a block skips the
+   fetch, the decode and the interrupt check the interpreter does for every instruction.
+
+   Anything that touches the rest of the machine is left to the interpreter: IN and OUT, EI, DI
+   and HLT end a block before them, as do the undocumented opcodes so strict mode still sees them,
+   and whenever an interrupt could be taken, the CPU is halted or it's an 8085, the interpreter
+   runs the next instruction instead of a block. Memory flags writes to pages that cached code
+   came from; a block stops after the instruction that wrote, and the blocks from those pages are
+   thrown away before the next one runs. So the results are the same as the interpreter's, down
+   to the instruction and cycle counts. */
+
+/// The longest block, in instructions.
+const MAX_LENGTH: usize = 64;
+
+#[derive(Debug, Copy, Clone)]
+struct Instruction {
+    op: Op,
+    pc: Address,
+    next: Address,
+    cycles: u8,
+    cycles_taken: u8,
+}
+
+struct Block {
+    instructions: Vec<Instruction>,
+    pages: Vec<u8>,
+}
+
+/// How much was run by one call to `BlockCache::run`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Ran {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+/// Decoded blocks by start address, along with how many were decoded and thrown away.
+pub struct BlockCache {
+    blocks: Vec<Option<Box<Block>>>,
+    /// The start addresses of the blocks decoded from each page.
+    page_blocks: Vec<Vec<Address>>,
+    pub compiled: u64,
+    pub invalidated: u64,
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        Self {
+            blocks: (0..0x10000).map(|_| None).collect(),
+            page_blocks: vec![Vec::new(); 0x100],
+            compiled: 0,
+            invalidated: 0,
+        }
+    }
+
+    /// Runs at least one instruction, and then a block's worth until `cycles` have gone by, with
+    /// the interpreter or from the cache.
+    pub fn run<T: IO + ?Sized>(&mut self, cpu: &mut CPU, io: &mut T, cycles: u64) -> Result<Ran, CpuError> {
+        if cpu.memory.code_written {
+            self.invalidate(cpu);
+        }
+        if needs_interpreter(cpu, io) {
+            return cpu.try_tick(io).map(|cycles| Ran { instructions: 1, cycles });
+        }
+
+        let pc = cpu.reg.pc;
+        if self.blocks[pc as usize].is_none() {
+            self.compile(cpu, pc);
+        }
+        let block = match self.blocks[pc as usize].as_ref() {
+            Some(block) if !block.instructions.is_empty() => block,
+            _ => return cpu.try_tick(io).map(|cycles| Ran { instructions: 1, cycles }),
+        };
+
+        let mut ran = Ran::default();
+        for instruction in block.instructions.iter() {
+            cpu.reg.pc = instruction.next;
+            let taken = cpu.execute(instruction.op, instruction.pc, io);
+            ran.instructions += 1;
+            ran.cycles += u64::from(if taken { instruction.cycles_taken } else { instruction.cycles });
+            if ran.cycles >= cycles || cpu.memory.code_written {
+                break;
+            }
+        }
+        Ok(ran)
+    }
+
+    /// How many blocks are cached.
+    pub fn len(&self) -> usize {
+        self.page_blocks.iter().map(|starts| starts.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.page_blocks.iter().all(|starts| starts.is_empty())
+    }
+
+    fn compile(&mut self, cpu: &mut CPU, start: Address) {
+        let table = decode::table(cpu.variant);
+        let mut instructions = Vec::new();
+        let mut pages = Vec::new();
+        let mut pc = start;
+        while instructions.len() < MAX_LENGTH {
+            let opcode = cpu.memory[pc];
+            let decoded = &table[opcode as usize];
+            if is_undocumented(opcode) || matches!(decoded.op, Op::In | Op::Out | Op::Ei | Op::Di | Op::Hlt) {
+                break;
+            }
+            let next = pc.wrapping_add(u16::from(decoded.length));
+            for offset in 0..u16::from(decoded.length) {
+                let page = (pc.wrapping_add(offset) >> 8) as u8;
+                if !pages.contains(&page) {
+                    pages.push(page);
+                }
+            }
+            instructions.push(Instruction {
+                op: decoded.op,
+                pc,
+                next,
+                cycles: decoded.cycles,
+                cycles_taken: decoded.cycles_taken,
+            });
+            if matches!(decoded.op, Op::Jump(_) | Op::Call(_) | Op::Return(_) | Op::Rst(_) | Op::Pchl) {
+                break;
+            }
+            pc = next;
+        }
+
+        // an empty block is kept too, so the interpreter gets the instruction without asking again
+        if pages.is_empty() {
+            pages.push((start >> 8) as u8);
+        }
+        for &page in pages.iter() {
+            cpu.memory.mark_code(page);
+            self.page_blocks[page as usize].push(start);
+        }
+        self.blocks[start as usize] = Some(Box::new(Block { instructions, pages }));
+        self.compiled += 1;
+    }
+
+    /// Throws away the blocks decoded from pages that have been written to.
+    fn invalidate(&mut self, cpu: &mut CPU) {
+        for page in cpu.memory.take_written_code() {
+            for start in core::mem::take(&mut self.page_blocks[page as usize]) {
+                if let Some(block) = self.blocks[start as usize].take() {
+                    self.invalidated += 1;
+                    for &other in block.pages.iter().filter(|&&other| other != page) {
+                        self.page_blocks[other as usize].retain(|&address| address != start);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs from the cache if there is one, otherwise an instruction with the interpreter.
+pub fn run<T: IO + ?Sized>(blocks: Option<&mut BlockCache>, cpu: &mut CPU, io: &mut T, cycles: u64) -> Result<Ran, CpuError> {
+    match blocks {
+        Some(blocks) => blocks.run(cpu, io, cycles),
+        None => cpu.try_tick(io).map(|cycles| Ran { instructions: 1, cycles }),
+    }
+}
+
+/// Whether the next instruction has to go through the interpreter, because an interrupt could be
+/// taken before it or the CPU is in a state blocks don't handle.
+fn needs_interpreter<T: IO + ?Sized>(cpu: &mut CPU, io: &mut T) -> bool {
+    if cpu.variant != Variant::I8080 || cpu.halted || cpu.ei_delay {
+        return true;
+    }
+    if cpu.interrupts_enabled {
+        // the interpreter asks before every instruction; the answer can only change at an IN or
+        // OUT, or between calls, so asking before each block is the same
+        if cpu.interrupt_request.is_none() {
+            cpu.interrupt_request = io.acknowledge_interrupt();
+        }
+        return cpu.interrupt_request.is_some();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::NullIO;
+    use crate::register::Reg16::{BC, DE, HL, PC, PSW, SP};
+
+    fn state(cpu: &CPU) -> (Vec<u16>, bool, bool, Vec<u8>) {
+        let registers = vec![cpu.reg[PSW], cpu.reg[BC], cpu.reg[DE], cpu.reg[HL], cpu.reg[SP], cpu.reg[PC]];
+        (registers, cpu.interrupts_enabled, cpu.halted, cpu.memory.view(0, 0xffff).to_vec())
+    }
+
+    /// Runs two copies of a program, one with blocks, in chunks of `cycles`, checking they agree
+    /// after each.
+    fn run_both(program: &[u8], origin: usize, cycles: u64, chunks: usize) -> (CPU, BlockCache) {
+        let (mut interpreted, mut cached) = (CPU::new(), CPU::new());
+        let mut blocks = BlockCache::new();
+        for cpu in [&mut interpreted, &mut cached].iter_mut() {
+            cpu.memory.load(origin, program);
+            // BDOS calls return straight away
+            cpu.memory.load(0x0005, &[0xc9]);
+            cpu.reg[SP] = 0xff00;
+            cpu.reg[PC] = origin as u16;
+        }
+        for chunk in 0..chunks {
+            let mut ran = Ran::default();
+            while ran.cycles < cycles {
+                ran.cycles += interpreted.tick(&mut NullIO);
+                ran.instructions += 1;
+            }
+            let mut ran_cached = Ran::default();
+            while ran_cached.cycles < cycles {
+                let more = blocks.run(&mut cached, &mut NullIO, cycles - ran_cached.cycles).unwrap();
+                ran_cached.cycles += more.cycles;
+                ran_cached.instructions += more.instructions;
+            }
+            assert_eq!(ran_cached, ran, "chunk {}", chunk);
+            assert!(state(&cached) == state(&interpreted), "state differs after chunk {}:\n{:?}\n{:?}", chunk, cached, interpreted);
+        }
+        (cached, blocks)
+    }
+
+    #[test]
+    fn test_cpudiag_matches_the_interpreter() {
+        let program = std::fs::read("../ROMS/cpudiag.bin").unwrap();
+        let (_, blocks) = run_both(&program, 0x100, 1000, 40);
+        assert!(blocks.len() > 20, "{} blocks", blocks.len());
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        let program = [
+            0x21, 0x00, 0x00,       // LXI H, 0
+            0x3e, 0x2c,             // loop: MVI A, INR L
+            0x32, 0x0a, 0x01,       // STA patch
+            0x00,                   // NOP
+            0x00,                   // NOP
+            0x00,                   // patch: NOP, then INR L
+            0x3e, 0x00,             // MVI A, NOP
+            0x32, 0x0a, 0x01,       // STA patch
+            0xc3, 0x03, 0x01,       // JMP loop
+        ];
+        let (cpu, blocks) = run_both(&program, 0x100, 20, 50);
+        assert!(cpu.reg[HL] > 10, "HL is {:04x}", cpu.reg[HL]);
+        assert!(blocks.invalidated > 10, "{} invalidated", blocks.invalidated);
+    }
+}
@@ -0,0 +1,2072 @@
+use super::decode::{self, Alu, Condition, Op};
+use super::disassembler::{is_undocumented, undocumented_alias};
+#[cfg(feature = "alloc")]
+use super::disassembler::{op_name, op_name_8085};
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+use super::i8085::{State8085, Variant};
+use super::io::{InterruptInstruction, IO};
+use super::timing;
+
+pub type Address = u16;
+pub type Word = u16;
+pub type Byte = u8;
+
+/// How long a halted CPU waits for an interrupt each tick, so machines keep their time.
+const HALTED_CYCLES: u64 = 4;
+
+const SZP_FLAGS: Byte = Sign as Byte | Zero as Byte | Parity as Byte;
+
+/// The sign, zero and parity flags for every result, as they sit in F.
+const SZP: [Byte; 256] = szp_table();
+
+const fn szp_table() -> [Byte; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as Byte;
+        table[i] = (byte & Sign as Byte)
+            | if byte == 0 { Zero as Byte } else { 0 }
+            | if byte.count_ones() & 1 == 0 { Parity as Byte } else { 0 };
+        i += 1;
+    }
+    table
+}
+
+use core::fmt;
+
+use super::register::Register;
+use super::memory::Memory;
+use super::register::Reg8;
+use super::register::Reg16;
+use super::register::Reg8::{A, F, M};
+#[cfg(any(feature = "alloc", test))]
+use super::register::Reg8::{B, C, D, E, H, L};
+use super::register::Reg16::{BC, DE, HL, SP, PC, PSW};
+use super::register::Flag::{Carry, Parity, Sign, AuxCarry, Zero};
+
+/// An 8080 or 8085 with its registers and 64K of memory. Make one with `new` or `with_variant`.
+#[non_exhaustive]
+pub struct CPU {
+    pub reg: Register,
+    pub memory: Memory,
+    pub interrupts_enabled: bool,
+    /// EI lets interrupts in only after the instruction following it, so that EI; RET at the end
+    /// of a handler returns before the next interrupt arrives.
+    pub ei_delay: bool,
+    pub halted: bool,
+    /// An interrupt held on the INT line until the CPU acknowledges it.
+    pub interrupt_request: Option<InterruptInstruction>,
+    pub num_interrupts: u64,
+    /// Treat JMP 0 as exit and CALL 5 as CP/M's print-string call, for running CP/M test
+    /// programs like cpudiag without an operating system underneath them.
+    pub bdos_traps: bool,
+    /// Refuse to run the undocumented opcodes, for finding programs that stray into data.
+    pub strict: bool,
+    pub variant: Variant,
+    pub i8085: State8085,
+}
+
+/// Why `try_tick` stopped.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum CpuError {
+    UndocumentedOpcode { opcode: Byte, address: Address },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            CpuError::UndocumentedOpcode { opcode, address } =>
+                write!(f, "undocumented opcode {:02x} ({} alias) at {:04x}", opcode, undocumented_alias(*opcode), address),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CpuError {}
+
+impl CPU {
+    pub fn new() -> Self {
+        Self::with_variant(Variant::I8080)
+    }
+
+    pub fn with_variant(variant: Variant) -> Self {
+        CPU {
+            reg: Register::new(),
+            memory: Memory::new(),
+            interrupts_enabled: false,
+            ei_delay: false,
+            halted: false,
+            interrupt_request: None,
+            num_interrupts: 0,
+            bdos_traps: false,
+            strict: false,
+            variant,
+            i8085: State8085::default(),
+        }
+    }
+
+    pub fn tick<T: IO + ?Sized>(&mut self, io: &mut T) -> u64 {
+        if let Some(cycles) = self.interrupt_or_halt(io) {
+            return cycles;
+        }
+        let opcode = self.fetch();
+        self.execute_opcode(opcode, io) as u64
+    }
+
+    /// Like `tick`, but in strict mode stops at an undocumented opcode instead of running it. An
+    /// interrupt taken first isn't stopped, since the opcode at PC doesn't run.
+    pub fn try_tick<T: IO + ?Sized>(&mut self, io: &mut T) -> Result<u64, CpuError> {
+        if let Some(cycles) = self.interrupt_or_halt(io) {
+            return Ok(cycles);
+        }
+        let opcode = self.fetch();
+        if self.strict && self.variant == Variant::I8080 && is_undocumented(opcode) {
+            return Err(CpuError::UndocumentedOpcode { opcode, address: self.reg[PC] });
+        }
+        Ok(self.execute_opcode(opcode, io) as u64)
+    }
+
+    /// Takes an interrupt, or waits while halted, returning the cycles spent if either happened
+    /// instead of an instruction being fetched.
+    fn interrupt_or_halt<T: IO + ?Sized>(&mut self, io: &mut T) -> Option<u64> {
+        let accepting = self.interrupts_enabled && !self.ei_delay;
+        self.ei_delay = false;
+
+        if self.variant == Variant::I8085 {
+            if let Some(cycles) = self.service_8085_interrupt(accepting) {
+                self.num_interrupts += 1;
+                self.halted = false;
+                return Some(cycles);
+            }
+        }
+        if accepting {
+            if let Some(instruction) = self.interrupt_request.take().or_else(|| io.acknowledge_interrupt()) {
+                return Some(self.execute_interrupt(instruction, io));
+            }
+        }
+        if self.halted {
+            return Some(HALTED_CYCLES);
+        }
+        None
+    }
+
+    pub fn fetch(&self) -> Byte {
+        self.memory[self.reg.pc]
+    }
+
+    /// Raises the INT line with RST `interrupt_num` on the bus. It's held until taken, and a later
+    /// request replaces one that hasn't been taken yet.
+    pub fn interrupt(&mut self, interrupt_num: Word) {
+        self.interrupt_request = Some(InterruptInstruction::rst(interrupt_num as u8));
+    }
+
+    /// Runs an instruction supplied by an interrupting device. It doesn't come from memory, so
+    /// the PC isn't moved past it and a CALL or RST pushes the address of the instruction that
+    /// was interrupted.
+    fn execute_interrupt<T: IO + ?Sized>(&mut self, instruction: InterruptInstruction, io: &mut T) -> u64 {
+        self.num_interrupts += 1;
+        self.interrupts_enabled = false;
+        self.halted = false;
+        match instruction {
+            InterruptInstruction::Opcode(opcode) if opcode & 0b1100_0111 == 0b1100_0111 => {
+                self.push_pc_and_jump(Word::from(opcode & 0b0011_1000));
+                timing::interrupt_cycles(self.variant)
+            },
+            InterruptInstruction::Opcode(opcode) => {
+                let length = decode::table(self.variant)[opcode as usize].length;
+                assert!(length == 1, "interrupting device supplied opcode {:02x}, which needs {} bytes; only a CALL can \
+                                      bring its operands over the bus, as InterruptInstruction::Call", opcode, length);
+                // run as if it sat just before PC, so that it leaves PC where it was
+                self.reg.pc = self.reg.pc.wrapping_sub(1);
+                u64::from(self.execute_opcode(opcode, io))
+            },
+            InterruptInstruction::Call(address) => {
+                self.push_pc_and_jump(address);
+                u64::from(timing::cycles(self.variant, 0xcd, true))
+            },
+        }
+    }
+
+    fn push_pc_and_jump(&mut self, address: Address) {
+        self.push_word(self.reg[PC]);
+        self.reg[PC] = address;
+    }
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "{:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4}",
+                 "a", "bc", "de", "hl", "pc", "sp", "flags")?;
+
+        write!(f,
+               "{:04x} {:04x} {:04x} {:04x} {:04x} {:04x} {}",
+               self.reg[A],
+               self.reg[BC],
+               self.reg[DE],
+               self.reg[HL],
+               self.reg[PC],
+               self.reg[SP],
+               self.reg.flag_names(),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "PC={:04x}:{:02x}:{}\
+            | a={:02x} b={:02x} c={:02x} d={:02x} e={:02x} h={:02x} l={:02x} | \
+             sp={:04x} flags={}",
+            self.reg[PC],
+            self.read_byte_at_address(self.reg[PC]),
+            self.next_opcode(),
+            self.reg[A],
+            self.reg[B],
+            self.reg[C],
+            self.reg[D],
+            self.reg[E],
+            self.reg[H],
+            self.reg[L],
+            self.reg[SP],
+            self.reg.flag_names(),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CPU {
+    /// A trace line in the stable NAME:VALUE format trace-diff compares, with F as PUSH PSW
+    /// would push it and the disassembly after a semicolon for reading.
+    pub fn trace_line(&self, cycles: u64) -> String {
+        format!(
+            "PC:{:04x} OP:{:02x} A:{:02x} F:{:02x} B:{:02x} C:{:02x} D:{:02x} E:{:02x} H:{:02x} L:{:02x} SP:{:04x} CYC:{} ; {}",
+            self.reg[PC],
+            self.read_byte_at_address(self.reg[PC]),
+            self.reg[A],
+            self.flags_as_pushed(),
+            self.reg[B],
+            self.reg[C],
+            self.reg[D],
+            self.reg[E],
+            self.reg[H],
+            self.reg[L],
+            self.reg[SP],
+            cycles,
+            self.next_opcode(),
+        )
+    }
+}
+
+impl CPU { // Helper functions
+
+    #[cfg(feature = "alloc")]
+    fn next_opcode(&self) -> String {
+        let opcode = self.memory[self.reg[PC]];
+        match self.variant {
+            Variant::I8080 => op_name(opcode, self.read_byte_immediate(), self.read_word_immediate()),
+            Variant::I8085 => op_name_8085(opcode, self.read_byte_immediate(), self.read_word_immediate()),
+        }
+    }
+
+    #[cfg(any(feature = "alloc", test))]
+    fn read_byte_at_address(&self, address: Address) -> Byte {
+        self.memory[address]
+    }
+
+    fn read_word_at_address(&self, address: Address) -> Word {
+         ((self.memory[address.wrapping_add(1)] as Word) << 8) | self.memory[address] as Word
+    }
+
+    #[cfg(any(feature = "alloc", test))]
+    fn read_byte_immediate(&self) -> Byte {
+        self.byte_after(self.reg.pc)
+    }
+
+    #[cfg(any(feature = "alloc", test))]
+    fn read_word_immediate(&self) -> Word {
+        self.word_after(self.reg.pc)
+    }
+
+    /// The byte following the opcode at `pc`.
+    fn byte_after(&self, pc: Address) -> Byte {
+        self.memory[pc.wrapping_add(1)]
+    }
+
+    /// The word following the opcode at `pc`.
+    fn word_after(&self, pc: Address) -> Word {
+        self.read_word_at_address(pc.wrapping_add(1))
+    }
+
+    fn write_word_to_memory(&mut self, address: Address, word: Word) {
+        self.memory[address.wrapping_add(1)] = (word >> 8) as Byte;
+        self.memory[address] = word as u8;
+    }
+
+    /// A register, or for M the byte HL points at.
+    pub(super) fn get_operand(&self, register: Reg8) -> Byte {
+        match register {
+            M => self.memory[self.reg[HL]],
+            _ => self.reg[register],
+        }
+    }
+
+    fn set_operand(&mut self, register: Reg8, byte: Byte) {
+        match register {
+            M => self.memory[self.reg[HL]] = byte,
+            _ => self.reg[register] = byte,
+        }
+    }
+
+    fn holds(&self, condition: Condition) -> bool {
+        match condition {
+            Condition::Always => true,
+            Condition::If(flag, set) => self.reg.get_flag(flag) == set,
+        }
+    }
+
+    fn set_flags_on_result(&mut self, result: Byte, overflow: bool, aux: bool) {
+        let aux = if aux { AuxCarry as Byte } else { 0 };
+        self.reg[F] = self.reg[F] & !(SZP_FLAGS | AuxCarry as Byte | Carry as Byte) | SZP[result as usize] | aux | overflow as Byte;
+    }
+
+    fn set_zsp_flags_on_byte(&mut self, byte: Byte) {
+        self.reg[F] = self.reg[F] & !SZP_FLAGS | SZP[byte as usize];
+    }
+
+    /* Every add and subtract comes down to these two. The 8080 subtracts by adding the complement,
+       so AC after a subtraction is the carry out of bit 3 of that addition, and CY is its inverse:
+       set when there was a borrow. */
+    fn add_with_carry(&mut self, byte: Byte, carry: bool) -> Byte {
+        let sum = Word::from(self.reg[A]) + Word::from(byte) + carry as Word;
+        let result = sum as Byte;
+        self.set_flags_on_result(result, sum > 0xFF, (self.reg[A] & 0xf) + (byte & 0xf) + carry as Byte > 0xf);
+        result
+    }
+
+    fn subtract_with_borrow(&mut self, byte: Byte, borrow: bool) -> Byte {
+        let result = self.reg[A].wrapping_sub(byte).wrapping_sub(borrow as Byte);
+        let borrowed = Word::from(self.reg[A]) < Word::from(byte) + borrow as Word;
+        self.set_flags_on_result(result, borrowed, (self.reg[A] & 0xf) + (!byte & 0xf) + !borrow as Byte > 0xf);
+        result
+    }
+}
+
+impl CPU { // ARITHMETIC GROUP
+    fn inr(&mut self, x: Reg8) {
+        let result = self.get_operand(x).wrapping_add(1);
+        self.set_operand(x, result);
+        self.set_zsp_flags_on_byte(result);
+        self.reg.set_flag(AuxCarry, result & 0xf == 0);
+    }
+
+    fn dcr(&mut self, x: Reg8) {
+        let result = self.get_operand(x).wrapping_sub(1);
+        self.set_operand(x, result);
+        self.set_zsp_flags_on_byte(result);
+        self.reg.set_flag(AuxCarry, result & 0xf != 0xf);
+    }
+
+    fn dad(&mut self, x: Reg16) {
+        let (result, overflow) = self.reg[HL].overflowing_add(self.reg[x]);
+        self.reg.set_flag(Carry, overflow);
+        self.reg[HL] = result;
+    }
+
+    /// The ALU block and its immediate forms, with A as the first operand.
+    fn alu(&mut self, op: Alu, byte: Byte) {
+        match op {
+            Alu::Add => self.reg[A] = self.add_with_carry(byte, false),
+            Alu::Adc => self.reg[A] = self.add_with_carry(byte, self.reg.get_flag(Carry)),
+            Alu::Sub => self.reg[A] = self.subtract_with_borrow(byte, false),
+            Alu::Sbb => self.reg[A] = self.subtract_with_borrow(byte, self.reg.get_flag(Carry)),
+            Alu::Ana => self.ana(byte),
+            Alu::Xra => {
+                self.reg[A] ^= byte;
+                self.set_flags_on_result(self.reg[A], false, false);
+            },
+            Alu::Ora => {
+                self.reg[A] |= byte;
+                self.set_flags_on_result(self.reg[A], false, false);
+            },
+            Alu::Cmp => {
+                self.subtract_with_borrow(byte, false);
+            },
+        }
+    }
+
+    /* Corrects A after adding two BCD numbers: 6 is added to a digit that went past 9 or carried
+       out of itself, and the carry out of the top digit is kept. */
+    fn daa(&mut self) {
+        let (low, high) = (self.reg[A] & 0xf, self.reg[A] >> 4);
+        let mut correction = 0;
+        let mut carry = self.reg.get_flag(Carry);
+        if low > 9 || self.reg.get_flag(AuxCarry) {
+            correction |= 0x06;
+        }
+        if high > 9 || carry || (high >= 9 && low > 9) {
+            correction |= 0x60;
+            carry = true;
+        }
+        let result = self.reg[A].wrapping_add(correction);
+        self.set_flags_on_result(result, carry, low + (correction & 0xf) > 0xf);
+        self.reg[A] = result;
+    }
+}
+
+impl CPU { // LOGICAL GROUP
+    /* The 8080 sets AC from bit 3 of the operands, the 8085 always sets it. */
+    fn ana(&mut self, byte: Byte) {
+        let aux = match self.variant {
+            Variant::I8080 => (self.reg[A] | byte) & 0x08 != 0,
+            Variant::I8085 => true,
+        };
+        self.reg[A] &= byte;
+        self.set_flags_on_result(self.reg[A], false, aux);
+    }
+
+    fn rlc(&mut self) {
+        self.reg.set_flag(Carry, self.reg[A] >> 7 != 0);
+        self.reg[A] = self.reg[A].rotate_left(1);
+    }
+
+    fn rrc(&mut self) {
+        self.reg.set_flag(Carry, self.reg[A] & 1 != 0);
+        self.reg[A] = self.reg[A].rotate_right(1);
+    }
+
+    fn ral(&mut self) {
+        let carry = self.reg[A] >> 7 != 0;
+        self.reg[A] = self.reg[A] << 1 | self.reg.get_flag(Carry) as Byte;
+        self.reg.set_flag(Carry, carry);
+    }
+
+    fn rar(&mut self) {
+        let carry = self.reg[A] & 1 != 0;
+        self.reg[A] = self.reg[A] >> 1 | (self.reg.get_flag(Carry) as Byte) << 7;
+        self.reg.set_flag(Carry, carry);
+    }
+}
+
+impl CPU { // STACK GROUP
+    pub(super) fn push_word(&mut self, word: Word) {
+        self.reg[SP] = self.reg[SP].wrapping_sub(2);
+        self.write_word_to_memory(self.reg[SP], word);
+    }
+
+    fn pop_word(&mut self) -> Word {
+        let word = self.read_word_at_address(self.reg[SP]);
+        self.reg[SP] = self.reg[SP].wrapping_add(2);
+        word
+    }
+
+    /// F with the bits that aren't flags as the chip pushes them.
+    pub fn flags_as_pushed(&self) -> Byte {
+        match self.variant {
+            Variant::I8080 => self.reg[F] & 0b1101_0101 | 0b0000_0010,
+            Variant::I8085 => self.reg[F] & 0b1111_0111,
+        }
+    }
+
+    /// Sets F from a byte in the pushed layout, keeping only the bits that are flags.
+    pub fn set_flags_from_pushed(&mut self, byte: Byte) {
+        self.reg[F] = byte & match self.variant {
+            Variant::I8080 => 0b1101_0101,
+            Variant::I8085 => 0b1111_0111,
+        };
+    }
+
+    fn xthl(&mut self) {
+        let hl = self.reg[HL];
+        self.reg[HL] = self.read_word_at_address(self.reg[SP]);
+        self.write_word_to_memory(self.reg[SP], hl);
+    }
+}
+
+impl CPU { // CP/M
+    /* With bdos_traps set, a JMP 0 ends the program and a CALL 5 is taken as a BDOS call, of which
+       only C = 9, print the string at DE up to a $, does anything. Without std there's no stdout
+       to print to or process to end, so the call does nothing and JMP 0 halts. */
+    #[cfg(feature = "std")]
+    fn bdos_exit(&mut self) {
+        println!();
+        ::std::process::exit(0);
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn bdos_exit(&mut self) {
+        self.halted = true;
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn bdos_call(&self) {}
+
+    #[cfg(feature = "std")]
+    fn bdos_call(&self) {
+        if self.reg[C] == 9 {
+            let mut address = self.reg[DE] + 3;
+            let mut bytes: Vec<char> = Vec::new();
+            while (self.memory[address] as char) != '$' {
+                bytes.push(self.memory[address] as char);
+                address += 1;
+            }
+            for byte in bytes {
+                print!("{}", byte);
+            }
+            println!();
+        }
+    }
+}
+
+impl CPU {
+    pub fn execute_opcode<T: IO + ?Sized>(&mut self, opcode: Byte, io: &mut T) -> Word {
+        let decoded = &decode::table(self.variant)[opcode as usize];
+        if self.variant == Variant::I8085 {
+            // the time depends on the flags before the instruction changes them
+            let taken = decoded.cycles != decoded.cycles_taken && self.condition_met(opcode);
+            self.update_8085_flags(opcode);
+            if let Some(op_length) = self.execute_8085_only(opcode) {
+                self.reg[PC] = self.reg[PC].wrapping_add(op_length);
+                return decoded.time(taken);
+            }
+        }
+        let pc = self.reg.pc;
+        self.reg.pc = pc.wrapping_add(Word::from(decoded.length));
+        let taken = self.execute(decoded.op, pc, io);
+        decoded.time(taken)
+    }
+
+    /// Runs an instruction the two chips share, found at `pc`. The PC has already been moved on
+    /// past it, which is where a CALL returns to. Returns false when a conditional jump, call or
+    /// return doesn't go anywhere.
+    pub(super) fn execute<T: IO + ?Sized>(&mut self, op: Op, pc: Address, io: &mut T) -> bool {
+        match op {
+            Op::Nop => {},
+            Op::Lxi(x) => self.reg[x] = self.word_after(pc),
+            Op::Stax(x) => self.memory[self.reg[x]] = self.reg[A],
+            Op::Ldax(x) => self.reg[A] = self.memory[self.reg[x]],
+            Op::Shld => self.write_word_to_memory(self.word_after(pc), self.reg[HL]),
+            Op::Lhld => self.reg[HL] = self.read_word_at_address(self.word_after(pc)),
+            Op::Sta => {
+                let address = self.word_after(pc);
+                self.memory[address] = self.reg[A];
+            },
+            Op::Lda => self.reg[A] = self.memory[self.word_after(pc)],
+            Op::Inx(x) => self.reg[x] = self.reg[x].wrapping_add(1),
+            Op::Dcx(x) => self.reg[x] = self.reg[x].wrapping_sub(1),
+            Op::Dad(x) => self.dad(x),
+            Op::Inr(x) => self.inr(x),
+            Op::Dcr(x) => self.dcr(x),
+            Op::Mvi(x) => self.set_operand(x, self.byte_after(pc)),
+            Op::Rlc => self.rlc(),
+            Op::Rrc => self.rrc(),
+            Op::Ral => self.ral(),
+            Op::Rar => self.rar(),
+            Op::Daa => self.daa(),
+            Op::Cma => self.reg[A] = !self.reg[A],
+            Op::Stc => self.reg.set_flag(Carry, true),
+            Op::Cmc => self.reg.set_flag(Carry, !self.reg.get_flag(Carry)),
+            Op::Mov(dest, src) => self.set_operand(dest, self.get_operand(src)),
+            Op::Hlt => self.halted = true,
+            Op::Alu(op, x) => self.alu(op, self.get_operand(x)),
+            Op::AluImmediate(op) => self.alu(op, self.byte_after(pc)),
+            Op::Jump(condition) => {
+                let address = self.word_after(pc);
+                if self.bdos_traps && address == 0 && matches!(condition, Condition::Always) {
+                    self.bdos_exit();
+                }
+                if !self.holds(condition) {
+                    return false;
+                }
+                self.reg.pc = address;
+            },
+            Op::Call(condition) => {
+                let address = self.word_after(pc);
+                if self.bdos_traps && address == 0x0005 && matches!(condition, Condition::Always) {
+                    self.bdos_call();
+                    return true;
+                }
+                if !self.holds(condition) {
+                    return false;
+                }
+                self.push_word(self.reg.pc);
+                self.reg.pc = address;
+            },
+            Op::Return(condition) => {
+                if !self.holds(condition) {
+                    return false;
+                }
+                self.reg.pc = self.pop_word();
+            },
+            Op::Rst(address) => {
+                self.push_word(self.reg.pc);
+                self.reg.pc = address;
+            },
+            Op::Push(PSW) => self.push_word((self.reg[A] as Word) << 8 | self.flags_as_pushed() as Word),
+            Op::Push(x) => self.push_word(self.reg[x]),
+            Op::Pop(PSW) => {
+                self.reg[PSW] = self.pop_word();
+                self.set_flags_from_pushed(self.reg[F]);
+            },
+            Op::Pop(x) => self.reg[x] = self.pop_word(),
+            Op::Out => io.output(self.byte_after(pc), self.reg[A]),
+            Op::In => self.reg[A] = io.input(self.byte_after(pc)),
+            Op::Xthl => self.xthl(),
+            Op::Xchg => {
+                let hl = self.reg[HL];
+                self.reg[HL] = self.reg[DE];
+                self.reg[DE] = hl;
+            },
+            Op::Pchl => self.reg.pc = self.reg[HL],
+            Op::Sphl => self.reg[SP] = self.reg[HL],
+            Op::Di => self.interrupts_enabled = false,
+            Op::Ei => {
+                self.interrupts_enabled = true;
+                self.ei_delay = true;
+            },
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::NullIO;
+    
+    #[test]
+    fn test_memory_accessors() {
+        let mut cpu = CPU::new();
+
+        cpu.memory[0x0] = 0x34;
+        cpu.memory[0x1] = 0x11;
+        cpu.memory[0x2] = 0x24;
+        cpu.memory[0x3] = 0x31;
+        cpu.memory[0x4] = 0x47;
+
+        assert_eq!(cpu.read_byte_at_address(0x0), 0x34);
+        assert_eq!(cpu.read_byte_at_address(0x1), 0x11);
+        assert_eq!(cpu.read_byte_at_address(0x2), 0x24);
+        assert_eq!(cpu.read_byte_at_address(0x3), 0x31);
+        assert_eq!(cpu.read_byte_at_address(0x4), 0x47);
+
+        assert_eq!(cpu.read_word_at_address(0x0), 0x1134);
+        assert_eq!(cpu.read_word_at_address(0x1), 0x2411);
+
+        cpu.reg.pc = 0x0;
+        assert_eq!(cpu.read_byte_immediate(), 0x11);
+        cpu.reg.pc += 0x1;
+        assert_eq!(cpu.read_byte_immediate(), 0x24);
+        assert_eq!(cpu.read_word_immediate(), 0x3124);
+    }
+
+    #[test]
+    fn test_undocumented_opcodes() {
+        let mut io = NullIO;
+        let mut cpu = CPU::new();
+        cpu.reg[SP] = 0x1000;
+        // 0xdd CALL 0x0200, 0xd9 RET, 0xcb JMP 0x0300, 0x38 NOP
+        cpu.memory.load(0, &[0xdd, 0x00, 0x02, 0xcb, 0x00, 0x03]);
+        cpu.memory.load(0x200, &[0xd9]);
+        cpu.memory.load(0x300, &[0x38]);
+
+        assert_eq!(cpu.tick(&mut io), 17);
+        assert_eq!(cpu.reg[PC], 0x200);
+        assert_eq!(cpu.tick(&mut io), 10);
+        assert_eq!(cpu.reg[PC], 0x003);
+        assert_eq!(cpu.tick(&mut io), 10);
+        assert_eq!(cpu.reg[PC], 0x300);
+        assert_eq!(cpu.tick(&mut io), 4);
+        assert_eq!(cpu.reg[PC], 0x301);
+
+        cpu.strict = true;
+        cpu.reg[PC] = 0x300;
+        let error = cpu.try_tick(&mut io).unwrap_err();
+        assert_eq!(error, CpuError::UndocumentedOpcode { opcode: 0x38, address: 0x300 });
+        assert_eq!(error.to_string(), "undocumented opcode 38 (NOP alias) at 0300");
+        assert_eq!(cpu.reg[PC], 0x300);
+        cpu.reg[PC] = 0x003;
+        assert!(cpu.try_tick(&mut io).is_err());
+        cpu.reg[PC] = 0x200;
+        assert!(cpu.try_tick(&mut io).is_err());
+
+        // an interrupt taken first runs, and the opcode is stopped once it's back at PC
+        cpu.interrupts_enabled = true;
+        cpu.interrupt(1);
+        assert_eq!(cpu.try_tick(&mut io), Ok(11));
+        assert_eq!(cpu.reg[PC], 0x008);
+        cpu.reg[PC] = cpu.pop_word();
+        assert!(cpu.try_tick(&mut io).is_err());
+        assert_eq!(cpu.reg[PC], 0x200);
+    }
+
+    #[test]
+    fn test_interrupts() {
+        let mut io = NullIO;
+        let mut cpu = CPU::new();
+        cpu.reg[SP] = 0x1000;
+        // EI; NOP; NOP
+        cpu.memory.load(0x100, &[0xfb, 0x00, 0x00]);
+        cpu.reg[PC] = 0x100;
+
+        // nothing is taken until the instruction after EI has run
+        cpu.interrupt(2);
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x102);
+        assert_eq!(cpu.tick(&mut io), 11);
+        assert_eq!(cpu.reg[PC], 0x10);
+        assert_eq!(cpu.memory.view(0x0ffe, 0x0fff), &[0x02, 0x01]);
+        assert!(!cpu.interrupts_enabled);
+        assert_eq!(cpu.num_interrupts, 1);
+
+        // RST from memory pushes the address after it
+        cpu.memory.load(0x10, &[0xff]);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x38);
+        assert_eq!(cpu.memory.view(0x0ffc, 0x0ffd), &[0x11, 0x00]);
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut io = NullIO;
+        let mut cpu = CPU::new();
+        cpu.reg[SP] = 0x1000;
+        // EI; HLT; then the handler at 0x08 is RET
+        cpu.memory.load(0x100, &[0xfb, 0x76, 0x00]);
+        cpu.memory.load(0x08, &[0xc9]);
+        cpu.reg[PC] = 0x100;
+
+        cpu.tick(&mut io);
+        assert_eq!(cpu.tick(&mut io), 7);
+        assert!(cpu.halted);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x102);
+
+        cpu.interrupt(1);
+        cpu.tick(&mut io);
+        assert!(!cpu.halted);
+        assert_eq!(cpu.reg[PC], 0x08);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x102);
+    }
+
+    #[test]
+    fn test_interrupt_instructions() {
+        let mut io = NullIO;
+        let mut cpu = CPU::new();
+        cpu.reg[SP] = 0x1000;
+        cpu.reg[PC] = 0x200;
+        cpu.interrupts_enabled = true;
+
+        // a three byte CALL, as an 8259 sends
+        cpu.interrupt_request = Some(InterruptInstruction::Call(0x1234));
+        assert_eq!(cpu.tick(&mut io), 17);
+        assert_eq!(cpu.reg[PC], 0x1234);
+        assert_eq!(cpu.memory.view(0x0ffe, 0x0fff), &[0x00, 0x02]);
+
+        // any other instruction runs in place of the next one, here INR A
+        cpu.interrupts_enabled = true;
+        cpu.interrupt_request = Some(InterruptInstruction::Opcode(0x3c));
+        assert_eq!(cpu.tick(&mut io), 5);
+        assert_eq!(cpu.reg[A], 1);
+        assert_eq!(cpu.reg[PC], 0x1234);
+
+        // held while interrupts are disabled
+        cpu.interrupt(7);
+        cpu.memory.load(0x1234, &[0x00, 0xfb, 0x00]);
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x1237);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x38);
+    }
+
+    #[test]
+    fn test_8085_interrupt_instruction() {
+        let mut cpu = CPU::with_variant(Variant::I8085);
+        cpu.reg[PC] = 0x200;
+        cpu.reg[HL] = 0x8002;
+        cpu.interrupts_enabled = true;
+        // ARHL, which only the 8085 has
+        cpu.interrupt_request = Some(InterruptInstruction::Opcode(0x10));
+        cpu.tick(&mut NullIO);
+        assert_eq!(cpu.reg[HL], 0xc001);
+        assert_eq!(cpu.reg[PC], 0x200);
+    }
+
+    #[test]
+    #[should_panic(expected = "opcode c3, which needs 3 bytes")]
+    fn test_interrupt_instruction_with_operands() {
+        let mut cpu = CPU::new();
+        cpu.reg[PC] = 0x200;
+        cpu.interrupts_enabled = true;
+        // JMP would read its address from memory after PC rather than from the device
+        cpu.interrupt_request = Some(InterruptInstruction::Opcode(0xc3));
+        cpu.tick(&mut NullIO);
+    }
+
+    #[test]
+    fn test_write_word() {
+        let mut cpu = CPU::new();
+
+        cpu.write_word_to_memory(0, 0xAABB);
+        assert_eq!(cpu.memory[0], 0xBB);
+        assert_eq!(cpu.memory[1], 0xAA);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x80;
+        cpu.memory[1] = 0x81;
+        cpu.memory[2] = 0x82;
+        cpu.memory[3] = 0x83;
+        cpu.memory[4] = 0x84;
+        cpu.memory[5] = 0x85;
+        cpu.memory[6] = 0x87;
+        cpu.memory[7] = 0x86;
+
+        cpu.reg[A] = 0b1;
+        cpu.reg[B] = 0b1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b10);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), false);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[C] = 0x1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b11);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), true);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[D] = 0x1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b100);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), false);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[E] = 0x1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b101);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), true);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[H] = 0x1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b110);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), true);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[L] = 0x1;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b111);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), false);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0b1110);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), false);
+        assert_eq!(cpu.reg.get_flag(Parity), false);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(AuxCarry), false);
+
+        cpu.reg[A] = 0x0;
+        cpu.memory[0x1001] = 0xFF;
+        cpu.reg[HL] = 0x1001;
+
+        cpu.tick(&mut io);
+
+        assert_eq!(cpu.reg[A], 0xFF);
+
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Sign), true);
+        assert_eq!(cpu.reg.get_flag(Parity), true);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_pc_increment() { 
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.reg[A] = 0x1;
+        cpu.reg[B] = 0x1;
+
+        cpu.memory[0] = 0x80;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg.pc, 0x1);
+    }
+
+    #[test]
+    fn test_lxi() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0x1;
+        cpu.memory[1] = 0x35;
+        cpu.memory[2] = 0x76;
+
+        cpu.memory[3] = 0x11;
+        cpu.memory[4] = 0x35;
+        cpu.memory[5] = 0x76;
+
+        cpu.memory[6] = 0x21;
+        cpu.memory[7] = 0x35;
+        cpu.memory[8] = 0x76;
+
+        cpu.memory[9] = 0x31;
+        cpu.memory[10] = 0x35;
+        cpu.memory[11] = 0x76;
+
+        cpu.tick(&mut io);;
+        assert_eq!(cpu.reg[BC], 0x7635);
+
+        cpu.tick(&mut io);;
+        assert_eq!(cpu.reg[DE], 0x7635);
+
+        cpu.tick(&mut io);;
+        assert_eq!(cpu.reg[HL], 0x7635);
+
+        cpu.tick(&mut io);;
+        assert_eq!(cpu.reg.sp, 0x7635);
+    }
+
+    #[test]
+    fn test_stax() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[cpu.reg.pc] = 0x2;
+        cpu.reg[A] = 0x42;
+        cpu.reg[BC] = 0xF00F;
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.memory[0xF00F], 0x42);
+
+        cpu.memory[cpu.reg.pc] = 0x12;
+        cpu.reg[A] = 0x82;
+        cpu.reg[DE] = 0xEA3;
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.memory[0xEA3], 0x82);
+    }
+
+    #[test]
+    fn test_inx() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0x3;
+        cpu.memory[1] = 0x13;
+        cpu.memory[2] = 0x23;
+
+        cpu.reg[BC] = 0;
+        cpu.reg[DE] = 0;
+        cpu.reg[HL] = 0;
+
+        cpu.tick(&mut io);;;
+        cpu.tick(&mut io);;;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[BC], 0x1);
+        assert_eq!(cpu.reg[DE], 0x1);
+        assert_eq!(cpu.reg[HL], 0x1);
+    }
+
+    #[test]
+    fn test_inr() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0x4;
+        cpu.memory[1] = 0xC;
+        cpu.memory[2] = 0x14;
+        cpu.memory[3] = 0x1C;
+        cpu.memory[4] = 0x24;
+        cpu.memory[5] = 0x2C;
+        cpu.memory[6] = 0x3C;
+        cpu.memory[7] = 0x34; // we execute M last because it changes the l register
+        
+        for _ in 0..7 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[A], 1);
+        assert_eq!(cpu.reg[B], 1);
+        assert_eq!(cpu.reg[C], 1);
+        assert_eq!(cpu.reg[D], 1);
+        assert_eq!(cpu.reg[E], 1);
+        assert_eq!(cpu.reg[H], 1);
+        assert_eq!(cpu.reg[L], 1);
+
+        cpu.reg[HL] = 8;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.memory[8], 1);
+    }
+
+    #[test]
+    fn test_dcr() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.reg[A] = 2;
+        cpu.reg[B] = 2;
+        cpu.reg[C] = 2;
+        cpu.reg[D] = 2;
+        cpu.reg[E] = 2;
+        cpu.reg[H] = 2;
+        cpu.reg[L] = 2;
+        
+        cpu.memory[0] = 0x5;
+        cpu.memory[1] = 0xD;
+        cpu.memory[2] = 0x15;
+        cpu.memory[3] = 0x1D;
+        cpu.memory[4] = 0x25;
+        cpu.memory[5] = 0x2D;
+        cpu.memory[6] = 0x3D;
+        cpu.memory[7] = 0x35; // we execute M last because it changes the l register
+        
+        for _ in 0..7 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[A], 1);
+        assert_eq!(cpu.reg[B], 1);
+        assert_eq!(cpu.reg[C], 1);
+        assert_eq!(cpu.reg[D], 1);
+        assert_eq!(cpu.reg[E], 1);
+        assert_eq!(cpu.reg[H], 1);
+        assert_eq!(cpu.reg[L], 1);
+
+        cpu.memory[8] = 2;
+        cpu.reg[HL] = 8;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.memory[8], 1);
+    }
+
+    #[test]
+    fn test_mvi() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x06;
+        cpu.memory[1] = 0x10;
+        cpu.memory[2] = 0x0E;
+        cpu.memory[3] = 0x10;
+        cpu.memory[4] = 0x16;
+        cpu.memory[5] = 0x10;
+        cpu.memory[6] = 0x1E;
+        cpu.memory[7] = 0x10;
+        cpu.memory[8] = 0x26;
+        cpu.memory[9] = 0x10;
+        cpu.memory[10] = 0x2E;
+        cpu.memory[11] = 0x10;
+        cpu.memory[12] = 0x3E;
+        cpu.memory[13] = 0x10;
+        cpu.memory[14] = 0x36;
+        cpu.memory[15] = 0x10;
+
+        for _ in 0..7 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[A], 0x10);
+        assert_eq!(cpu.reg[B], 0x10);
+        assert_eq!(cpu.reg[C], 0x10);
+        assert_eq!(cpu.reg[D], 0x10);
+        assert_eq!(cpu.reg[E], 0x10);
+        assert_eq!(cpu.reg[H], 0x10);
+        assert_eq!(cpu.reg[L], 0x10);
+
+        cpu.reg[HL] = 0xFF;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.memory[cpu.reg[HL]], 0x10);
+    }
+
+    #[test]
+    fn test_dad() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x9;
+        cpu.memory[1] = 0x19;
+        cpu.memory[2] = 0x29;
+        cpu.reg[BC] = 0x1;
+        cpu.reg[DE] = 0x1;
+        cpu.reg[HL] = 0x1;
+
+        cpu.tick(&mut io);;;
+        cpu.tick(&mut io);;;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[HL], 0x6);
+
+        cpu.memory[3] = 0x39;
+        cpu.reg.sp = 1;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[HL], 0x7);
+        assert!(!cpu.reg.get_flag(Carry));
+
+        cpu.memory[4] = 0x29;
+        cpu.reg[HL] = 0x8001;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[HL], 0x0002);
+        assert!(cpu.reg.get_flag(Carry));
+    }
+
+    #[test]
+    fn test_ldax() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x0A;
+        cpu.memory[1] = 0x1A;
+
+        cpu.memory[0xF1] = 0x10;
+        cpu.memory[0xF2] = 0x20;
+
+        cpu.reg[BC] = 0xF1;
+        cpu.reg[DE] = 0xF2;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0x10);
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0x20);
+    }
+
+    #[test]
+    fn test_dcx() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x0B;
+        cpu.memory[1] = 0x1B;
+        cpu.memory[2] = 0x2B;
+        cpu.memory[3] = 0x3B;
+
+        cpu.reg[BC] = 1;
+        cpu.reg[DE] = 1;
+        cpu.reg[HL] = 1;
+        cpu.reg.sp = 1;
+
+        for _ in 0..4 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[BC], 0);
+        assert_eq!(cpu.reg[DE], 0);
+        assert_eq!(cpu.reg[HL], 0);
+        assert_eq!(cpu.reg.sp, 0);
+    }
+
+    #[test]
+    fn test_rlc() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x07;
+        cpu.reg[A] = 0b10101010;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b01010101);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.reg.pc = 0;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0b10101010);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_rrc() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x0F;
+        cpu.reg[A] = 0b10000001;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b11000000);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.reg.pc = 0;
+        cpu.tick(&mut io);;;
+        cpu.reg[A] = 0b01100000;
+         assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_ral() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x17;
+        cpu.reg[A] = 0b10101010;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b01010100);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.reg.pc = 0;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0b10101001);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_rar() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x1F;
+        cpu.reg[A] = 0b10000001;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b01000000);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.reg.pc = 0;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0b10100000);
+    }
+
+    #[test]
+    fn test_shld() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x22;
+        cpu.memory[1] = 0xAA;
+        cpu.memory[2] = 0xAA;
+        cpu.reg[L] = 0xCC;
+        cpu.reg[H] = 0xDD;
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.memory[0xAAAA], 0xCC);
+        assert_eq!(cpu.memory[0xAAAB], 0xDD);
+    }
+
+    #[test]
+    fn test_lhld() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x2a;
+        cpu.memory[1] = 0xAA;
+        cpu.memory[2] = 0xAA;
+        cpu.memory[0xAAAA] = 0xEE;
+        cpu.memory[0xAAAB] = 0xFF;
+        cpu.reg[L] = 0xCC;
+        cpu.reg[H] = 0xDD;
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[L], 0xEE);
+        assert_eq!(cpu.reg[H], 0xFF);
+    }
+
+    #[test]
+    fn test_cma() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x2f;
+        cpu.reg[A] = 0b00000001;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b11111110);
+    }
+
+    #[test]
+    fn test_sta() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x32;
+        cpu.memory[1] = 0xBB;
+        cpu.memory[2] = 0xAA;
+        cpu.reg[A] = 0xFF;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.memory[0xAABB], 0xFF);
+    }
+
+    #[test]
+    fn test_inxsp() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x33;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg.sp, 1);
+    }
+
+    #[test]
+    fn test_stc() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x37;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+    }
+
+    #[test]
+    fn test_lda() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x3a;
+        cpu.memory[1] = 0xFF;
+        cpu.memory[2] = 0x00;
+        cpu.memory[0x00FF] = 0xAA;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0xAA);
+    }
+
+    #[test]
+    fn test_cmc() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        cpu.memory[0] = 0x3F;
+        cpu.memory[1] = 0x3F;
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_mov() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        let mut index = 0;
+
+        for x in 0x40..0x80 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        let registers = [B, C, D, E, H, L, M, A];
+
+        for &dest_register in registers.iter() {
+            for &source_register in registers.iter() {
+                // println!("Source: {:?}, Dest: {:?}", source_register, dest_register);
+                if source_register == M {
+                    cpu.reg[HL] = 0xFFFE;
+                    cpu.memory[cpu.reg[HL]] = 0x1;
+                    // println!("Setting memory at {:04X} to 1", cpu.reg[HL]);
+                }
+                if source_register != M {
+                    cpu.reg[source_register] = 0x1;
+                    // println!("Setting SR to 1");
+                    // println!("SR pre-tick: {:?}", cpu.reg[source_register]);
+                }
+
+                cpu.tick(&mut io);;;
+
+                if source_register != M && dest_register != M {
+                    if source_register == M {
+                    // println!("DR post-tick: {:?}", cpu.reg[dest_register]);
+                    // println!("M@HL({:04X}): {} vs V->{:?}: {}", cpu.reg[HL], cpu.memory[cpu.reg[HL]], dest_register, cpu.reg[dest_register]);
+                    assert_eq!(cpu.memory[0xFFFE], cpu.reg[dest_register]);
+                    } 
+                    else if dest_register == M {
+                        // println!("SR post-tick: {:?}", cpu.reg[source_register]);
+                        // println!("Memory at HL({:?}): {:04X}", cpu.reg[HL], cpu.memory[cpu.reg[HL]]);
+                        assert_eq!(cpu.memory[cpu.reg[HL]], cpu.reg[source_register]);
+                        
+                    } 
+                    else {
+                        // println!("DR post-tick: {:?}", cpu.reg[dest_register]);
+                        // println!("SR post-tick: {:?}", cpu.reg[source_register]);
+                        assert_eq!(cpu.reg[dest_register], cpu.reg[source_register]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_adc() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0x80;
+        cpu.memory[1] = 0x88;
+        cpu.memory[2] = 0x89;
+        cpu.memory[3] = 0x8a;
+        cpu.memory[4] = 0x8b;
+        cpu.memory[5] = 0x8c;
+        cpu.memory[6] = 0x8d;
+        cpu.memory[7] = 0x8e;
+        cpu.memory[8] = 0x8f;
+
+        cpu.reg[A] = 0b11111111;
+        cpu.reg[B] = 0b00000001;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b00000000);
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b00000010);
+
+        cpu.reg[C] = 1;
+        cpu.reg[D] = 1;
+        cpu.reg[E] = 1;
+        cpu.reg[H] = 1;
+        cpu.reg[L] = 1;
+        cpu.memory[cpu.reg[HL]] = 1;
+
+        for _ in 0..6 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[A], 0b1000);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0b10000);
+    }
+
+    #[test]
+    fn test_sub() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        let mut index = 0;
+    
+        for x in 0x90..0x98 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        cpu.reg[A] = 0b0000000;
+        cpu.reg[B] = 0b0000001;
+        cpu.reg[C] = 0b0000001;
+        cpu.reg[D] = 0b0000001;
+        cpu.reg[E] = 0b0000001;
+        cpu.reg[H] = 0b0000001;
+        cpu.reg[L] = 0b0000001;
+        cpu.memory[cpu.reg[HL]] = 0b0000001;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b11111111);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        for _ in 0..6 {
+            cpu.tick(&mut io);;;
+        }
+        
+        assert_eq!(cpu.reg[A], 0b11111111 - 6);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0);
+    }
+
+    #[test]
+    fn test_sbb() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        let mut index = 0;
+    
+        for x in 0x98..0xa0 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        cpu.reg[A] = 0b0000000;
+        cpu.reg[B] = 0b0000001;
+        cpu.reg[C] = 0b0000001;
+        cpu.reg[D] = 0b0000001;
+        cpu.reg[E] = 0b0000001;
+        cpu.reg[H] = 0b0000001;
+        cpu.reg[L] = 0b0000001;
+        cpu.memory[cpu.reg[HL]] = 0b0000001;
+
+        cpu.tick(&mut io);;;
+        assert_eq!(cpu.reg[A], 0b11111111);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        for _ in 0..6 {
+            cpu.tick(&mut io);;;
+        }
+        
+        assert_eq!(cpu.reg[A], 0b11111111 - 7);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[A], 0);
+    }
+
+    #[test]
+    fn test_ana() {
+        let registers = [A, B, C, D, E, H, L];
+
+        let values = [
+            0b11111111, // Initial
+            0b11111110, // B
+            0b11111100, // C
+            0b11111000, // D
+            0b11110000, // E
+            0b11100000, // H
+            0b11000000, // L
+            0b10000000, // M
+        ];
+
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+        let mut index = 0;
+        
+        for x in 0xa0..0xa8 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        for x in 0..7 {
+            cpu.reg[registers[x]] = values[x];
+        }
+
+        index = 1;
+        for x in 0xa0..0xa8 {
+            if x == 0xa6 {
+                cpu.reg[HL] = 0xDDDD;
+                cpu.memory[cpu.reg[HL]] = 0b10000000;
+            }
+            
+            cpu.tick(&mut io);;;
+
+            if x == 0xa6 {
+                assert_eq!(cpu.reg[A], 0b10000000);
+            }
+            else {
+                assert_eq!(cpu.reg[A], values[(index) as usize]);
+            }
+            if index != 7 {index += 1};
+        }
+    }
+
+    #[test]
+    fn test_xra() {
+        let registers = [A, B, C, D, E, H, L];
+
+        let values = [
+            0b11111111, // Initial
+            0b11111110, // B
+            0b00000011, // C
+            0b00000110, // D
+            0b00001100, // E
+            0b00011000, // H
+            0b00110000, // L
+            0b01100000, // M
+        ];
+
+        let mut results = Vec::new();
+
+        results.push(values[0] ^ values[1]);
+
+        for x in 1..7 {
+            results.push(results[x - 1] ^ values[x + 1]);
+        }
+
+        results.push(0b00000000);
+
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        let mut index = 0;
+        for x in 0xa8..0xb0 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        for x in 0..7 {
+            cpu.reg[registers[x as usize]] = values[x as usize];
+        }
+
+        index = 0;
+        for x in 0xa8..0xb0 {
+            if x == 0xae {
+                cpu.reg[HL] = 0xDDDD;
+                cpu.memory[cpu.reg[HL]] = 0b01100000;
+            }
+
+            cpu.tick(&mut io);;;
+            
+            assert_eq!(cpu.reg[A], results[index as usize]);
+
+            if index != 7 {index += 1};
+        }
+    }
+
+    #[test]
+    fn test_ora() {
+        let registers = [A, B, C, D, E, H, L];
+
+        let values = [
+            0b11111111, // Initial
+            0b11111110, // B
+            0b00000011, // C
+            0b00000110, // D
+            0b00001100, // E
+            0b00011000, // H
+            0b00110000, // L
+            0b01100000, // M
+        ];
+
+        let mut results = Vec::new();
+
+        results.push(values[0] | values[1]);
+
+        for x in 1..7 {
+            results.push(results[x - 1] | values[x + 1]);
+        }
+
+        results.push(0b11111111);
+
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        let mut index = 0;
+        for x in 0xb0..0xb8 {
+            cpu.memory[index] = x;
+            index += 1;
+        }
+
+        for x in 0..7 {
+            cpu.reg[registers[x as usize]] = values[x as usize];
+        }
+
+        index = 0;
+        for x in 0xa8..0xb0 {
+            if x == 0xae {
+                cpu.reg[HL] = 0xDDDD;
+                cpu.memory[cpu.reg[HL]] = 0b01100000;
+            }
+
+            cpu.tick(&mut io);;;
+            
+            assert_eq!(cpu.reg[A], results[index as usize]);
+
+            if index != 7 {index += 1};
+        }
+    }
+
+    #[test]
+    fn test_cmp() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xb8;
+        cpu.memory[1] = 0xb9;
+        cpu.memory[2] = 0xba;
+
+        cpu.reg[A] = 0b00001111;
+        cpu.reg[B] = 0b00001111; // Zero set
+        cpu.reg[C] = 0b00011111; // Carry set
+        cpu.reg[D] = 0b00000001; // Both reset
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg.get_flag(Zero), true);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xc5;
+        cpu.memory[1] = 0xd1;
+        cpu.reg[BC] = 0xABBA;
+        cpu.reg[SP] = 0xDDDD;
+
+        cpu.tick(&mut io);;;
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[BC], cpu.reg[DE]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_trace_line() {
+        let mut cpu = CPU::new();
+        cpu.memory.load(0x100, &[0x3e, 0x12]);
+        cpu.reg[PC] = 0x100;
+        cpu.reg[SP] = 0xff00;
+        cpu.reg[HL] = 0x1234;
+        cpu.reg.set_flag(Zero, true);
+        assert_eq!(cpu.trace_line(42),
+                   "PC:0100 OP:3e A:00 F:42 B:00 C:00 D:00 E:00 H:12 L:34 SP:ff00 CYC:42 ; MVI A, $12");
+    }
+
+    #[test]
+    fn test_push_pop_psw() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+        cpu.reg[SP] = 0x1000;
+
+        // XRA A; PUSH PSW leaves Z and P set, bit 1 set, and the rest clear
+        cpu.memory.load(0, &[0xaf, 0xf5]);
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.memory.view(0x0ffe, 0x0fff), &[0x46, 0x00]);
+
+        // POP PSW keeps only the five flags, and they are pushed back the same way
+        cpu.memory.load(0x0ffe, &[0xff, 0x12]);
+        cpu.memory.load(2, &[0xf1, 0xf5]);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[A], 0x12);
+        assert_eq!(cpu.reg.get_flags_as_u8(), 0xd5);
+        assert!(cpu.reg.get_flag(AuxCarry));
+        cpu.tick(&mut io);
+        assert_eq!(cpu.memory.view(0x0ffe, 0x0fff), &[0xd7, 0x12]);
+
+        // an 8085 keeps V and K in bits 1 and 5
+        let mut cpu = CPU::with_variant(Variant::I8085);
+        cpu.reg[SP] = 0x0ffe;
+        cpu.memory.load(0x0ffe, &[0xff, 0x12]);
+        cpu.memory.load(0, &[0xf1, 0xf5]);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg.get_flags_as_u8(), 0xf7);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.memory.view(0x0ffe, 0x0fff), &[0xf7, 0x12]);
+    }
+
+    /// Runs a CP/M program with just enough of the BDOS for the diagnostics to print their
+    /// results, returning what they printed.
+    fn run_cpm_program(program: &[u8], max_instructions: u64) -> String {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+        let mut output = String::new();
+        cpu.memory.load(0x100, program);
+        // BDOS calls return straight away, and the top of memory is just below it
+        cpu.memory.load(0x0005, &[0xc9]);
+        cpu.memory.load(0x0006, &[0x00, 0xff]);
+        cpu.reg[SP] = 0xff00;
+        cpu.reg[PC] = 0x100;
+
+        for _ in 0..max_instructions {
+            match cpu.reg[PC] {
+                0x0000 => return output,
+                0x0005 => match cpu.reg[C] {
+                    2 => output.push(cpu.reg[E] as char),
+                    9 => {
+                        let mut address = cpu.reg[DE];
+                        while cpu.memory[address] != b'$' {
+                            output.push(cpu.memory[address] as char);
+                            address = address.wrapping_add(1);
+                        }
+                    },
+                    _ => {},
+                },
+                _ => {},
+            }
+            cpu.tick(&mut io);
+        }
+        panic!("Still running after {} instructions, printed: {}", max_instructions, output);
+    }
+
+    #[test]
+    fn test_cpudiag() {
+        let program = std::fs::read("../ROMS/cpudiag.bin").unwrap();
+        let output = run_cpm_program(&program, 1_000_000);
+        assert!(output.contains("CPU IS OPERATIONAL"), "{}", output);
+    }
+
+    /* Frank Cringle's exerciser runs every instruction over a spread of operands and compares a
+       CRC of the results and the pushed flags with the ones recorded on a real 8080. It takes a
+       few billion instructions and isn't distributed with this repository, so put 8080EXM.COM in
+       ROMS and run `cargo test --release -- --ignored` to check against it. */
+    #[test]
+    #[ignore]
+    fn test_8080exm() {
+        let program = match std::fs::read("../ROMS/8080EXM.COM") {
+            Ok(program) => program,
+            Err(e) => panic!("Could not read ROMS/8080EXM.COM: {}", e),
+        };
+        let output = run_cpm_program(&program, 50_000_000_000);
+        assert!(!output.contains("ERROR"), "{}", output);
+        assert!(output.contains("Tests complete"), "{}", output);
+    }
+
+    #[test]
+    fn test_jmp() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xc3;
+        cpu.memory[1] = 0xBB;
+        cpu.memory[2] = 0xAA;
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[PC], 0xAABB);
+    }
+
+    #[test]
+    fn test_jnz() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xc2;
+        cpu.memory[1] = 0xBB;
+        cpu.memory[2] = 0xAA;
+        cpu.memory[3] = 0xc2;
+        cpu.memory[4] = 0xBB;
+        cpu.memory[5] = 0xAA;
+        cpu.reg.set_flag(Zero, true);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[PC], 3);
+
+        cpu.reg.set_flag(Zero, false);
+
+        cpu.tick(&mut io);;;
+
+        assert_eq!(cpu.reg[PC], 0xAABB);
+    }
+
+    // TODO: Add test for jz
+
+    #[test]
+    fn test_call_ret() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.reg[SP] = 0x100;
+
+        cpu.reg[B] = 1;
+        cpu.reg[C] = 2;
+
+        cpu.memory[0] = 0xcd;
+        cpu.memory[1] = 0xBB;
+        cpu.memory[2] = 0xAA;
+        cpu.memory[0xAABB] = 0x80;
+        cpu.memory[0xAABC] = 0xc9;
+        cpu.memory[3] = 0x81;
+
+        for _ in 0..4 {
+            cpu.tick(&mut io);;;
+        }
+
+        assert_eq!(cpu.reg[PC], 0x4);
+        assert_eq!(cpu.reg[A], 0x3);
+    }
+
+    #[test]
+    fn test_pchl() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xe9;
+        cpu.reg[HL] = 0xBB;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[PC], 0xBB);
+    }
+
+    #[test]
+    fn test_sphl() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xf9;
+        cpu.reg[HL] = 0xFF;
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[SP], 0xFF);
+    }
+
+    #[test]
+    fn test_xchg() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xeb;
+        cpu.reg[DE] = 0xAA;
+        cpu.reg[HL] = 0xBB;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[DE], 0xBB);
+        assert_eq!(cpu.reg[HL], 0xAA);
+    }
+
+    #[test]
+    fn test_xthl() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xe3;
+        cpu.reg[SP] = 0x100;
+        cpu.memory[cpu.reg[SP]] = 0xAA;
+        cpu.memory[cpu.reg[SP] + 1] = 0xBB;
+        cpu.reg[H] = 0xEE;
+        cpu.reg[L] = 0xFF;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[L], 0xAA);
+        assert_eq!(cpu.reg[H], 0xBB);
+
+        assert_eq!(cpu.memory[cpu.reg[SP]], 0xFF);
+        assert_eq!(cpu.memory[cpu.reg[SP] + 1], 0xEE);
+    }
+
+    #[test]
+    fn test_cpi() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.memory[0] = 0xfe;
+        cpu.memory[1] = 0x1;
+        cpu.reg[A] = 0x1;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(Zero), true);
+
+        cpu.memory[2] = 0xfe;
+        cpu.memory[3] = 0x2;
+        cpu.reg[A] = 0x1;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+
+        cpu.memory[4] = 0xfe;
+        cpu.memory[5] = 0x1;
+        cpu.reg[A] = 0x2;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+        assert_eq!(cpu.reg.get_flag(Zero), false);
+    }
+
+    #[test]
+    fn test_aci() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.reg.set_flag(Carry, true);
+        cpu.reg[A] = 0xF5;
+
+        cpu.memory[0] = 0xCE;
+        cpu.memory[1] = 0xA;
+        cpu.memory[2] = 0xCE;
+        cpu.memory[3] = 0xA;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[A], 0xB);
+    }
+
+    #[test]
+    fn test_sui() {
+        let mut cpu = CPU::new();
+        let mut io = NullIO;
+
+
+        cpu.reg[A] = 0xB;
+
+        cpu.memory[0] = 0xD6;
+        cpu.memory[1] = 0xC;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[A], 0xFF);
+        assert_eq!(cpu.reg.get_flag(Carry), true);
+
+        cpu.memory[2] = 0xD6;
+        cpu.memory[3] = 0xF;
+
+        cpu.tick(&mut io);;
+
+        assert_eq!(cpu.reg[A], 0xF0); 
+        assert_eq!(cpu.reg.get_flag(Carry), false);
+    }
+}
\ No newline at end of file
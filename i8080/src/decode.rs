@@ -0,0 +1,300 @@
+use super::cpu::{Byte, Word};
+use super::i8085::Variant;
+use super::register::Flag::{self, Carry, Parity, Sign, Zero};
+use super::register::Reg16::{self, BC, DE, HL, PSW, SP};
+use super::register::Reg8::{self, A, B, C, D, E, H, L, M};
+use super::timing;
+
+/* Every opcode is decoded once, when the program is built, into what it does, how long it is and
+   how many states it takes, so running an instruction is a lookup and a match over a few dozen
+   kinds of instruction rather than 256 opcodes. The 8080's encoding does most of the work: MOV is
+   01DDDSSS and the ALU block 10AAASSS, with the register fields numbered B C D E H L M A, so those
+   128 opcodes come down to two kinds with their registers already picked out. */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Alu {
+    Add,
+    Adc,
+    Sub,
+    Sbb,
+    Ana,
+    Xra,
+    Ora,
+    Cmp,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Condition {
+    Always,
+    /// When the flag is set (true) or clear.
+    If(Flag, bool),
+}
+
+/// An instruction, with M standing for the byte HL points at.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum Op {
+    Nop,
+    Lxi(Reg16),
+    Stax(Reg16),
+    Ldax(Reg16),
+    Shld,
+    Lhld,
+    Sta,
+    Lda,
+    Inx(Reg16),
+    Dcx(Reg16),
+    Dad(Reg16),
+    Inr(Reg8),
+    Dcr(Reg8),
+    Mvi(Reg8),
+    Rlc,
+    Rrc,
+    Ral,
+    Rar,
+    Daa,
+    Cma,
+    Stc,
+    Cmc,
+    Mov(Reg8, Reg8),
+    Hlt,
+    Alu(Alu, Reg8),
+    AluImmediate(Alu),
+    Jump(Condition),
+    Call(Condition),
+    Return(Condition),
+    Rst(Word),
+    Push(Reg16),
+    Pop(Reg16),
+    Out,
+    In,
+    Xthl,
+    Xchg,
+    Pchl,
+    Sphl,
+    Di,
+    Ei,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Decoded {
+    pub op: Op,
+    pub length: u8,
+    /// T-states, with a conditional instruction's condition failing.
+    pub cycles: u8,
+    pub cycles_taken: u8,
+}
+
+impl Decoded {
+    pub fn time(&self, taken: bool) -> Word {
+        if taken { self.cycles_taken as Word } else { self.cycles as Word }
+    }
+}
+
+/// The register a three bit register field picks.
+pub const fn register(field: Byte) -> Reg8 {
+    match field & 0b111 {
+        0 => B,
+        1 => C,
+        2 => D,
+        3 => E,
+        4 => H,
+        5 => L,
+        6 => M,
+        _ => A,
+    }
+}
+
+/// The pair a two bit register pair field picks, with PUSH and POP using PSW in place of SP.
+const fn pair(field: Byte, stack: bool) -> Reg16 {
+    match field & 0b11 {
+        0 => BC,
+        1 => DE,
+        2 => HL,
+        _ if stack => PSW,
+        _ => SP,
+    }
+}
+
+const fn alu(field: Byte) -> Alu {
+    match field & 0b111 {
+        0 => Alu::Add,
+        1 => Alu::Adc,
+        2 => Alu::Sub,
+        3 => Alu::Sbb,
+        4 => Alu::Ana,
+        5 => Alu::Xra,
+        6 => Alu::Ora,
+        _ => Alu::Cmp,
+    }
+}
+
+/// NZ Z NC C PO PE P M.
+const fn condition(field: Byte) -> Condition {
+    let flag = match (field >> 1) & 0b11 {
+        0 => Zero,
+        1 => Carry,
+        2 => Parity,
+        _ => Sign,
+    };
+    Condition::If(flag, field & 1 != 0)
+}
+
+/// What an opcode does on an 8080, undocumented ones included.
+pub const fn decode(opcode: Byte) -> Op {
+    let (y, z) = ((opcode >> 3) & 0b111, opcode & 0b111);
+    let p = y >> 1;
+    match opcode >> 6 {
+        0b00 => match z {
+            0 => Op::Nop,
+            1 if y & 1 == 0 => Op::Lxi(pair(p, false)),
+            1 => Op::Dad(pair(p, false)),
+            2 => match y {
+                0 | 2 => Op::Stax(pair(p, false)),
+                1 | 3 => Op::Ldax(pair(p, false)),
+                4 => Op::Shld,
+                5 => Op::Lhld,
+                6 => Op::Sta,
+                _ => Op::Lda,
+            },
+            3 if y & 1 == 0 => Op::Inx(pair(p, false)),
+            3 => Op::Dcx(pair(p, false)),
+            4 => Op::Inr(register(y)),
+            5 => Op::Dcr(register(y)),
+            6 => Op::Mvi(register(y)),
+            _ => match y {
+                0 => Op::Rlc,
+                1 => Op::Rrc,
+                2 => Op::Ral,
+                3 => Op::Rar,
+                4 => Op::Daa,
+                5 => Op::Cma,
+                6 => Op::Stc,
+                _ => Op::Cmc,
+            },
+        },
+        0b01 if opcode == 0x76 => Op::Hlt,
+        0b01 => Op::Mov(register(y), register(z)),
+        0b10 => Op::Alu(alu(y), register(z)),
+        _ => match z {
+            0 => Op::Return(condition(y)),
+            1 => match y {
+                1 | 3 => Op::Return(Condition::Always),
+                5 => Op::Pchl,
+                7 => Op::Sphl,
+                _ => Op::Pop(pair(p, true)),
+            },
+            2 => Op::Jump(condition(y)),
+            3 => match y {
+                0 | 1 => Op::Jump(Condition::Always),
+                2 => Op::Out,
+                3 => Op::In,
+                4 => Op::Xthl,
+                5 => Op::Xchg,
+                6 => Op::Di,
+                _ => Op::Ei,
+            },
+            4 => Op::Call(condition(y)),
+            5 if y & 1 == 0 => Op::Push(pair(p, true)),
+            5 => Op::Call(Condition::Always),
+            6 => Op::AluImmediate(alu(y)),
+            _ => Op::Rst((y << 3) as Word),
+        },
+    }
+}
+
+const fn length(op: Op) -> u8 {
+    match op {
+        Op::Lxi(_) | Op::Shld | Op::Lhld | Op::Sta | Op::Lda | Op::Jump(_) | Op::Call(_) => 3,
+        Op::Mvi(_) | Op::AluImmediate(_) | Op::Out | Op::In => 2,
+        _ => 1,
+    }
+}
+
+const fn build(variant: Variant) -> [Decoded; 256] {
+    let mut table = [Decoded { op: Op::Nop, length: 1, cycles: 0, cycles_taken: 0 }; 256];
+    let mut i = 0;
+    while i < 256 {
+        let opcode = i as Byte;
+        let op = decode(opcode);
+        table[i] = Decoded {
+            op,
+            length: length(op),
+            cycles: timing::cycles(variant, opcode, false) as u8,
+            cycles_taken: timing::cycles(variant, opcode, true) as u8,
+        };
+        i += 1;
+    }
+    table
+}
+
+static TABLE_8080: [Decoded; 256] = build(Variant::I8080);
+static TABLE_8085: [Decoded; 256] = build(Variant::I8085);
+
+/// Every opcode decoded, with the timings of the chip given.
+pub fn table(variant: Variant) -> &'static [Decoded; 256] {
+    match variant {
+        Variant::I8080 => &TABLE_8080,
+        Variant::I8085 => &TABLE_8085,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::disassembler::op_name;
+
+    #[test]
+    fn test_register_fields() {
+        match decode(0x46) {
+            Op::Mov(B, M) => {},
+            other => panic!("0x46 decoded as {:?}", other),
+        }
+        match decode(0x7b) {
+            Op::Mov(A, E) => {},
+            other => panic!("0x7b decoded as {:?}", other),
+        }
+        match decode(0x9e) {
+            Op::Alu(Alu::Sbb, M) => {},
+            other => panic!("0x9e decoded as {:?}", other),
+        }
+        match decode(0xf5) {
+            Op::Push(PSW) => {},
+            other => panic!("0xf5 decoded as {:?}", other),
+        }
+        match decode(0xe8) {
+            Op::Return(Condition::If(Parity, true)) => {},
+            other => panic!("0xe8 decoded as {:?}", other),
+        }
+        match decode(0xff) {
+            Op::Rst(0x38) => {},
+            other => panic!("0xff decoded as {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_lengths_match_the_disassembler() {
+        // the disassembler shows an immediate byte as $xx and an address or word as $xxxx
+        for opcode in 0..=255u8 {
+            let name = op_name(opcode, 0, 0);
+            let expected = match name.find('$') {
+                Some(i) => 1 + (name.len() - i - 1) as u8 / 2,
+                None => 1,
+            };
+            assert_eq!(table(Variant::I8080)[opcode as usize].length, expected, "{:02x} {}", opcode, name);
+        }
+    }
+
+    #[test]
+    fn test_timings() {
+        for &variant in [Variant::I8080, Variant::I8085].iter() {
+            for opcode in 0..=255u8 {
+                let decoded = table(variant)[opcode as usize];
+                assert_eq!(decoded.time(false), timing::cycles(variant, opcode, false));
+                assert_eq!(decoded.time(true), timing::cycles(variant, opcode, true));
+            }
+        }
+    }
+}
@@ -1,6 +1,27 @@
-use crate::i8080::cpu::Byte;
-use crate::i8080::cpu::Word;
+use super::cpu::Byte;
+#[cfg(feature = "alloc")]
+use super::cpu::Word;
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
 
+/// Whether an opcode is one Intel left out of the 8080's documentation. Each one does the same as
+/// a documented instruction: the eight in the 00-38 column are NOPs, 0xcb is JMP, 0xd9 is RET, and
+/// 0xdd, 0xed and 0xfd are CALL.
+pub fn is_undocumented(opcode: Byte) -> bool {
+    matches!(opcode, 0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 | 0xcb | 0xd9 | 0xdd | 0xed | 0xfd)
+}
+
+/// The documented instruction an undocumented opcode runs as.
+pub fn undocumented_alias(opcode: Byte) -> &'static str {
+    match opcode {
+        0xcb => "JMP",
+        0xd9 => "RET",
+        0xdd | 0xed | 0xfd => "CALL",
+        _ => "NOP",
+    }
+}
+
+#[cfg(feature = "alloc")]
 pub fn op_name(opcode: Byte, following_byte: Byte, following_word: Word) -> String {
     match opcode {
         0x00 | 0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => "NOP".into(),
@@ -215,7 +236,7 @@ pub fn op_name(opcode: Byte, following_byte: Byte, following_word: Word) -> Stri
         0xda => format!("JC ${:04x}", following_word),
         0xdb => format!("IN ${:02x}", following_byte),
         0xdc => format!("CC ${:04x}", following_word),
-        0xde => "SBI D8".into(),
+        0xde => format!("SBI ${:02x}", following_byte),
         0xdf => "RST 3".into(),
         0xe0 => "RPO".into(),
         0xe1 => "POP H".into(),
@@ -250,14 +271,35 @@ pub fn op_name(opcode: Byte, following_byte: Byte, following_word: Word) -> Stri
     }
 }
 
-#[cfg(test)]
+/// Names opcodes the way an 8085 runs them, including the instructions it added in the 8080's
+/// unused opcodes.
+#[cfg(feature = "alloc")]
+pub fn op_name_8085(opcode: Byte, following_byte: Byte, following_word: Word) -> String {
+    match opcode {
+        0x08 => "DSUB".into(),
+        0x10 => "ARHL".into(),
+        0x18 => "RDEL".into(),
+        0x20 => "RIM".into(),
+        0x28 => format!("LDHI ${:02x}", following_byte),
+        0x30 => "SIM".into(),
+        0x38 => format!("LDSI ${:02x}", following_byte),
+        0xcb => "RSTV".into(),
+        0xd9 => "SHLX".into(),
+        0xdd => format!("JNK ${:04x}", following_word),
+        0xed => "LHLX".into(),
+        0xfd => format!("JK ${:04x}", following_word),
+        _ => op_name(opcode, following_byte, following_word),
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
-    use crate::i8080;
+    use crate::cpu::CPU;
 
     #[test]
     fn test_disassembler() {
-        let mut cpu = i8080::cpu::CPU::new();
+        let mut cpu = CPU::new();
 
         for x in 0..0xFE {
             cpu.memory[x] = x as u8;
@@ -267,4 +309,30 @@ mod tests {
             op_name(x, 0, 0);
         }
     }
+
+    #[test]
+    fn test_undocumented_names() {
+        assert_eq!(op_name(0x38, 0, 0), "NOP");
+        assert_eq!(op_name(0xcb, 0, 0x1234), op_name(0xc3, 0, 0x1234));
+        assert_eq!(op_name(0xd9, 0, 0), op_name(0xc9, 0, 0));
+        for &opcode in &[0xdd, 0xed, 0xfd] {
+            assert_eq!(op_name(opcode, 0, 0x1234), op_name(0xcd, 0, 0x1234));
+        }
+        assert_eq!((0..=0xff).filter(|&opcode| is_undocumented(opcode)).count(), 12);
+    }
+
+    #[test]
+    fn test_undocumented_aliases() {
+        for opcode in (0..=0xff).filter(|&opcode| is_undocumented(opcode)) {
+            assert!(op_name(opcode, 0, 0).starts_with(undocumented_alias(opcode)), "{:02x}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_8085_names() {
+        assert_eq!(op_name_8085(0x20, 0, 0), "RIM");
+        assert_eq!(op_name_8085(0x28, 0x12, 0), "LDHI $12");
+        assert_eq!(op_name_8085(0xfd, 0, 0x1234), "JK $1234");
+        assert_eq!(op_name_8085(0x3e, 0x12, 0), op_name(0x3e, 0x12, 0));
+    }
 }
\ No newline at end of file
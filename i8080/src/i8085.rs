@@ -0,0 +1,391 @@
+use super::cpu::{Byte, Word, CPU};
+use super::decode::register;
+use super::timing;
+use super::register::Reg16::{BC, DE, HL, PC, SP};
+use super::register::Reg8::{A, H, L};
+use super::register::Flag::{Carry, Overflow, Parity, Sign, Underflow, Zero};
+
+/* The 8085 runs the 8080's instruction set with a few additions: RIM and SIM in two of the
+   unused opcodes for its serial line and interrupt masks, four more interrupt inputs with fixed
+   vectors, ten undocumented instructions in the remaining holes (found by Dehnhardt and Sorensen
+   in 1979), two undocumented flags they use, and its own instruction timings. */
+
+/// Which chip a `CPU` is.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Variant {
+    I8080,
+    I8085,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interrupt8085 {
+    /// Non-maskable, edge triggered, vector 0x24.
+    Trap,
+    /// Edge triggered into a flip-flop, vector 0x3C.
+    Rst75,
+    /// Level triggered, vector 0x34.
+    Rst65,
+    /// Level triggered, vector 0x2C.
+    Rst55,
+}
+
+#[derive(Debug, Default)]
+pub struct State8085 {
+    /// The interrupt masks set by SIM: bit 2 for RST 7.5, bit 1 for 6.5, bit 0 for 5.5.
+    pub masks: Byte,
+    pub rst75_pending: bool,
+    pub rst65: bool,
+    pub rst55: bool,
+    trap: bool,
+    trap_pending: bool,
+    /// The interrupt enable from before a TRAP, which the next RIM reports.
+    ie_before_trap: Option<bool>,
+    /// Serial input and output data lines.
+    pub sid: bool,
+    pub sod: bool,
+}
+
+impl CPU {
+    /// Raises or lowers one of the 8085's interrupt inputs.
+    pub fn set_interrupt_line(&mut self, line: Interrupt8085, active: bool) {
+        let state = &mut self.i8085;
+        match line {
+            Interrupt8085::Trap => {
+                state.trap_pending |= active && !state.trap;
+                state.trap = active;
+            },
+            Interrupt8085::Rst75 => state.rst75_pending |= active,
+            Interrupt8085::Rst65 => state.rst65 = active,
+            Interrupt8085::Rst55 => state.rst55 = active,
+        }
+    }
+
+    /// Takes the highest priority 8085 interrupt that is pending and not masked, returning the
+    /// cycles it took.
+    pub(super) fn service_8085_interrupt(&mut self, accepting: bool) -> Option<u64> {
+        let state = &mut self.i8085;
+        let vector = if state.trap_pending {
+            state.trap_pending = false;
+            state.ie_before_trap = Some(self.interrupts_enabled);
+            0x24
+        } else if !accepting {
+            return None;
+        } else if state.rst75_pending && state.masks & 0b100 == 0 {
+            state.rst75_pending = false;
+            0x3C
+        } else if state.rst65 && state.masks & 0b010 == 0 {
+            0x34
+        } else if state.rst55 && state.masks & 0b001 == 0 {
+            0x2C
+        } else {
+            return None;
+        };
+
+        self.interrupts_enabled = false;
+        self.push_word(self.reg[PC]);
+        self.reg[PC] = vector;
+        Some(timing::interrupt_cycles(Variant::I8085))
+    }
+
+    /// Works out V and K the way the 8085 does for an instruction about to be executed.
+    pub(super) fn update_8085_flags(&mut self, opcode: Byte) {
+        let carry = self.reg.get_flag(Carry) as Byte;
+        let operand = self.get_operand(register(opcode));
+        let target = self.get_operand(register(opcode >> 3));
+        let immediate = self.memory[self.reg[PC].wrapping_add(1)];
+
+        // (first operand, second operand as added, carry in)
+        let sum = match opcode {
+            0x80..=0x87 => (self.reg[A], operand, 0),
+            0x88..=0x8f => (self.reg[A], operand, carry),
+            0x90..=0x97 | 0xb8..=0xbf => (self.reg[A], !operand, 1),
+            0x98..=0x9f => (self.reg[A], !operand, 1 - carry),
+            0xc6 => (self.reg[A], immediate, 0),
+            0xce => (self.reg[A], immediate, carry),
+            0xd6 | 0xfe => (self.reg[A], !immediate, 1),
+            0xde => (self.reg[A], !immediate, 1 - carry),
+            _ if opcode & 0b1100_0111 == 0b0000_0100 => (target, 1, 0),
+            _ if opcode & 0b1100_0111 == 0b0000_0101 => (target, 0xFF, 0),
+            0xa0..=0xb7 | 0xe6 | 0xee | 0xf6 => {
+                self.reg.set_flag(Overflow, false);
+                return;
+            },
+            // INX and DCX: K reports wrapping past 0xFFFF or below 0
+            0x03 | 0x13 | 0x23 | 0x33 => {
+                self.reg.set_flag(Underflow, self.reg[pair(opcode)] == 0xFFFF);
+                return;
+            },
+            0x0b | 0x1b | 0x2b | 0x3b => {
+                self.reg.set_flag(Underflow, self.reg[pair(opcode)] == 0x0000);
+                return;
+            },
+            _ => return,
+        };
+
+        let (a, b, carry_in) = sum;
+        let result = a.wrapping_add(b).wrapping_add(carry_in);
+        self.set_overflow_flags(a & 0x80 != 0, b & 0x80 != 0, result & 0x80 != 0);
+    }
+
+    /// Runs the instructions the 8085 added, returning their length, or None for everything it
+    /// shares with the 8080.
+    pub(super) fn execute_8085_only(&mut self, opcode: Byte) -> Option<Word> {
+        let length = match opcode {
+            0x20 => { self.rim(); 1 },
+            0x30 => { self.sim(); 1 },
+            0x08 => { self.dsub(); 1 },
+            0x10 => { // ARHL
+                self.reg.set_flag(Carry, self.reg[HL] & 1 != 0);
+                self.reg[HL] = ((self.reg[HL] as i16) >> 1) as Word;
+                1
+            },
+            0x18 => { // RDEL
+                let de = self.reg[DE];
+                self.reg[DE] = de << 1 | self.reg.get_flag(Carry) as Word;
+                self.reg.set_flag(Carry, de & 0x8000 != 0);
+                self.reg.set_flag(Overflow, (de ^ (de << 1)) & 0x8000 != 0);
+                1
+            },
+            0x28 => { // LDHI
+                self.reg[DE] = self.reg[HL].wrapping_add(Word::from(self.memory[self.reg[PC].wrapping_add(1)]));
+                2
+            },
+            0x38 => { // LDSI
+                self.reg[DE] = self.reg[SP].wrapping_add(Word::from(self.memory[self.reg[PC].wrapping_add(1)]));
+                2
+            },
+            0xcb => { // RSTV
+                if self.reg.get_flag(Overflow) {
+                    self.push_word(self.reg[PC].wrapping_add(1));
+                    self.reg[PC] = 0x40;
+                    0
+                } else {
+                    1
+                }
+            },
+            0xd9 => { // SHLX
+                let address = self.reg[DE];
+                self.memory[address] = self.reg[L];
+                self.memory[address.wrapping_add(1)] = self.reg[H];
+                1
+            },
+            0xed => { // LHLX
+                let address = self.reg[DE];
+                self.reg[L] = self.memory[address];
+                self.reg[H] = self.memory[address.wrapping_add(1)];
+                1
+            },
+            0xdd | 0xfd => { // JNK, JK
+                if self.reg.get_flag(Underflow) == (opcode == 0xfd) {
+                    let pc = self.reg[PC];
+                    self.reg[PC] = Word::from(self.memory[pc.wrapping_add(1)])
+                        | Word::from(self.memory[pc.wrapping_add(2)]) << 8;
+                    0
+                } else {
+                    3
+                }
+            },
+            _ => return None,
+        };
+        Some(length)
+    }
+
+    /// Sets V and K from the signs of an addition's operands and result. K is the carry out of
+    /// bit 7 of the signs alone, which is what the 8085 actually computes.
+    fn set_overflow_flags(&mut self, sa: bool, sb: bool, sr: bool) {
+        self.reg.set_flag(Overflow, sa == sb && sr != sa);
+        self.reg.set_flag(Underflow, if sa == sb { sa } else { !sr });
+    }
+
+    fn rim(&mut self) {
+        let state = &mut self.i8085;
+        let enabled = state.ie_before_trap.take().unwrap_or(self.interrupts_enabled);
+        self.reg[A] = (state.sid as Byte) << 7
+            | (state.rst75_pending as Byte) << 6
+            | (state.rst65 as Byte) << 5
+            | (state.rst55 as Byte) << 4
+            | (enabled as Byte) << 3
+            | state.masks;
+    }
+
+    fn sim(&mut self) {
+        let value = self.reg[A];
+        let state = &mut self.i8085;
+        if value & 0b0000_1000 != 0 {
+            state.masks = value & 0b111;
+        }
+        if value & 0b0001_0000 != 0 {
+            state.rst75_pending = false;
+        }
+        if value & 0b0100_0000 != 0 {
+            state.sod = value & 0b1000_0000 != 0;
+        }
+    }
+
+    fn dsub(&mut self) {
+        let (hl, bc) = (self.reg[HL], self.reg[BC]);
+        let (result, borrow) = hl.overflowing_sub(bc);
+        self.reg[HL] = result;
+        self.reg.set_flag(Carry, borrow);
+        self.reg.set_flag(Zero, result == 0);
+        self.reg.set_flag(Sign, result & 0x8000 != 0);
+        self.reg.set_flag(Parity, (result as Byte).count_ones() & 1 == 0);
+        self.set_overflow_flags(hl & 0x8000 != 0, bc & 0x8000 == 0, result & 0x8000 != 0);
+    }
+}
+
+fn pair(opcode: Byte) -> super::register::Reg16 {
+    match (opcode >> 4) & 0b11 {
+        0 => BC,
+        1 => DE,
+        2 => HL,
+        _ => SP,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::NullIO;
+
+    fn cpu_8085(program: &[u8]) -> CPU {
+        let mut cpu = CPU::with_variant(Variant::I8085);
+        cpu.memory.load(0, program);
+        cpu.reg[SP] = 0x1000;
+        cpu
+    }
+
+    #[test]
+    fn test_rim_and_sim() {
+        // MVI A,0x1D; SIM; MVI A,0; RIM
+        let mut cpu = cpu_8085(&[0x3e, 0x1d, 0x30, 0x3e, 0x00, 0x20]);
+        let mut io = NullIO;
+        cpu.set_interrupt_line(Interrupt8085::Rst75, true);
+        cpu.i8085.sid = true;
+        for _ in 0..4 {
+            cpu.tick(&mut io);
+        }
+        // masks 101, RST 7.5 reset by R7.5, SID high
+        assert_eq!(cpu.i8085.masks, 0b101);
+        assert_eq!(cpu.reg[A], 0b1000_0101);
+
+        // SOD is only written when SDE is set
+        cpu.reg[A] = 0b1100_0000;
+        cpu.execute_opcode(0x30, &mut io);
+        assert!(cpu.i8085.sod);
+        cpu.reg[A] = 0b0000_0000;
+        cpu.execute_opcode(0x30, &mut io);
+        assert!(cpu.i8085.sod);
+
+        // on an 8080 both are NOPs
+        let mut cpu = CPU::new();
+        cpu.reg[A] = 0x1D;
+        cpu.execute_opcode(0x30, &mut io);
+        cpu.execute_opcode(0x20, &mut io);
+        assert_eq!(cpu.reg[A], 0x1D);
+    }
+
+    #[test]
+    fn test_interrupt_priority_and_masks() {
+        let mut cpu = cpu_8085(&[0x00; 0x100]);
+        let mut io = NullIO;
+        cpu.set_interrupt_line(Interrupt8085::Rst55, true);
+        cpu.set_interrupt_line(Interrupt8085::Rst65, true);
+
+        // nothing is taken with interrupts disabled
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 1);
+
+        cpu.interrupts_enabled = true;
+        assert_eq!(cpu.tick(&mut io), 12);
+        assert_eq!(cpu.reg[PC], 0x34);
+        assert!(!cpu.interrupts_enabled);
+
+        // masked 6.5 lets 5.5 through
+        cpu.interrupts_enabled = true;
+        cpu.i8085.masks = 0b010;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x2C);
+
+        // TRAP ignores both the enable and the masks, and RIM reports the enable from before it
+        cpu.i8085.masks = 0b111;
+        cpu.interrupts_enabled = true;
+        cpu.set_interrupt_line(Interrupt8085::Trap, true);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x24);
+        cpu.execute_opcode(0x20, &mut io);
+        assert_eq!(cpu.reg[A] & 0b1000, 0b1000);
+        cpu.execute_opcode(0x20, &mut io);
+        assert_eq!(cpu.reg[A] & 0b1000, 0);
+
+        // TRAP is edge triggered
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[PC], 0x27);
+    }
+
+    #[test]
+    fn test_undocumented_instructions() {
+        let mut io = NullIO;
+
+        // LXI H,0x1234; LXI B,0x0235; DSUB; ARHL
+        let mut cpu = cpu_8085(&[0x21, 0x34, 0x12, 0x01, 0x35, 0x02, 0x08, 0x10]);
+        for _ in 0..3 {
+            cpu.tick(&mut io);
+        }
+        assert_eq!(cpu.reg[HL], 0x0FFF);
+        assert!(!cpu.reg.get_flag(Carry));
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[HL], 0x07FF);
+        assert!(cpu.reg.get_flag(Carry));
+
+        // LDHI 5; SHLX; LXI H,0; LHLX
+        let mut cpu = cpu_8085(&[0x28, 0x05, 0xd9, 0x21, 0x00, 0x00, 0xed]);
+        cpu.reg[HL] = 0x0200;
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[DE], 0x0205);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.memory.view(0x0205, 0x0206), &[0x00, 0x02]);
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        assert_eq!(cpu.reg[HL], 0x0200);
+    }
+
+    #[test]
+    fn test_overflow_and_underflow_flags() {
+        let mut io = NullIO;
+
+        // MVI A,0x7F; ADI 1 overflows; RSTV then restarts at 0x40
+        let mut cpu = cpu_8085(&[0x3e, 0x7f, 0xc6, 0x01, 0xcb]);
+        for _ in 0..2 {
+            cpu.tick(&mut io);
+        }
+        assert!(cpu.reg.get_flag(Overflow));
+        assert_eq!(cpu.tick(&mut io), 12);
+        assert_eq!(cpu.reg[PC], 0x40);
+
+        // LXI B,0; DCX B sets K; JK 0x0050
+        let mut cpu = cpu_8085(&[0x01, 0x00, 0x00, 0x0b, 0xfd, 0x50, 0x00]);
+        for _ in 0..2 {
+            cpu.tick(&mut io);
+        }
+        assert!(cpu.reg.get_flag(Underflow));
+        assert_eq!(cpu.tick(&mut io), 10);
+        assert_eq!(cpu.reg[PC], 0x50);
+    }
+
+    #[test]
+    fn test_timing() {
+        let mut io = NullIO;
+        // MOV B,C is 4 cycles instead of 5, INX 6 instead of 5, PUSH 12 instead of 11
+        let mut cpu = cpu_8085(&[0x41, 0x03, 0xc5, 0xc2, 0x00, 0x00, 0xc2, 0x00, 0x00]);
+        assert_eq!(cpu.tick(&mut io), 4);
+        assert_eq!(cpu.tick(&mut io), 6);
+        assert_eq!(cpu.tick(&mut io), 12);
+        cpu.reg.set_flag(Zero, true);
+        assert_eq!(cpu.tick(&mut io), 7);
+        cpu.reg.set_flag(Zero, false);
+        assert_eq!(cpu.tick(&mut io), 10);
+        assert_eq!(cpu.reg[PC], 0);
+    }
+}
@@ -0,0 +1,50 @@
+/* The 8080 talks to the rest of the machine through 256 input and 256 output ports via the IN and
+   OUT instructions. Each machine supplies its own implementation of what sits on those ports.
+   Reading a port can change the device behind it (e.g. taking a byte out of a serial card's
+   receive buffer), so input takes `&mut self` too.
+
+   Interrupts come in over the same bus: when the CPU is ready to take one it runs an interrupt
+   acknowledge cycle, and whatever is interrupting puts an instruction on the data bus for it to
+   execute in place of the next one from memory. That's usually an RST, but an 8259 supplies a
+   whole CALL. */
+
+use super::cpu::{Address, Byte};
+
+/// What a device puts on the data bus to answer an interrupt acknowledge.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum InterruptInstruction {
+    /// A one-byte instruction, normally RST n. The CPU panics at one that has operands, which
+    /// would have to come over the bus too; a CALL is sent as `Call`.
+    Opcode(Byte),
+    /// CALL and its address, as sent by an 8259 over three acknowledge cycles.
+    Call(Address),
+}
+
+impl InterruptInstruction {
+    pub fn rst(n: u8) -> Self {
+        InterruptInstruction::Opcode(0xc7 | (n & 7) << 3)
+    }
+}
+
+pub trait IO {
+    fn input(&mut self, port: u8) -> u8;
+    fn output(&mut self, port: u8, value: u8);
+
+    /// Acknowledges an interrupt, asked whenever the CPU would take one. Devices without an
+    /// interrupt to raise leave the bus alone.
+    fn acknowledge_interrupt(&mut self) -> Option<InterruptInstruction> {
+        None
+    }
+}
+
+/// Nothing attached: reads float high, writes go nowhere.
+pub struct NullIO;
+
+impl IO for NullIO {
+    fn input(&mut self, _port: u8) -> u8 {
+        0xFF
+    }
+
+    fn output(&mut self, _port: u8, _value: u8) {}
+}
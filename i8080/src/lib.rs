@@ -0,0 +1,47 @@
+//! An Intel 8080 and 8085: the registers, memory, instruction decoder, disassembler and an
+//! interpreter, with a cache of decoded blocks for when that isn't fast enough.
+//!
+//! A `cpu::CPU` owns its registers and 64K of memory and runs an instruction per `tick`, talking
+//! to the rest of the machine through an `io::IO` implementation:
+//!
+//! ```
+//! use i8080::cpu::CPU;
+//! use i8080::io::NullIO;
+//! use i8080::register::Reg16::PC;
+//! use i8080::register::Reg8::A;
+//!
+//! let mut cpu = CPU::new();
+//! cpu.memory.load(0, &[0x3e, 0x42, 0x76]); // MVI A, 42h; HLT
+//! cpu.tick(&mut NullIO);
+//! assert_eq!(cpu.reg[A], 0x42);
+//! assert_eq!(cpu.reg[PC], 2);
+//! ```
+//!
+//! `Op`, `Variant`, `CpuError`, `InterruptInstruction` and `timing::MachineCycle` may gain
+//! variants and `CPU` may gain fields, so they are `#[non_exhaustive]`. The registers, flags, ALU
+//! operations and conditions are the chips' own and stay as they are. The fields of `CPU`,
+//! `Register` and `Memory` are public for machines and debuggers to read and poke, so their
+//! layout is part of the API too.
+//!
+//! Without the default `std` feature the crate is `no_std`, for embedded targets. The `alloc`
+//! feature, which `std` turns on, adds what needs a heap: the disassembler's names, trace lines,
+//! `Display` for `CPU` and the block cache. With `std`, CP/M's print-string call goes to stdout
+//! and JMP 0 exits; without it the call is ignored and JMP 0 halts.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod memory;
+#[cfg(feature = "alloc")]
+pub mod blocks;
+pub mod cpu;
+pub mod decode;
+pub mod disassembler;
+pub mod i8085;
+pub mod io;
+pub mod register;
+pub mod timing;
+#[cfg(all(test, feature = "alloc"))]
+mod reference;
@@ -0,0 +1,136 @@
+use core::ops::{Index, IndexMut};
+use super::cpu::Byte;
+use super::cpu::Address;
+
+/* This is purely for vanity - I hate having to constantly convert index values to usize.
+
+   It also watches for writes to code the block cache has decoded. Pages of 256 bytes holding
+   cached code are marked, and writing to one flags it so the cache can throw away what it
+   decoded from there. Writes through the `memory` array itself aren't seen. */
+
+/// 64K, indexed by address.
+pub struct Memory {
+     pub memory: [Byte; 0x10000],
+     code_pages: [bool; 0x100],
+     written_pages: [bool; 0x100],
+     /// Set by a write to any page holding cached code.
+     pub code_written: bool,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self {
+            memory: [0; 0x10000],
+            code_pages: [false; 0x100],
+            written_pages: [false; 0x100],
+            code_written: false,
+        }
+    }
+
+    /// Starts watching a page for writes.
+    pub fn mark_code(&mut self, page: u8) {
+        self.code_pages[page as usize] = true;
+    }
+
+    /// The pages holding code that have been written since the last call, no longer watched.
+    pub fn take_written_code(&mut self) -> impl Iterator<Item = u8> + '_ {
+        self.code_written = false;
+        let (code, written) = (&mut self.code_pages, &mut self.written_pages);
+        (0..=0xffu8).filter(move |&page| {
+            let was_written = core::mem::replace(&mut written[page as usize], false);
+            if was_written {
+                code[page as usize] = false;
+            }
+            was_written
+        })
+    }
+
+    fn written(&mut self, address: Address) {
+        let page = (address >> 8) as usize;
+        if self.code_pages[page] {
+            self.written_pages[page] = true;
+            self.code_written = true;
+        }
+    }
+
+    pub fn view(&self, start: Address, end: Address) -> &[Byte] {
+        &self.memory[(start as usize)..=(end as usize)]
+    }
+
+    pub fn load(&mut self, address: usize, data: &[u8]) {
+        self.memory[address..(address + data.len())].copy_from_slice(data);
+        for page in (address >> 8)..((address + data.len() + 0xff) >> 8) {
+            self.written((page << 8) as Address);
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<Address> for Memory {
+    type Output = Byte;
+    fn index(&self, index: Address) -> &Self::Output {
+        &self.memory[index as usize]
+    }
+}
+
+impl IndexMut<Address> for Memory {
+    fn index_mut(&mut self, index: Address) -> &mut Byte {
+        self.written(index);
+        &mut self.memory[index as usize]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_memory_access_with_u16() {
+        let mut memory = Memory::new();
+
+        memory[1] = 2;
+
+        assert_eq!(memory[1], 2);
+
+        let address: Address = 0x78;
+
+        memory[address] = 45;
+
+        assert_eq!(memory[address], 45);
+
+        memory[address] = 0;
+
+        assert_eq!(memory[address], 0);
+    }
+
+    #[test]
+    fn test_top_of_memory() {
+        let mut memory = Memory::new();
+        memory[0xFFFF] = 0x12;
+        assert_eq!(memory.view(0xFFFE, 0xFFFF), &[0x00, 0x12]);
+    }
+
+    #[test]
+    fn test_writes_to_code() {
+        let mut memory = Memory::new();
+        memory.mark_code(0x01);
+        memory.mark_code(0x20);
+        memory[0x0200] = 1;
+        assert!(!memory.code_written);
+        memory[0x01ff] = 1;
+        memory.load(0x1ff0, &[0; 0x20]);
+        assert!(memory.code_written);
+        assert_eq!(memory.take_written_code().collect::<Vec<u8>>(), vec![0x01, 0x20]);
+        assert!(!memory.code_written);
+
+        // written pages aren't watched until they're marked again
+        memory[0x0100] = 2;
+        assert!(!memory.code_written);
+    }
+}
\ No newline at end of file
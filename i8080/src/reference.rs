@@ -0,0 +1,752 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::cpu::CPU;
+use super::disassembler::op_name;
+use super::io::IO;
+use super::register::Reg16::{PC, SP};
+use super::register::Reg8::{A, B, C, D, E, F, H, L};
+
+/* A second, independent 8080 to check the real one against. It decodes opcodes with a table of
+   bit patterns rather than a 256-way match, keeps its flags as the byte PUSH PSW would push, and
+   works everything out from the Intel manual rather than from cpu.rs, so the two are unlikely to
+   share a mistake.
+
+   The fuzzer runs single instructions through both from random registers, flags and memory and
+   compares every register, flag, memory byte, port write and cycle count. When they disagree it
+   shrinks the starting state towards zeros, one field at a time, for as long as they still
+   disagree, and reports that as the reproducer. */
+
+const SIGN: u8 = 0x80;
+const ZERO: u8 = 0x40;
+const AUX: u8 = 0x10;
+const PARITY: u8 = 0x04;
+const ALWAYS_ONE: u8 = 0x02;
+const CARRY: u8 = 0x01;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Reg {
+    B, C, D, E, H, L, M, A,
+}
+
+const REGS: [Reg; 8] = [Reg::B, Reg::C, Reg::D, Reg::E, Reg::H, Reg::L, Reg::M, Reg::A];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Pair {
+    BC, DE, HL, SP, Psw,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Alu {
+    Add, Adc, Sub, Sbb, Ana, Xra, Ora, Cmp,
+}
+
+const ALUS: [Alu; 8] = [Alu::Add, Alu::Adc, Alu::Sub, Alu::Sbb, Alu::Ana, Alu::Xra, Alu::Ora, Alu::Cmp];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Cond {
+    Always,
+    /// The flag to test and the value it must have.
+    If(u8, bool),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Nop, Hlt, Di, Ei,
+    Lxi(Pair), Stax(Pair), Ldax(Pair), Inx(Pair), Dcx(Pair), Dad(Pair), Push(Pair), Pop(Pair),
+    Inr(Reg), Dcr(Reg), Mvi(Reg), Mov(Reg, Reg),
+    Alu(Alu, Option<Reg>),
+    Rlc, Rrc, Ral, Rar, Daa, Cma, Stc, Cmc,
+    Shld, Lhld, Sta, Lda, Xthl, Pchl, Xchg, Sphl,
+    Jmp(Cond), Call(Cond), Ret(Cond), Rst(u8),
+    In, Out,
+}
+
+fn reg(opcode: u8, shift: u8) -> Reg {
+    REGS[(opcode >> shift & 7) as usize]
+}
+
+fn pair(opcode: u8) -> Pair {
+    [Pair::BC, Pair::DE, Pair::HL, Pair::SP][(opcode >> 4 & 3) as usize]
+}
+
+fn stack_pair(opcode: u8) -> Pair {
+    [Pair::BC, Pair::DE, Pair::HL, Pair::Psw][(opcode >> 4 & 3) as usize]
+}
+
+fn cond(opcode: u8) -> Cond {
+    let flag = [ZERO, CARRY, PARITY, SIGN][(opcode >> 4 & 3) as usize];
+    Cond::If(flag, opcode & 0x08 != 0)
+}
+
+/// (mask, bits, decoder): an opcode decodes with the first row where `opcode & mask == bits`.
+type Pattern = (u8, u8, fn(u8) -> Op);
+
+const DECODE: &[Pattern] = &[
+    (0xff, 0x76, |_| Op::Hlt),
+    (0xc0, 0x40, |o| Op::Mov(reg(o, 3), reg(o, 0))),
+    (0xc0, 0x80, |o| Op::Alu(ALUS[(o >> 3 & 7) as usize], Some(reg(o, 0)))),
+    (0xc7, 0xc6, |o| Op::Alu(ALUS[(o >> 3 & 7) as usize], None)),
+    (0xcf, 0x01, |o| Op::Lxi(pair(o))),
+    (0xef, 0x02, |o| Op::Stax(pair(o))),
+    (0xef, 0x0a, |o| Op::Ldax(pair(o))),
+    (0xcf, 0x03, |o| Op::Inx(pair(o))),
+    (0xcf, 0x0b, |o| Op::Dcx(pair(o))),
+    (0xcf, 0x09, |o| Op::Dad(pair(o))),
+    (0xc7, 0x04, |o| Op::Inr(reg(o, 3))),
+    (0xc7, 0x05, |o| Op::Dcr(reg(o, 3))),
+    (0xc7, 0x06, |o| Op::Mvi(reg(o, 3))),
+    (0xff, 0x07, |_| Op::Rlc),
+    (0xff, 0x0f, |_| Op::Rrc),
+    (0xff, 0x17, |_| Op::Ral),
+    (0xff, 0x1f, |_| Op::Rar),
+    (0xff, 0x22, |_| Op::Shld),
+    (0xff, 0x2a, |_| Op::Lhld),
+    (0xff, 0x27, |_| Op::Daa),
+    (0xff, 0x2f, |_| Op::Cma),
+    (0xff, 0x32, |_| Op::Sta),
+    (0xff, 0x3a, |_| Op::Lda),
+    (0xff, 0x37, |_| Op::Stc),
+    (0xff, 0x3f, |_| Op::Cmc),
+    (0xc7, 0x00, |_| Op::Nop),
+    (0xc7, 0xc0, |o| Op::Ret(cond(o))),
+    (0xef, 0xc9, |_| Op::Ret(Cond::Always)),
+    (0xcf, 0xc1, |o| Op::Pop(stack_pair(o))),
+    (0xcf, 0xc5, |o| Op::Push(stack_pair(o))),
+    (0xc7, 0xc2, |o| Op::Jmp(cond(o))),
+    (0xf7, 0xc3, |_| Op::Jmp(Cond::Always)),
+    (0xc7, 0xc4, |o| Op::Call(cond(o))),
+    (0xcf, 0xcd, |_| Op::Call(Cond::Always)),
+    (0xc7, 0xc7, |o| Op::Rst(o & 0x38)),
+    (0xff, 0xd3, |_| Op::Out),
+    (0xff, 0xdb, |_| Op::In),
+    (0xff, 0xe3, |_| Op::Xthl),
+    (0xff, 0xe9, |_| Op::Pchl),
+    (0xff, 0xeb, |_| Op::Xchg),
+    (0xff, 0xf9, |_| Op::Sphl),
+    (0xff, 0xf3, |_| Op::Di),
+    (0xff, 0xfb, |_| Op::Ei),
+];
+
+fn decode(opcode: u8) -> Op {
+    let &(_, _, decoder) = DECODE.iter().find(|&&(mask, bits, _)| opcode & mask == bits)
+        .unwrap_or_else(|| panic!("no decoding for {:02x}", opcode));
+    decoder(opcode)
+}
+
+/// Sign, zero and parity of a result.
+fn szp(value: u8) -> u8 {
+    let mut flags = value & SIGN;
+    if value == 0 {
+        flags |= ZERO;
+    }
+    if value.count_ones() & 1 == 0 {
+        flags |= PARITY;
+    }
+    flags
+}
+
+/// The port the fuzzer's devices read and write.
+struct FuzzIO {
+    written: Vec<(u8, u8)>,
+}
+
+impl IO for FuzzIO {
+    fn input(&mut self, port: u8) -> u8 {
+        port.rotate_left(3) ^ 0xA5
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.written.push((port, value));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    a: u8, b: u8, c: u8, d: u8, e: u8, h: u8, l: u8,
+    flags: u8,
+    sp: u16,
+    pc: u16,
+    inte: bool,
+    halted: bool,
+    memory: BTreeMap<u16, u8>,
+    written: Vec<(u8, u8)>,
+    cycles: u64,
+}
+
+impl State {
+    fn read(&self, address: u16) -> u8 {
+        self.memory.get(&address).cloned().unwrap_or(0)
+    }
+
+    fn read_word(&self, address: u16) -> u16 {
+        u16::from(self.read(address)) | u16::from(self.read(address.wrapping_add(1))) << 8
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory.insert(address, value);
+    }
+
+    fn write_word(&mut self, address: u16, value: u16) {
+        self.write(address, value as u8);
+        self.write(address.wrapping_add(1), (value >> 8) as u8);
+    }
+
+    fn hl(&self) -> u16 {
+        u16::from(self.h) << 8 | u16::from(self.l)
+    }
+
+    fn get(&self, reg: Reg) -> u8 {
+        match reg {
+            Reg::A => self.a, Reg::B => self.b, Reg::C => self.c, Reg::D => self.d,
+            Reg::E => self.e, Reg::H => self.h, Reg::L => self.l, Reg::M => self.read(self.hl()),
+        }
+    }
+
+    fn set(&mut self, reg: Reg, value: u8) {
+        match reg {
+            Reg::A => self.a = value, Reg::B => self.b = value, Reg::C => self.c = value,
+            Reg::D => self.d = value, Reg::E => self.e = value, Reg::H => self.h = value,
+            Reg::L => self.l = value, Reg::M => self.write(self.hl(), value),
+        }
+    }
+
+    fn get_pair(&self, pair: Pair) -> u16 {
+        let join = |high: u8, low: u8| u16::from(high) << 8 | u16::from(low);
+        match pair {
+            Pair::BC => join(self.b, self.c),
+            Pair::DE => join(self.d, self.e),
+            Pair::HL => join(self.h, self.l),
+            Pair::SP => self.sp,
+            Pair::Psw => join(self.a, self.flags),
+        }
+    }
+
+    fn set_pair(&mut self, pair: Pair, value: u16) {
+        let (high, low) = ((value >> 8) as u8, value as u8);
+        match pair {
+            Pair::BC => { self.b = high; self.c = low; },
+            Pair::DE => { self.d = high; self.e = low; },
+            Pair::HL => { self.h = high; self.l = low; },
+            Pair::SP => self.sp = value,
+            Pair::Psw => { self.a = high; self.flags = low & 0xD5 | ALWAYS_ONE; },
+        }
+    }
+
+    fn set_flags(&mut self, flags: u8, carry: bool, aux: bool) {
+        self.flags = flags | ALWAYS_ONE | if carry { CARRY } else { 0 } | if aux { AUX } else { 0 };
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    fn holds(&self, cond: Cond) -> bool {
+        match cond {
+            Cond::Always => true,
+            Cond::If(flag, value) => self.flag(flag) == value,
+        }
+    }
+
+    fn push(&mut self, value: u16) {
+        self.sp = self.sp.wrapping_sub(2);
+        self.write_word(self.sp, value);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let value = self.read_word(self.sp);
+        self.sp = self.sp.wrapping_add(2);
+        value
+    }
+
+    fn alu(&mut self, op: Alu, value: u8) {
+        let a = self.a;
+        let carry = self.flag(CARRY) as u8;
+        match op {
+            Alu::Add | Alu::Adc => {
+                let carry_in = if op == Alu::Adc { carry } else { 0 };
+                let sum = u16::from(a) + u16::from(value) + u16::from(carry_in);
+                self.a = sum as u8;
+                self.set_flags(szp(self.a), sum > 0xFF, (a & 0xF) + (value & 0xF) + carry_in > 0xF);
+            },
+            Alu::Sub | Alu::Sbb | Alu::Cmp => {
+                // subtraction is addition of the complement, and AC is the carry out of bit 3 of that
+                let borrow_in = if op == Alu::Sbb { carry } else { 0 };
+                let difference = i16::from(a) - i16::from(value) - i16::from(borrow_in);
+                let result = difference as u8;
+                let aux = (a & 0xF) + (!value & 0xF) + (1 - borrow_in) > 0xF;
+                self.set_flags(szp(result), difference < 0, aux);
+                if op != Alu::Cmp {
+                    self.a = result;
+                }
+            },
+            Alu::Ana => {
+                self.a = a & value;
+                self.set_flags(szp(self.a), false, (a | value) & 0x08 != 0);
+            },
+            Alu::Xra => {
+                self.a = a ^ value;
+                self.set_flags(szp(self.a), false, false);
+            },
+            Alu::Ora => {
+                self.a = a | value;
+                self.set_flags(szp(self.a), false, false);
+            },
+        }
+    }
+
+    /// Runs the instruction at PC.
+    fn step(&mut self, io: &mut FuzzIO) {
+        let opcode = self.read(self.pc);
+        let byte = self.read(self.pc.wrapping_add(1));
+        let word = self.read_word(self.pc.wrapping_add(1));
+        let op = decode(opcode);
+        let length = match op {
+            Op::Lxi(_) | Op::Shld | Op::Lhld | Op::Sta | Op::Lda | Op::Jmp(_) | Op::Call(_) => 3,
+            Op::Mvi(_) | Op::Alu(_, None) | Op::In | Op::Out => 2,
+            _ => 1,
+        };
+        let next = self.pc.wrapping_add(length);
+        self.pc = next;
+        let carry = self.flag(CARRY);
+
+        self.cycles = match op {
+            Op::Nop => 4,
+            Op::Hlt => { self.halted = true; 7 },
+            Op::Di => { self.inte = false; 4 },
+            Op::Ei => { self.inte = true; 4 },
+            Op::Lxi(pair) => { self.set_pair(pair, word); 10 },
+            Op::Stax(pair) => { let address = self.get_pair(pair); self.write(address, self.a); 7 },
+            Op::Ldax(pair) => { self.a = self.read(self.get_pair(pair)); 7 },
+            Op::Inx(pair) => { self.set_pair(pair, self.get_pair(pair).wrapping_add(1)); 5 },
+            Op::Dcx(pair) => { self.set_pair(pair, self.get_pair(pair).wrapping_sub(1)); 5 },
+            Op::Dad(pair) => {
+                let sum = u32::from(self.hl()) + u32::from(self.get_pair(pair));
+                self.set_pair(Pair::HL, sum as u16);
+                self.flags = self.flags & !CARRY | if sum > 0xFFFF { CARRY } else { 0 };
+                10
+            },
+            Op::Push(pair) => { self.push(self.get_pair(pair)); 11 },
+            Op::Pop(pair) => { let value = self.pop(); self.set_pair(pair, value); 10 },
+            Op::Inr(reg) | Op::Dcr(reg) => {
+                let value = if let Op::Inr(_) = op { self.get(reg).wrapping_add(1) } else { self.get(reg).wrapping_sub(1) };
+                self.set(reg, value);
+                let aux = if let Op::Inr(_) = op { value & 0xF == 0 } else { value & 0xF != 0xF };
+                self.set_flags(szp(value), carry, aux);
+                if reg == Reg::M { 10 } else { 5 }
+            },
+            Op::Mvi(reg) => { self.set(reg, byte); if reg == Reg::M { 10 } else { 7 } },
+            Op::Mov(to, from) => {
+                self.set(to, self.get(from));
+                if to == Reg::M || from == Reg::M { 7 } else { 5 }
+            },
+            Op::Alu(op, Some(reg)) => { self.alu(op, self.get(reg)); if reg == Reg::M { 7 } else { 4 } },
+            Op::Alu(op, None) => { self.alu(op, byte); 7 },
+            Op::Rlc => { self.a = self.a.rotate_left(1); self.flags = self.flags & !CARRY | self.a & 1; 4 },
+            Op::Rrc => { self.flags = self.flags & !CARRY | self.a & 1; self.a = self.a.rotate_right(1); 4 },
+            Op::Ral => {
+                let out = self.a >> 7;
+                self.a = self.a << 1 | carry as u8;
+                self.flags = self.flags & !CARRY | out;
+                4
+            },
+            Op::Rar => {
+                let out = self.a & 1;
+                self.a = self.a >> 1 | (carry as u8) << 7;
+                self.flags = self.flags & !CARRY | out;
+                4
+            },
+            Op::Daa => {
+                let (low, high) = (self.a & 0xF, self.a >> 4);
+                let mut correction = 0;
+                let mut carry_out = carry;
+                if low > 9 || self.flag(AUX) {
+                    correction |= 0x06;
+                }
+                if high > 9 || carry || (high >= 9 && low > 9) {
+                    correction |= 0x60;
+                    carry_out = true;
+                }
+                let aux = low + (correction & 0xF) > 0xF;
+                self.a = self.a.wrapping_add(correction);
+                self.set_flags(szp(self.a), carry_out, aux);
+                4
+            },
+            Op::Cma => { self.a = !self.a; 4 },
+            Op::Stc => { self.flags |= CARRY; 4 },
+            Op::Cmc => { self.flags ^= CARRY; 4 },
+            Op::Shld => { self.write_word(word, self.hl()); 16 },
+            Op::Lhld => { let value = self.read_word(word); self.set_pair(Pair::HL, value); 16 },
+            Op::Sta => { self.write(word, self.a); 13 },
+            Op::Lda => { self.a = self.read(word); 13 },
+            Op::Xthl => {
+                let top = self.read_word(self.sp);
+                self.write_word(self.sp, self.hl());
+                self.set_pair(Pair::HL, top);
+                18
+            },
+            Op::Pchl => { self.pc = self.hl(); 5 },
+            Op::Xchg => {
+                let de = self.get_pair(Pair::DE);
+                self.set_pair(Pair::DE, self.hl());
+                self.set_pair(Pair::HL, de);
+                4
+            },
+            Op::Sphl => { self.sp = self.hl(); 5 },
+            Op::Jmp(cond) => {
+                if self.holds(cond) {
+                    self.pc = word;
+                }
+                10
+            },
+            Op::Call(cond) => {
+                if self.holds(cond) {
+                    self.push(next);
+                    self.pc = word;
+                    17
+                } else {
+                    11
+                }
+            },
+            Op::Ret(Cond::Always) => { self.pc = self.pop(); 10 },
+            Op::Ret(cond) => {
+                if self.holds(cond) {
+                    self.pc = self.pop();
+                    11
+                } else {
+                    5
+                }
+            },
+            Op::Rst(address) => { self.push(next); self.pc = u16::from(address); 11 },
+            Op::In => { self.a = io.input(byte); 10 },
+            Op::Out => { io.output(byte, self.a); 10 },
+        };
+    }
+}
+
+/// A starting state for one instruction. Memory is described relative to the registers, so that
+/// shrinking a register moves the bytes it points at along with it.
+#[derive(Clone, PartialEq)]
+struct Case {
+    opcode: u8,
+    operands: [u8; 2],
+    regs: [u8; 7],
+    flags: u8,
+    sp: u16,
+    pc: u16,
+    inte: bool,
+    stack: [u8; 4],
+    at_hl: [u8; 2],
+    at_bc: u8,
+    at_de: u8,
+    at_operand: [u8; 2],
+}
+
+impl fmt::Debug for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let [a, b, c, d, e, h, l] = self.regs;
+        write!(f, "{:02x} {} at pc={:04x} with a={:02x} b={:02x} c={:02x} d={:02x} e={:02x} h={:02x} l={:02x} \
+                   flags={:02x} sp={:04x} inte={} operands={:02x?} stack={:02x?} (hl)={:02x?} (bc)={:02x} \
+                   (de)={:02x} (operand)={:02x?}",
+               self.opcode, op_name(self.opcode, self.operands[0], u16::from(self.operands[1]) << 8 | u16::from(self.operands[0])),
+               self.pc, a, b, c, d, e, h, l, self.flags, self.sp, self.inte, self.operands, self.stack,
+               self.at_hl, self.at_bc, self.at_de, self.at_operand)
+    }
+}
+
+impl Case {
+    fn random(opcode: u8, rng: &mut Rng) -> Self {
+        let byte = |rng: &mut Rng| rng.next() as u8;
+        let mut case = Case {
+            opcode,
+            operands: [byte(rng), byte(rng)],
+            regs: [0; 7],
+            flags: byte(rng) & 0xD5 | ALWAYS_ONE,
+            sp: rng.next() as u16,
+            pc: rng.next() as u16,
+            inte: rng.next() & 1 != 0,
+            stack: [byte(rng), byte(rng), byte(rng), byte(rng)],
+            at_hl: [byte(rng), byte(rng)],
+            at_bc: byte(rng),
+            at_de: byte(rng),
+            at_operand: [byte(rng), byte(rng)],
+        };
+        for reg in case.regs.iter_mut() {
+            // plenty of edge values, where the flags are most interesting
+            *reg = match rng.next() % 4 {
+                0 => [0x00, 0x0F, 0x10, 0x7F, 0x80, 0x99, 0x9A, 0xFF][rng.next() as usize % 8],
+                _ => byte(rng),
+            };
+        }
+        case
+    }
+
+    /// The memory the case starts with. Later entries win where they overlap, so the instruction
+    /// itself is always intact.
+    fn memory(&self) -> BTreeMap<u16, u8> {
+        let [_, b, c, d, e, h, l] = self.regs;
+        let join = |high: u8, low: u8| u16::from(high) << 8 | u16::from(low);
+        let operand = join(self.operands[1], self.operands[0]);
+        let mut memory = BTreeMap::new();
+        let mut put = |address: u16, value: u8| { memory.insert(address, value); };
+        put(join(b, c), self.at_bc);
+        put(join(d, e), self.at_de);
+        for i in 0..2 {
+            put(operand.wrapping_add(i), self.at_operand[i as usize]);
+            put(join(h, l).wrapping_add(i), self.at_hl[i as usize]);
+        }
+        for i in 0..4 {
+            put(self.sp.wrapping_sub(2).wrapping_add(i), self.stack[i as usize]);
+        }
+        put(self.pc, self.opcode);
+        put(self.pc.wrapping_add(1), self.operands[0]);
+        put(self.pc.wrapping_add(2), self.operands[1]);
+        memory
+    }
+
+    fn state(&self) -> State {
+        let [a, b, c, d, e, h, l] = self.regs;
+        State {
+            a, b, c, d, e, h, l,
+            flags: self.flags,
+            sp: self.sp,
+            pc: self.pc,
+            inte: self.inte,
+            halted: false,
+            memory: self.memory(),
+            written: Vec::new(),
+            cycles: 0,
+        }
+    }
+
+    /// Simpler versions of the case: each field zeroed, or halved, or a flag cleared.
+    fn shrunk(&self) -> Vec<Case> {
+        let mut candidates = Vec::new();
+        let mut try_with = |change: &dyn Fn(&mut Case)| {
+            let mut case = self.clone();
+            change(&mut case);
+            if case != *self {
+                candidates.push(case);
+            }
+        };
+        for i in 0..7 {
+            try_with(&|case| case.regs[i] = 0);
+            try_with(&|case| case.regs[i] /= 2);
+        }
+        for i in 0..2 {
+            try_with(&|case| case.operands[i] = 0);
+            try_with(&|case| case.at_hl[i] = 0);
+            try_with(&|case| case.at_operand[i] = 0);
+        }
+        for i in 0..4 {
+            try_with(&|case| case.stack[i] = 0);
+        }
+        for &flag in &[SIGN, ZERO, AUX, PARITY, CARRY] {
+            try_with(&|case| case.flags &= !flag);
+        }
+        try_with(&|case| case.at_bc = 0);
+        try_with(&|case| case.at_de = 0);
+        try_with(&|case| case.inte = false);
+        try_with(&|case| case.sp = 0x1000);
+        try_with(&|case| case.pc = 0x0100);
+        candidates
+    }
+}
+
+/// Xorshift, so runs are repeatable without another dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// A CPU with every page of memory watched, so `run_cpu` can find stores the reference doesn't
+/// make.
+fn watched_cpu() -> CPU {
+    let mut cpu = CPU::new();
+    for page in 0..=0xff {
+        cpu.memory.mark_code(page);
+    }
+    cpu
+}
+
+/// Runs a case on the real CPU, which is reused to save clearing 64K each time.
+fn run_cpu(cpu: &mut CPU, case: &Case) -> State {
+    let memory = case.memory();
+    for (&address, &value) in memory.iter() {
+        cpu.memory.memory[address as usize] = value;
+    }
+    let [a, b, c, d, e, h, l] = case.regs;
+    cpu.reg[A] = a; cpu.reg[B] = b; cpu.reg[C] = c; cpu.reg[D] = d;
+    cpu.reg[E] = e; cpu.reg[H] = h; cpu.reg[L] = l;
+    cpu.reg[F] = case.flags & !ALWAYS_ONE;
+    cpu.reg[SP] = case.sp;
+    cpu.reg[PC] = case.pc;
+    cpu.interrupts_enabled = case.inte;
+    cpu.ei_delay = false;
+    cpu.halted = false;
+
+    let mut io = FuzzIO { written: Vec::new() };
+    let opcode = cpu.fetch();
+    let cycles = u64::from(cpu.execute_opcode(opcode, &mut io));
+
+    // read back what the case held, what the reference touched and anything else the CPU stored,
+    // and put memory back to zeros
+    let mut expected = case.state();
+    expected.step(&mut FuzzIO { written: Vec::new() });
+    let mut touched: BTreeSet<u16> = memory.keys().chain(expected.memory.keys()).cloned().collect();
+    let written: Vec<u8> = cpu.memory.take_written_code().collect();
+    for page in written {
+        let start = u16::from(page) << 8;
+        touched.extend((start..=start | 0xff).filter(|&address| cpu.memory[address] != 0));
+        cpu.memory.mark_code(page);
+    }
+    let memory = touched.iter().map(|&address| (address, cpu.memory[address])).collect();
+    for &address in touched.iter() {
+        cpu.memory.memory[address as usize] = 0;
+    }
+
+    State {
+        a: cpu.reg[A], b: cpu.reg[B], c: cpu.reg[C], d: cpu.reg[D],
+        e: cpu.reg[E], h: cpu.reg[H], l: cpu.reg[L],
+        flags: cpu.reg[F] | ALWAYS_ONE,
+        sp: cpu.reg[SP],
+        pc: cpu.reg[PC],
+        inte: cpu.interrupts_enabled,
+        halted: cpu.halted,
+        memory,
+        written: io.written,
+        cycles,
+    }
+}
+
+fn run_reference(case: &Case) -> State {
+    let mut state = case.state();
+    let mut io = FuzzIO { written: Vec::new() };
+    state.step(&mut io);
+    state.written = io.written;
+    state
+}
+
+/// The fields where two states differ.
+fn differences(actual: &State, expected: &State) -> String {
+    let mut out = Vec::new();
+    macro_rules! compare {
+        ($($field:ident),*) => {
+            $(if actual.$field != expected.$field {
+                out.push(format!("{} is {:02x?}, expected {:02x?}", stringify!($field), actual.$field, expected.$field));
+            })*
+        };
+    }
+    compare!(a, b, c, d, e, h, l, flags, sp, pc, inte, halted, written, cycles);
+    for (address, value) in expected.memory.iter() {
+        let actual = actual.memory.get(address).cloned().unwrap_or(0);
+        if actual != *value {
+            out.push(format!("({:04x}) is {:02x}, expected {:02x}", address, actual, value));
+        }
+    }
+    for (address, value) in actual.memory.iter().filter(|(address, _)| !expected.memory.contains_key(address)) {
+        out.push(format!("({:04x}) written with {:02x}, reference doesn't store there", address, value));
+    }
+    out.join(", ")
+}
+
+/// Runs a case on the CPU, turning a panic into the message it panicked with. The CPU is replaced
+/// afterwards, as the panic will have left bytes of the case in its memory.
+fn try_run_cpu(cpu: &mut CPU, case: &Case) -> Result<State, String> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| run_cpu(cpu, case)));
+    result.map_err(|payload| {
+        *cpu = watched_cpu();
+        payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default()
+    })
+}
+
+fn fails(cpu: &mut CPU, case: &Case) -> bool {
+    try_run_cpu(cpu, case).map_or(true, |state| state != run_reference(case))
+}
+
+/// Shrinks a failing case for as long as it keeps failing.
+fn minimise(cpu: &mut CPU, mut case: Case) -> Case {
+    'shrinking: loop {
+        for candidate in case.shrunk() {
+            if fails(cpu, &candidate) {
+                case = candidate;
+                continue 'shrinking;
+            }
+        }
+        return case;
+    }
+}
+
+/// Fuzzes every documented opcode, returning a minimal reproducer for each one that fails.
+fn fuzz(cases_per_opcode: usize, seed: u64) -> Vec<String> {
+    let mut rng = Rng(seed);
+    let mut cpu = watched_cpu();
+    let mut failures = Vec::new();
+    for opcode in 0..=0xff {
+        if super::disassembler::is_undocumented(opcode) {
+            continue;
+        }
+        for _ in 0..cases_per_opcode {
+            let case = Case::random(opcode, &mut rng);
+            if fails(&mut cpu, &case) {
+                let case = minimise(&mut cpu, case);
+                let difference = match try_run_cpu(&mut cpu, &case) {
+                    Ok(state) => differences(&state, &run_reference(&case)),
+                    Err(message) => format!("panicked: {}", message),
+                };
+                failures.push(format!("{:?}: {}", case, difference));
+                break;
+            }
+        }
+    }
+    failures
+}
+
+#[test]
+fn test_fuzz_against_reference() {
+    let failures = fuzz(300, 0x8080_8080_1974);
+    assert!(failures.is_empty(), "{} opcodes differ from the reference:\n{}", failures.len(), failures.join("\n"));
+}
+
+/// A longer run with a different seed each time: `cargo test --release -- --ignored fuzz_long`.
+#[test]
+#[ignore]
+fn test_fuzz_long() {
+    let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() as u64 | 1;
+    let failures = fuzz(100_000, seed);
+    assert!(failures.is_empty(), "seed {:x}:\n{}", seed, failures.join("\n"));
+}
+
+#[test]
+fn test_stray_store_is_reported() {
+    let case = Case::random(0x00, &mut Rng(1));
+    let expected = run_reference(&case);
+    let mut actual = expected.clone();
+    let address = (0..=0xffff).find(|address| !expected.memory.contains_key(address)).unwrap();
+    actual.memory.insert(address, 0x5a);
+    assert_eq!(differences(&actual, &expected),
+               format!("({:04x}) written with 5a, reference doesn't store there", address));
+}
+
+#[test]
+fn test_reference_decodes_every_opcode() {
+    for opcode in 0..=0xff {
+        decode(opcode);
+    }
+    assert_eq!(decode(0x76), Op::Hlt);
+    assert_eq!(decode(0x86), Op::Alu(Alu::Add, Some(Reg::M)));
+    assert_eq!(decode(0xde), Op::Alu(Alu::Sbb, None));
+    assert_eq!(decode(0xd9), Op::Ret(Cond::Always));
+    assert_eq!(decode(0xfd), Op::Call(Cond::Always));
+    assert_eq!(decode(0xcb), Op::Jmp(Cond::Always));
+    assert_eq!(decode(0xf5), Op::Push(Pair::Psw));
+}
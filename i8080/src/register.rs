@@ -0,0 +1,267 @@
+use super::cpu::Byte;
+use super::cpu::Word;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+
+/// The 8-bit registers, numbered by where they sit in the register file.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Reg8 {
+    F = 0,
+    A = 1,
+    C = 2,
+    B = 3,
+    E = 4,
+    D = 5,
+    L = 6,
+    H = 7,
+    M = 8,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Reg16 {
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+    PSW,
+}
+
+/* The flags sit in F where PUSH PSW puts them: S Z K AC 0 P V CY from bit 7 down. On an 8080,
+   bit 1 is always pushed as 1 and bits 3 and 5 as 0. The 8085 uses bits 1 and 5 for its
+   undocumented V and K flags, which the 8080 never sets. */
+
+#[derive(Debug, Copy, Clone)]
+pub enum Flag {
+    Carry = 0b00000001,
+    /// 8085 only: two's complement overflow.
+    Overflow = 0b00000010,
+    Parity = 0b00000100,
+    AuxCarry = 0b00010000,
+    /// 8085 only: the sign carry used by JK and JNK, also called X5.
+    Underflow = 0b00100000,
+    Zero = 0b01000000,
+    Sign = 0b10000000,
+}
+
+/// Shows the flags set in an F register, from `Register::flag_names`.
+pub struct FlagNames(Byte);
+
+impl fmt::Display for FlagNames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let names = [(Flag::Sign, 'S'), (Flag::Zero, 'Z'), (Flag::AuxCarry, 'A'), (Flag::Parity, 'P'),
+                     (Flag::Carry, 'C'), (Flag::Overflow, 'V'), (Flag::Underflow, 'K')];
+        for &(flag, name) in names.iter() {
+            if self.0 & flag as Byte != 0 {
+                write!(f, "{},", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+use Reg16::{BC, DE, HL, PC, SP, PSW};
+use Reg8::{F, M};
+
+/* The pairs are kept as four words, PSW BC DE HL, which on a little-endian host puts each pair's
+   low byte first: F A C B E D L H. A Reg8 is its index into those bytes, so a register picked
+   out of an instruction at run time is read without a branch. A big-endian host has each pair's
+   bytes the other way round, so there the index flips its lowest bit. */
+union Pairs {
+    words: [Word; 4],
+    bytes: [Byte; 8],
+}
+
+const fn byte_index(register: Reg8) -> usize {
+    register as usize ^ cfg!(target_endian = "big") as usize
+}
+
+/// The register file, indexed by `Reg8` and `Reg16`.
+pub struct Register {
+    pairs: Pairs,
+    pub sp: Word,
+    pub pc: Word,
+}
+
+impl Register {
+    pub fn new() -> Self {
+        Register {
+            pairs: Pairs { words: [0; 4] },
+            sp: 0,
+            pc: 0,
+        }
+    }
+
+    pub fn get_flag(&self, flag: Flag) -> bool {
+        (self[F] & flag as Byte) == flag as Byte
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, set: bool) {
+        if set {
+            self[F] |= flag as Byte;
+        } else {
+            self[F] &= !(flag as Byte);
+        }
+    }
+
+    pub fn get_flags_as_u8(&self) -> u8 {
+        self[F]
+    }
+
+    /// The flags that are set, as e.g. `S,Z,C,`.
+    pub fn flag_names(&self) -> FlagNames {
+        FlagNames(self[F])
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn get_flags_as_string(&self) -> String {
+        self.flag_names().to_string()
+    }
+}
+
+impl Default for Register {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<Reg8> for Register {
+    type Output = Byte;
+    fn index(&self, register: Reg8) -> &Self::Output {
+        match register {
+            M => panic!("Cannot access memory through use of fake `M` register!"),
+            _ => unsafe { &self.pairs.bytes[byte_index(register)] },
+        }
+    }
+}
+
+impl IndexMut<Reg8> for Register {
+    fn index_mut(&mut self, register: Reg8) -> &mut Byte {
+        match register {
+            M => panic!("Cannot access memory through use of fake `M` register!"),
+            _ => unsafe { &mut self.pairs.bytes[byte_index(register)] },
+        }
+    }
+}
+
+impl Index<Reg16> for Register {
+    type Output = Word;
+    fn index(&self, register: Reg16) -> &Self::Output {
+        unsafe {
+            match register {
+                PSW => &self.pairs.words[0],
+                BC => &self.pairs.words[1],
+                DE => &self.pairs.words[2],
+                HL => &self.pairs.words[3],
+                SP => &self.sp,
+                PC => &self.pc,
+            }
+        }
+    }
+}
+
+impl IndexMut<Reg16> for Register {
+    fn index_mut(&mut self, register: Reg16) -> &mut Word {
+        unsafe {
+            match register {
+                PSW => &mut self.pairs.words[0],
+                BC => &mut self.pairs.words[1],
+                DE => &mut self.pairs.words[2],
+                HL => &mut self.pairs.words[3],
+                SP => &mut self.sp,
+                PC => &mut self.pc,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Reg8::{B, C, D, E, H, L};
+    #[test]
+    fn test_get_word_registers() {
+        let mut reg = Register::new();
+        reg[B] = 0xAA;
+        reg[C] = 0xBB;
+        assert_eq!(reg[BC], 0xAABB);
+
+        reg[D] = 0xCC;
+        reg[E] = 0xDD;
+        assert_eq!(reg[DE], 0xCCDD);
+
+        reg[H] = 0xEE;
+        reg[L] = 0xFF;
+        assert_eq!(reg[HL], 0xEEFF);
+    }
+
+    #[test]
+    fn test_set_word_registers() {
+        let mut reg = Register::new();
+
+        reg[BC] = 0xAABB;
+        assert_eq!(reg[B], 0xAA);
+        assert_eq!(reg[C], 0xBB);
+
+        reg[DE] = 0xCCDD;
+        assert_eq!(reg[D], 0xCC);
+        assert_eq!(reg[E], 0xDD);
+
+        reg[HL] = 0xEEFF;
+        assert_eq!(reg[H], 0xEE);
+        assert_eq!(reg[L], 0xFF);
+    }
+
+    #[test]
+    fn test_flag_names() {
+        let mut reg = Register::new();
+        assert_eq!(reg.flag_names().to_string(), "");
+
+        reg.set_flag(Flag::Carry, true);
+        reg.set_flag(Flag::Zero, true);
+        reg.set_flag(Flag::Underflow, true);
+        assert_eq!(reg.flag_names().to_string(), "Z,C,K,");
+        #[cfg(feature = "alloc")]
+        assert_eq!(reg.get_flags_as_string(), "Z,C,K,");
+    }
+
+    #[test]
+    fn test_get_flags() {
+        let mut reg = Register::new();
+
+        reg.set_flag(Flag::Carry, true);
+        assert_eq!(reg.get_flag(Flag::Carry), true);
+
+        reg.set_flag(Flag::Sign, true);
+        assert_eq!(reg[F], 0b10000001);
+        assert_eq!(reg.get_flag(Flag::Sign), true);
+
+        reg.set_flag(Flag::Carry, false);
+
+        assert_eq!(reg.get_flag(Flag::Carry), false);
+        assert_eq!(reg.get_flag(Flag::Sign), true);
+    }
+
+    #[test]
+    fn test_set_flags() {
+        let mut reg = Register::new();
+
+        reg.set_flag(Flag::Carry, true);
+
+        assert_eq!(reg[F], Flag::Carry as Byte);
+
+        reg.set_flag(Flag::Carry, false);
+
+        assert_eq!(reg[F], 0b00000000);
+
+        reg.set_flag(Flag::Sign, true);
+
+        assert_eq!(reg[F], Flag::Sign as Byte);
+
+        reg.set_flag(Flag::Sign, false);
+
+        assert_eq!(reg[F], 0b00000000);
+    }
+}
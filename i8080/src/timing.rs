@@ -0,0 +1,340 @@
+use core::fmt;
+
+use super::cpu::{Byte, Word, CPU};
+use super::i8085::Variant;
+use super::register::Flag::{Carry, Overflow, Parity, Sign, Underflow, Zero};
+
+/* Instruction timings from the Intel datasheets. Each instruction is a sequence of machine cycles
+   (M-states): an opcode fetch followed by up to four 3-state bus transfers, with a few states
+   tacked on here and there. The tables give the total for each opcode, with conditional
+   instructions at their not-taken count, and `machine_cycles` breaks a count down the way the
+   chip spends it. The two are checked against each other by the tests. */
+
+const CYCLES_8080: [u8; 256] = [
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x00..0x0f
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x10..0x1f
+    4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 16, 5, 5, 5, 7, 4, // 0x20..0x2f
+    4, 10, 13, 5, 10, 10, 10, 4, 4, 10, 13, 5, 5, 5, 7, 4, // 0x30..0x3f
+
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x40..0x4f
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5,
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5,
+    7, 7, 7, 7, 7, 7, 7, 7, 5, 5, 5, 5, 5, 5, 7, 5,
+
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x80..0x8f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, // 0xc0..0xcf
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11,
+    5, 10, 10, 18, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11,
+    5, 10, 10, 4, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11,
+];
+
+const CYCLES_8085: [u8; 256] = [
+    4, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4, 7, 4, // 0x00..0x0f
+    7, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4, 7, 4, // 0x10..0x1f
+    4, 10, 16, 6, 4, 4, 7, 4, 10, 10, 16, 6, 4, 4, 7, 4, // 0x20..0x2f
+    4, 10, 13, 6, 10, 10, 10, 4, 10, 10, 13, 6, 4, 4, 7, 4, // 0x30..0x3f
+
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x40..0x4f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    7, 7, 7, 7, 7, 7, 5, 7, 4, 4, 4, 4, 4, 4, 7, 4,
+
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x80..0x8f
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4,
+
+    6, 10, 7, 10, 9, 12, 7, 12, 6, 10, 7, 6, 9, 18, 7, 12, // 0xc0..0xcf
+    6, 10, 7, 10, 9, 12, 7, 12, 6, 10, 7, 10, 9, 7, 7, 12,
+    6, 10, 7, 16, 9, 12, 7, 12, 6, 6, 7, 4, 9, 10, 7, 12,
+    6, 10, 7, 4, 9, 12, 7, 12, 6, 6, 7, 4, 9, 7, 7, 12,
+];
+
+/// Whether an opcode only sometimes does all its work.
+pub const fn is_conditional(variant: Variant, opcode: Byte) -> bool {
+    let jump_or_call = opcode & 0b1100_0001 == 0b1100_0000 && opcode & 0b110 != 0b110;
+    match variant {
+        // an 8080 always jumps at its conditional jumps' full length
+        Variant::I8080 => jump_or_call && opcode & 0b111 != 0b010,
+        Variant::I8085 => jump_or_call || matches!(opcode, 0xcb | 0xdd | 0xfd),
+    }
+}
+
+/// T-states taken by an instruction.
+pub const fn cycles(variant: Variant, opcode: Byte, taken: bool) -> Word {
+    let table = match variant {
+        Variant::I8080 => &CYCLES_8080,
+        Variant::I8085 => &CYCLES_8085,
+    };
+    let base = table[opcode as usize] as Word;
+    if !taken || !is_conditional(variant, opcode) {
+        return base;
+    }
+    base + match (variant, opcode & 0b111) {
+        (_, 0b000) => 6,            // returns
+        (Variant::I8080, _) => 6,   // calls
+        (_, 0b010) => 3,            // the 8085's jumps read their address only when taken
+        (_, 0b100) => 9,            // the 8085's calls
+        _ => match opcode {
+            0xcb => 6,              // RSTV
+            _ => 3,                 // JNK and JK
+        },
+    }
+}
+
+/// T-states to acknowledge an interrupt and push the PC.
+pub fn interrupt_cycles(variant: Variant) -> u64 {
+    match variant {
+        Variant::I8080 => 11,
+        Variant::I8085 => 12,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum MachineCycle {
+    /// M1, reading the opcode and decoding it, with its length in T-states.
+    Fetch(u8),
+    MemoryRead,
+    MemoryWrite,
+    StackRead,
+    StackWrite,
+    Input,
+    Output,
+    /// States where the bus isn't used, like DAD working on the high byte.
+    Idle(u8),
+    /// Stopping at HLT, up to where the chip waits.
+    Halt(u8),
+}
+
+impl MachineCycle {
+    pub fn states(self) -> u8 {
+        match self {
+            MachineCycle::Fetch(states) | MachineCycle::Idle(states) | MachineCycle::Halt(states) => states,
+            _ => 3,
+        }
+    }
+}
+
+impl fmt::Display for MachineCycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            MachineCycle::Fetch(states) => write!(f, "M1:{}", states),
+            MachineCycle::MemoryRead => write!(f, "MR:3"),
+            MachineCycle::MemoryWrite => write!(f, "MW:3"),
+            MachineCycle::StackRead => write!(f, "SR:3"),
+            MachineCycle::StackWrite => write!(f, "SW:3"),
+            MachineCycle::Input => write!(f, "IN:3"),
+            MachineCycle::Output => write!(f, "OUT:3"),
+            MachineCycle::Idle(states) => write!(f, "BI:{}", states),
+            MachineCycle::Halt(states) => write!(f, "HLT:{}", states),
+        }
+    }
+}
+
+/// An instruction's machine cycles, at most six of them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MachineCycles {
+    cycles: [MachineCycle; 6],
+    len: usize,
+}
+
+impl MachineCycles {
+    fn new(fetch: u8) -> Self {
+        Self { cycles: [MachineCycle::Fetch(fetch); 6], len: 1 }
+    }
+
+    fn then(mut self, cycles: &[MachineCycle]) -> Self {
+        self.cycles[self.len..self.len + cycles.len()].copy_from_slice(cycles);
+        self.len += cycles.len();
+        self
+    }
+
+    pub fn as_slice(&self) -> &[MachineCycle] {
+        &self.cycles[..self.len]
+    }
+
+    pub fn states(&self) -> Word {
+        self.as_slice().iter().map(|cycle| Word::from(cycle.states())).sum()
+    }
+}
+
+impl fmt::Display for MachineCycles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (i, cycle) in self.as_slice().iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", cycle)?;
+        }
+        Ok(())
+    }
+}
+
+/// How an instruction spends its T-states.
+pub fn machine_cycles(variant: Variant, opcode: Byte, taken: bool) -> MachineCycles {
+    use MachineCycle::*;
+    let i8085 = variant == Variant::I8085;
+    let taken = taken || !is_conditional(variant, opcode);
+    // the long fetch of instructions that do some work before or instead of using the bus
+    let (short, long) = (MachineCycles::new(4), MachineCycles::new(if i8085 { 6 } else { 5 }));
+    let read_address = [MemoryRead, MemoryRead];
+
+    match opcode {
+        0x76 => short.then(&[Halt(if i8085 { 1 } else { 3 })]),
+        0x40..=0x7f if opcode & 0b111 == 0b110 => short.then(&[MemoryRead]),
+        0x70..=0x77 => short.then(&[MemoryWrite]),
+        0x40..=0x7f => if i8085 { short } else { long },
+        0x80..=0xbf if opcode & 0b111 == 0b110 => short.then(&[MemoryRead]),
+        0x80..=0xbf => short,
+
+        0x01 | 0x11 | 0x21 | 0x31 => short.then(&read_address),
+        0x02 | 0x12 => short.then(&[MemoryWrite]),
+        0x0a | 0x1a => short.then(&[MemoryRead]),
+        0x03 | 0x13 | 0x23 | 0x33 | 0x0b | 0x1b | 0x2b | 0x3b => long,
+        0x34 | 0x35 => short.then(&[MemoryRead, MemoryWrite]),
+        0x04..=0x3d if opcode & 0b110 == 0b100 => if i8085 { short } else { long },
+        0x36 => short.then(&[MemoryRead, MemoryWrite]),
+        0x06 | 0x0e | 0x16 | 0x1e | 0x26 | 0x2e | 0x3e => short.then(&[MemoryRead]),
+        0x09 | 0x19 | 0x29 | 0x39 => short.then(&[Idle(3), Idle(3)]),
+        0x22 => short.then(&[MemoryRead, MemoryRead, MemoryWrite, MemoryWrite]),
+        0x2a => short.then(&[MemoryRead, MemoryRead, MemoryRead, MemoryRead]),
+        0x32 => short.then(&[MemoryRead, MemoryRead, MemoryWrite]),
+        0x3a => short.then(&[MemoryRead, MemoryRead, MemoryRead]),
+
+        // the 8085's additions
+        0x08 | 0x18 if i8085 => short.then(&[Idle(3), Idle(3)]),
+        0x10 if i8085 => short.then(&[Idle(3)]),
+        0x28 | 0x38 if i8085 => short.then(&[MemoryRead, Idle(3)]),
+        0xd9 if i8085 => short.then(&[MemoryWrite, MemoryWrite]),
+        0xed if i8085 => short.then(&[MemoryRead, MemoryRead]),
+        0xcb if i8085 => if taken { long.then(&[StackWrite, StackWrite]) } else { long },
+        0xdd | 0xfd if i8085 => if taken { short.then(&read_address) } else { short.then(&[MemoryRead]) },
+
+        0x00..=0x3f => short,
+
+        // returns
+        0xc9 | 0xd9 => short.then(&[StackRead, StackRead]),
+        0xc0..=0xff if opcode & 0b111 == 0b000 => if taken { long.then(&[StackRead, StackRead]) } else { long },
+        // jumps
+        0xc3 | 0xcb => short.then(&read_address),
+        0xc0..=0xff if opcode & 0b111 == 0b010 => {
+            if taken { short.then(&read_address) } else { short.then(&[MemoryRead]) }
+        },
+        // calls
+        0xcd | 0xdd | 0xed | 0xfd => long.then(&[MemoryRead, MemoryRead, StackWrite, StackWrite]),
+        0xc0..=0xff if opcode & 0b111 == 0b100 => {
+            if taken {
+                long.then(&[MemoryRead, MemoryRead, StackWrite, StackWrite])
+            } else if i8085 {
+                long.then(&[MemoryRead])
+            } else {
+                long.then(&read_address)
+            }
+        },
+        // RST
+        0xc0..=0xff if opcode & 0b111 == 0b111 => long.then(&[StackWrite, StackWrite]),
+        0xc1 | 0xd1 | 0xe1 | 0xf1 => short.then(&[StackRead, StackRead]),
+        0xc5 | 0xd5 | 0xe5 | 0xf5 => long.then(&[StackWrite, StackWrite]),
+        0xc6 | 0xce | 0xd6 | 0xde | 0xe6 | 0xee | 0xf6 | 0xfe => short.then(&[MemoryRead]),
+        0xd3 => short.then(&[MemoryRead, Output]),
+        0xdb => short.then(&[MemoryRead, Input]),
+        0xe3 if i8085 => short.then(&[StackRead, StackRead, StackWrite, StackWrite]),
+        // the 8080 takes two more states finishing the exchange
+        0xe3 => short.then(&[StackRead, StackRead, StackWrite, StackWrite, Idle(2)]),
+        0xe9 | 0xf9 => long,
+        _ => short,
+    }
+}
+
+impl CPU {
+    /// Whether the condition of a conditional instruction holds.
+    pub(super) fn condition_met(&self, opcode: Byte) -> bool {
+        match opcode {
+            0xcb => self.reg.get_flag(Overflow),
+            0xdd => !self.reg.get_flag(Underflow),
+            0xfd => self.reg.get_flag(Underflow),
+            _ => {
+                let flag = match (opcode >> 4) & 0b11 {
+                    0 => self.reg.get_flag(Zero),
+                    1 => self.reg.get_flag(Carry),
+                    2 => self.reg.get_flag(Parity),
+                    _ => self.reg.get_flag(Sign),
+                };
+                flag == (opcode & 0b1000 != 0)
+            },
+        }
+    }
+
+    /// The machine cycles the next instruction is going to take, for tracing.
+    pub fn next_machine_cycles(&self) -> MachineCycles {
+        let opcode = self.fetch();
+        machine_cycles(self.variant, opcode, self.condition_met(opcode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::NullIO;
+    use crate::register::Reg16::{PC, SP};
+    use crate::register::Reg8::F;
+
+    #[test]
+    fn test_machine_cycles_add_up() {
+        for &variant in &[Variant::I8080, Variant::I8085] {
+            for opcode in 0..=0xff {
+                for &taken in &[false, true] {
+                    let breakdown = machine_cycles(variant, opcode, taken);
+                    assert_eq!(breakdown.states(), cycles(variant, opcode, taken),
+                               "{:?} {:02x} taken={}: {}", variant, opcode, taken, breakdown);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_conditional_cycles() {
+        let v = Variant::I8080;
+        assert_eq!((cycles(v, 0xc4, false), cycles(v, 0xc4, true)), (11, 17));
+        assert_eq!((cycles(v, 0xc0, false), cycles(v, 0xc0, true)), (5, 11));
+        assert_eq!((cycles(v, 0xc2, false), cycles(v, 0xc2, true)), (10, 10));
+        assert_eq!((cycles(v, 0xcd, false), cycles(v, 0xcd, true)), (17, 17));
+
+        let v = Variant::I8085;
+        assert_eq!((cycles(v, 0xc4, false), cycles(v, 0xc4, true)), (9, 18));
+        assert_eq!((cycles(v, 0xc0, false), cycles(v, 0xc0, true)), (6, 12));
+        assert_eq!((cycles(v, 0xc2, false), cycles(v, 0xc2, true)), (7, 10));
+        assert_eq!((cycles(v, 0xcb, false), cycles(v, 0xcb, true)), (6, 12));
+        assert_eq!((cycles(v, 0xfd, false), cycles(v, 0xfd, true)), (7, 10));
+
+        assert_eq!(machine_cycles(Variant::I8080, 0xcc, true).to_string(), "M1:5 MR:3 MR:3 SW:3 SW:3");
+        assert_eq!(machine_cycles(Variant::I8080, 0xcc, false).to_string(), "M1:5 MR:3 MR:3");
+    }
+
+    /// Runs every opcode on a CPU set up so that its condition holds, or doesn't, and checks it
+    /// takes as long as the table says.
+    #[test]
+    fn test_every_opcode_takes_its_cycles() {
+        let mut io = NullIO;
+        for &variant in &[Variant::I8080, Variant::I8085] {
+            for opcode in 0..=0xff {
+                for &flags in &[0x00, 0xff] {
+                    let mut cpu = CPU::with_variant(variant);
+                    cpu.memory.load(0x100, &[opcode, 0x00, 0x02]);
+                    cpu.reg[PC] = 0x100;
+                    cpu.reg[SP] = 0x1000;
+                    cpu.reg[F] = flags;
+                    let taken = cpu.condition_met(opcode);
+                    assert_eq!(cpu.next_machine_cycles().states(), cycles(variant, opcode, taken));
+                    assert_eq!(cpu.tick(&mut io), u64::from(cycles(variant, opcode, taken)),
+                               "{:?} opcode {:02x} with flags {:02x}", variant, opcode, flags);
+                }
+            }
+        }
+    }
+}
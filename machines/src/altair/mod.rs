@@ -0,0 +1,189 @@
+pub mod sio;
+
+use std::io::Write;
+
+use i8080::blocks::{self, BlockCache};
+use i8080::io::IO;
+use self::sio::Serial;
+
+/* The MITS Altair 8800: an 8080 at 2 MHz with nothing but RAM, a front panel and whatever cards
+   are plugged into the bus. This one is fully populated with 64K of RAM and has an 88-2SIO serial
+   card at ports 0x10/0x11 for the terminal. Programs such as Altair BASIC are loaded straight into
+   memory, as if toggled in from the front panel or read from paper tape. */
+
+pub const NAME: &str = "altair";
+pub const DESCRIPTION: &str = "MITS Altair 8800 with 64K RAM and an 88-2SIO serial terminal";
+
+pub struct Altair8800 {
+    pub cpu: i8080::cpu::CPU,
+    io: AltairIO,
+
+    pub instructions: u64,
+    pub cycles: u64,
+
+    /// When set, a line describing the CPU state is written before every instruction.
+    pub trace: Option<Box<dyn Write>>,
+    /// When set, and not tracing, code runs from a cache of decoded blocks.
+    pub blocks: Option<BlockCache>,
+}
+
+impl Altair8800 {
+    pub const CLOCK_HZ: u64 = 2_000_000;
+
+    pub fn new() -> Self {
+        Self {
+            cpu: i8080::cpu::CPU::new(),
+            io: AltairIO::new(),
+            instructions: 0,
+            cycles: 0,
+            trace: None,
+            blocks: None,
+        }
+    }
+
+    /// Deposits `program` into memory starting at `address`.
+    pub fn load(&mut self, address: u16, program: &[u8]) -> Result<(), String> {
+        if address as usize + program.len() > 0x10000 {
+            return Err(format!(
+                "A {} byte program doesn't fit in memory at {:04x}", program.len(), address));
+        }
+        self.cpu.memory.load(address as usize, program);
+        Ok(())
+    }
+
+    /// Like the front panel's EXAMINE switch: sets the program counter to `address`.
+    pub fn examine(&mut self, address: u16) {
+        self.cpu.reg.pc = address;
+    }
+
+    /// The upper eight address switches, which programs read from port 0xFF.
+    pub fn set_sense_switches(&mut self, switches: u8) {
+        self.io.sense_switches = switches;
+    }
+
+    /// Runs for at least `cycles` clock cycles.
+    pub fn run(&mut self, cycles: u64) -> Result<(), String> {
+        let mut cycles_spent = 0;
+        while cycles_spent < cycles {
+            if let Some(trace) = self.trace.as_mut() {
+                if let Err(e) = writeln!(trace, "{}", self.cpu.trace_line(self.cycles)) {
+                    eprintln!("Stopping trace: {}", e);
+                    self.trace = None;
+                }
+            }
+
+            let blocks = if self.trace.is_none() { self.blocks.as_mut() } else { None };
+            let ran = blocks::run(blocks, &mut self.cpu, &mut self.io, cycles - cycles_spent).map_err(|e| e.to_string())?;
+            cycles_spent += ran.cycles;
+            self.instructions += ran.instructions;
+            self.cycles += ran.cycles;
+        }
+        Ok(())
+    }
+
+    /// Characters typed at the terminal.
+    pub fn type_keys(&mut self, bytes: &[u8]) {
+        self.io.serial.receive(bytes);
+    }
+
+    pub fn keys_pending(&self) -> usize {
+        self.io.serial.pending()
+    }
+
+    /// Characters the program has sent to the terminal since the last call.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.io.serial.take_sent()
+    }
+}
+
+impl Default for Altair8800 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct AltairIO {
+    serial: Serial,
+    sense_switches: u8,
+}
+
+impl AltairIO {
+    const SERIAL_STATUS: u8 = 0x10;
+    const SERIAL_DATA: u8 = 0x11;
+    const SENSE_SWITCHES: u8 = 0xFF;
+
+    pub fn new() -> Self {
+        Self { serial: Serial::new(), sense_switches: 0 }
+    }
+}
+
+impl Default for AltairIO {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IO for AltairIO {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            Self::SERIAL_STATUS => self.serial.status(),
+            Self::SERIAL_DATA => self.serial.read(),
+            Self::SENSE_SWITCHES => self.sense_switches,
+            // an empty slot on the bus
+            _ => 0xFF,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            Self::SERIAL_STATUS => self.serial.control(value),
+            // the terminal is 7-bit, some programs send mark parity
+            Self::SERIAL_DATA => self.serial.write(value & 0x7F),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Echoes characters back in upper case until it reads a '.', then halts in a loop:
+       0000 IN 10; ANI 01; JZ 0000; IN 11; CPI '.'; JZ 001a; CPI 'a'; JC 0015; SUI 20;
+       0015 OUT 11; JMP 0000; 001a JMP 001a */
+    const ECHO: [u8; 29] = [
+        0xdb, 0x10, 0xe6, 0x01, 0xca, 0x00, 0x00, 0xdb, 0x11, 0xfe, b'.', 0xca, 0x1a, 0x00,
+        0xfe, b'a', 0xda, 0x15, 0x00, 0xd6, 0x20, 0xd3, 0x11, 0xc3, 0x00, 0x00, 0xc3, 0x1a, 0x00,
+    ];
+
+    #[test]
+    fn test_serial_echo() {
+        let mut altair = Altair8800::new();
+        altair.load(0, &ECHO).unwrap();
+
+        altair.run(10_000).unwrap();
+        assert_eq!(altair.take_output(), b"");
+
+        altair.type_keys(b"Hi there.ignored");
+        altair.run(10_000).unwrap();
+        assert_eq!(altair.take_output(), b"HI THERE");
+        assert_eq!(altair.keys_pending(), "ignored".len());
+        assert_eq!(altair.cpu.reg.pc, 0x1a);
+    }
+
+    #[test]
+    fn test_sense_switches_and_empty_ports() {
+        let mut io = AltairIO::new();
+        assert_eq!(io.input(0xFF), 0);
+        io.sense_switches = 0b1010_0000;
+        assert_eq!(io.input(0xFF), 0b1010_0000);
+        assert_eq!(io.input(0x00), 0xFF);
+    }
+
+    #[test]
+    fn test_load_bounds() {
+        let mut altair = Altair8800::new();
+        assert!(altair.load(0xFFFF, &[0x76]).is_ok());
+        assert!(altair.load(0xFFFF, &[0x76, 0x76]).is_err());
+    }
+}
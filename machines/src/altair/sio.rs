@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+
+/* One channel of the MITS 88-2SIO serial card, a Motorola 6850 ACIA. The first port is the
+   control/status register and the second the data register. Software polls the status for
+   "receive data register full" before reading a character and "transmit data register empty"
+   before sending one. The card never makes the CPU wait - with nothing received, a read just
+   returns the last character again. */
+
+#[derive(Debug, Default)]
+pub struct Serial {
+    received: VecDeque<u8>,
+    last: u8,
+    sent: Vec<u8>,
+}
+
+impl Serial {
+    pub const RECEIVE_FULL: u8 = 0b0000_0001;
+    pub const TRANSMIT_EMPTY: u8 = 0b0000_0010;
+    const MASTER_RESET: u8 = 0b0000_0011;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> u8 {
+        let receive = if self.received.is_empty() { 0 } else { Self::RECEIVE_FULL };
+        // output is taken as soon as it is written, so the transmitter is always ready
+        receive | Self::TRANSMIT_EMPTY
+    }
+
+    pub fn control(&mut self, value: u8) {
+        if value & Self::MASTER_RESET == Self::MASTER_RESET {
+            self.received.clear();
+        }
+    }
+
+    pub fn read(&mut self) -> u8 {
+        if let Some(byte) = self.received.pop_front() {
+            self.last = byte;
+        }
+        self.last
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.sent.push(value);
+    }
+
+    /// Queues characters typed at the terminal.
+    pub fn receive(&mut self, bytes: &[u8]) {
+        self.received.extend(bytes);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.received.len()
+    }
+
+    /// Characters sent by the program since the last call.
+    pub fn take_sent(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receive() {
+        let mut serial = Serial::new();
+        assert_eq!(serial.status(), Serial::TRANSMIT_EMPTY);
+
+        serial.receive(b"AB");
+        assert_eq!(serial.status(), Serial::RECEIVE_FULL | Serial::TRANSMIT_EMPTY);
+        assert_eq!(serial.read(), b'A');
+        assert_eq!(serial.read(), b'B');
+        assert_eq!(serial.status() & Serial::RECEIVE_FULL, 0);
+        assert_eq!(serial.read(), b'B');
+    }
+
+    #[test]
+    fn test_reset_and_send() {
+        let mut serial = Serial::new();
+        serial.receive(b"junk");
+        serial.control(0x03);
+        assert_eq!(serial.pending(), 0);
+
+        serial.control(0x15);
+        serial.write(b'O');
+        serial.write(b'K');
+        assert_eq!(serial.take_sent(), b"OK");
+        assert_eq!(serial.take_sent(), b"");
+    }
+}
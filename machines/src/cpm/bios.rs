@@ -0,0 +1,223 @@
+use super::disk::SECTORS_PER_TRACK;
+use super::port;
+
+/* CP/M keeps everything hardware specific in the BIOS, a jump table of 17 entry points at the top
+   of memory. Rather than running the BIOS that came on a disk, which was written for somebody
+   else's hardware, a BIOS is put together here at boot: each entry point is a few instructions
+   that pass its arguments to the console or the disk controller through I/O ports, followed by
+   the tables the BDOS needs to find its way around an 8" disk. */
+
+/// Bytes from the BIOS base: code in the first page, disk tables after it.
+pub const SIZE: u16 = 0x2C0;
+
+const DPBASE: u16 = 0x100;
+const XLT: u16 = 0x140;
+const DPB: u16 = 0x160;
+const DIRBUF: u16 = 0x180;
+const CSV: u16 = 0x200;
+const ALV: u16 = 0x240;
+
+const DRIVES: u16 = 4;
+const CSV_SIZE: u16 = 16;
+const ALV_SIZE: u16 = 31;
+
+/// The standard sector skew of 6 for 8" disks.
+const SKEW: [u8; SECTORS_PER_TRACK as usize] = [
+    1, 7, 13, 19, 25, 5, 11, 17, 23, 3, 9, 15, 21, 2, 8, 14, 20, 26, 6, 12, 18, 24, 4, 10, 16, 22,
+];
+
+/// The disk parameter block for IBM 3740: 26 sectors a track, 1K blocks, 243 blocks, 64
+/// directory entries and two reserved system tracks.
+const IBM_3740: [u8; 15] = [26, 0, 3, 7, 0, 242, 0, 63, 0, 0xC0, 0x00, 16, 0, 2, 0];
+
+/// A tiny assembler: just enough to lay out bytes and know where they are.
+struct Asm {
+    origin: u16,
+    bytes: Vec<u8>,
+}
+
+impl Asm {
+    fn here(&self) -> u16 {
+        self.origin + self.bytes.len() as u16
+    }
+
+    fn op(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn op_word(&mut self, opcode: u8, word: u16) {
+        self.bytes.extend_from_slice(&[opcode, word as u8, (word >> 8) as u8]);
+    }
+
+    fn word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&[word as u8, (word >> 8) as u8]);
+    }
+
+    fn pad_to(&mut self, offset: u16) {
+        assert!(self.bytes.len() <= offset as usize, "BIOS code overran its space");
+        self.bytes.resize(offset as usize, 0);
+    }
+}
+
+const JMP: u8 = 0xc3;
+const JZ: u8 = 0xca;
+const LXI_SP: u8 = 0x31;
+const LXI_H: u8 = 0x21;
+const LXI_D: u8 = 0x11;
+const LDA: u8 = 0x3a;
+const MVI_A: u8 = 0x3e;
+const MVI_C: u8 = 0x0e;
+const MVI_H: u8 = 0x26;
+const IN: u8 = 0xdb;
+const OUT: u8 = 0xd3;
+const ANI: u8 = 0xe6;
+const MOV_A_B: u8 = 0x78;
+const MOV_A_C: u8 = 0x79;
+const MOV_C_A: u8 = 0x4f;
+const MOV_L_C: u8 = 0x69;
+const MOV_L_M: u8 = 0x6e;
+const ORA_A: u8 = 0xb7;
+const DAD_B: u8 = 0x09;
+const DAD_D: u8 = 0x19;
+const DAD_H: u8 = 0x29;
+const XCHG: u8 = 0xeb;
+const RNZ: u8 = 0xc0;
+const RET: u8 = 0xc9;
+
+/// The BIOS for a system whose CCP starts at `ccp`, to be loaded at `base`.
+pub fn build(base: u16, ccp: u16) -> Vec<u8> {
+    let mut asm = Asm { origin: base, bytes: Vec::new() };
+    // the jump table is filled in once the entry points are known
+    asm.pad_to(17 * 3);
+    let mut entries = Vec::new();
+
+    // WBOOT and BOOT: the loader port reloads the CCP and BDOS and sets up page zero
+    let wboot = asm.here();
+    asm.op_word(LXI_SP, 0x0080);
+    asm.op(&[MVI_A, 1]);
+    let gocpm = asm.here();
+    asm.op(&[OUT, port::LOADER]);
+    asm.op_word(LDA, 0x0004);
+    asm.op(&[MOV_C_A]);
+    asm.op_word(JMP, ccp);
+    let boot = asm.here();
+    asm.op_word(LXI_SP, 0x0080);
+    asm.op(&[MVI_A, 0]);
+    asm.op_word(JMP, gocpm);
+    entries.push(boot);
+    entries.push(wboot);
+
+    // CONST
+    entries.push(asm.here());
+    asm.op(&[IN, port::CONSOLE_STATUS, RET]);
+
+    // CONIN
+    let conin = asm.here();
+    entries.push(conin);
+    asm.op(&[IN, port::CONSOLE_STATUS, ORA_A]);
+    asm.op_word(JZ, conin);
+    asm.op(&[IN, port::CONSOLE_DATA, ANI, 0x7F, RET]);
+
+    // CONOUT
+    entries.push(asm.here());
+    asm.op(&[MOV_A_C, OUT, port::CONSOLE_DATA, RET]);
+
+    // LIST and PUNCH go nowhere, READER is always at end of file
+    let nowhere = asm.here();
+    asm.op(&[RET]);
+    entries.push(nowhere);
+    entries.push(nowhere);
+    entries.push(asm.here());
+    asm.op(&[MVI_A, 0x1A, RET]);
+
+    // HOME falls through into SETTRK
+    entries.push(asm.here());
+    asm.op(&[MVI_C, 0]);
+    let settrk = asm.here();
+    asm.op(&[MOV_A_C, OUT, port::TRACK, RET]);
+
+    // SELDSK returns the drive's disk parameter header, or 0 if nothing is mounted
+    entries.push(asm.here());
+    asm.op_word(LXI_H, 0x0000);
+    asm.op(&[MOV_A_C, OUT, port::DRIVE, IN, port::DRIVE, ORA_A, RNZ]);
+    asm.op(&[MOV_L_C, DAD_H, DAD_H, DAD_H, DAD_H]);
+    asm.op_word(LXI_D, base + DPBASE);
+    asm.op(&[DAD_D, RET]);
+
+    entries.push(settrk);
+
+    // SETSEC
+    entries.push(asm.here());
+    asm.op(&[MOV_A_C, OUT, port::SECTOR, RET]);
+
+    // SETDMA
+    entries.push(asm.here());
+    asm.op(&[MOV_A_C, OUT, port::DMA_LOW, MOV_A_B, OUT, port::DMA_HIGH, RET]);
+
+    // READ and WRITE return the controller's status, 0 for success
+    entries.push(asm.here());
+    asm.op(&[MVI_A, port::READ, OUT, port::COMMAND, IN, port::STATUS, RET]);
+    entries.push(asm.here());
+    asm.op(&[MVI_A, port::WRITE, OUT, port::COMMAND, IN, port::STATUS, RET]);
+
+    // LISTST: the list device is always ready
+    entries.push(asm.here());
+    asm.op(&[MVI_A, 0xFF, RET]);
+
+    // SECTRAN: HL = XLT[BC]
+    entries.push(asm.here());
+    asm.op(&[XCHG, DAD_B, MOV_L_M, MVI_H, 0, RET]);
+
+    for (i, &entry) in entries.iter().enumerate() {
+        asm.bytes[3 * i] = JMP;
+        asm.bytes[3 * i + 1] = entry as u8;
+        asm.bytes[3 * i + 2] = (entry >> 8) as u8;
+    }
+
+    asm.pad_to(DPBASE);
+    for drive in 0..DRIVES {
+        asm.word(base + XLT);
+        asm.op(&[0; 6]);
+        asm.word(base + DIRBUF);
+        asm.word(base + DPB);
+        asm.word(base + CSV + drive * CSV_SIZE);
+        asm.word(base + ALV + drive * ALV_SIZE);
+    }
+    asm.pad_to(XLT);
+    asm.op(&SKEW);
+    asm.pad_to(DPB);
+    asm.op(&IBM_3740);
+    // the directory buffer, checksum and allocation vectors start out zeroed
+    asm.pad_to(SIZE);
+
+    asm.bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let bios = build(0xFA00, 0xE400);
+        assert_eq!(bios.len(), SIZE as usize);
+
+        // every jump table entry lands in the code page
+        for i in 0..17 {
+            assert_eq!(bios[3 * i], JMP);
+            let target = u16::from(bios[3 * i + 1]) | u16::from(bios[3 * i + 2]) << 8;
+            assert!((0xFA00 + 17 * 3..0xFA00 + DPBASE).contains(&target), "entry {} is {:04x}", i, target);
+        }
+
+        // drive B's header points at the shared tables and its own vectors
+        let dph = &bios[(DPBASE + 16) as usize..(DPBASE + 32) as usize];
+        assert_eq!(dph[0..2], [0x40, 0xFB]);
+        assert_eq!(dph[8..10], [0x80, 0xFB]);
+        assert_eq!(dph[10..12], [0x60, 0xFB]);
+        assert_eq!(dph[12..14], [0x10, 0xFC]);
+        assert_eq!(dph[14..16], [0x5F, 0xFC]);
+
+        assert_eq!(bios[XLT as usize + 1], 7);
+        assert_eq!(bios[DPB as usize], 26);
+    }
+}
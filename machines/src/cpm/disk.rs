@@ -0,0 +1,146 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/* A single-sided, single-density 8" floppy in IBM 3740 format - the disk CP/M 2.2 was
+   distributed on. 77 tracks of 26 sectors of 128 bytes, numbered from track 0 and sector 1, and
+   stored in an image file in that order with no headers. */
+
+pub const TRACKS: u8 = 77;
+pub const SECTORS_PER_TRACK: u8 = 26;
+pub const SECTOR_SIZE: usize = 128;
+pub const IMAGE_SIZE: u64 = TRACKS as u64 * SECTORS_PER_TRACK as u64 * SECTOR_SIZE as u64;
+
+/// Anything a disk image can be kept in: a file, or memory for a scratch disk.
+pub trait Storage: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> Storage for T {}
+
+#[derive(Debug)]
+pub enum DiskError {
+    BadTrack(u8),
+    BadSector(u8),
+    ReadOnly,
+    Io(std::io::Error),
+}
+
+impl DiskError {
+    /// The value the disk controller's status port reads after a failed command.
+    pub fn status(&self) -> u8 {
+        match self {
+            DiskError::BadTrack(_) => 2,
+            DiskError::BadSector(_) => 3,
+            DiskError::Io(_) => 5,
+            DiskError::ReadOnly => 7,
+        }
+    }
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            DiskError::BadTrack(track) => write!(f, "there is no track {}", track),
+            DiskError::BadSector(sector) => write!(f, "there is no sector {}", sector),
+            DiskError::ReadOnly => write!(f, "the disk is write protected"),
+            DiskError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+pub struct Disk {
+    storage: Box<dyn Storage>,
+    pub read_only: bool,
+}
+
+impl Disk {
+    /// Opens an image file, read-only if it can't be written to.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("Could not open disk image {}: {}", path.display(), e);
+        let (file, read_only) = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => (file, false),
+            Err(_) => (OpenOptions::new().read(true).open(path).map_err(error)?, true),
+        };
+
+        let size = file.metadata().map_err(error)?.len();
+        if size != IMAGE_SIZE {
+            return Err(format!(
+                "Disk image {} is {} bytes, an 8\" IBM 3740 image is {} bytes", path.display(), size, IMAGE_SIZE));
+        }
+        Ok(Self { storage: Box::new(file), read_only })
+    }
+
+    /// A freshly formatted disk held in memory.
+    pub fn blank() -> Self {
+        let image = vec![0xE5; IMAGE_SIZE as usize];
+        Self { storage: Box::new(std::io::Cursor::new(image)), read_only: false }
+    }
+
+    pub fn read_sector(&mut self, track: u8, sector: u8, data: &mut [u8]) -> Result<(), DiskError> {
+        self.seek(track, sector)?;
+        self.storage.read_exact(&mut data[..SECTOR_SIZE]).map_err(DiskError::Io)
+    }
+
+    pub fn write_sector(&mut self, track: u8, sector: u8, data: &[u8]) -> Result<(), DiskError> {
+        if self.read_only {
+            return Err(DiskError::ReadOnly);
+        }
+        self.seek(track, sector)?;
+        self.storage.write_all(&data[..SECTOR_SIZE]).map_err(DiskError::Io)?;
+        self.storage.flush().map_err(DiskError::Io)
+    }
+
+    fn seek(&mut self, track: u8, sector: u8) -> Result<(), DiskError> {
+        if track >= TRACKS {
+            return Err(DiskError::BadTrack(track));
+        }
+        if sector == 0 || sector > SECTORS_PER_TRACK {
+            return Err(DiskError::BadSector(sector));
+        }
+        let index = u64::from(track) * u64::from(SECTORS_PER_TRACK) + u64::from(sector - 1);
+        self.storage.seek(SeekFrom::Start(index * SECTOR_SIZE as u64)).map_err(DiskError::Io)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sectors() {
+        let mut disk = Disk::blank();
+        let mut data = [0; SECTOR_SIZE];
+        disk.read_sector(0, 1, &mut data).unwrap();
+        assert_eq!(data[..], [0xE5; SECTOR_SIZE][..]);
+
+        disk.write_sector(76, 26, &[0x42; SECTOR_SIZE]).unwrap();
+        disk.read_sector(76, 25, &mut data).unwrap();
+        assert_eq!(data[0], 0xE5);
+        disk.read_sector(76, 26, &mut data).unwrap();
+        assert_eq!(data[..], [0x42; SECTOR_SIZE][..]);
+
+        assert_eq!(disk.read_sector(77, 1, &mut data).unwrap_err().status(), 2);
+        assert_eq!(disk.read_sector(0, 0, &mut data).unwrap_err().status(), 3);
+        assert_eq!(disk.read_sector(0, 27, &mut data).unwrap_err().status(), 3);
+
+        disk.read_only = true;
+        assert_eq!(disk.write_sector(0, 1, &data).unwrap_err().status(), 7);
+    }
+
+    #[test]
+    fn test_open_checks_size() {
+        let path = std::env::temp_dir().join(format!("rust-8080-disk-{}.dsk", std::process::id()));
+        std::fs::write(&path, vec![0xE5; 1000]).unwrap();
+        assert!(Disk::open(&path).err().unwrap().contains("is 1000 bytes"));
+
+        std::fs::write(&path, vec![0xE5; IMAGE_SIZE as usize]).unwrap();
+        let mut disk = Disk::open(&path).unwrap();
+        disk.write_sector(2, 1, &[0x11; SECTOR_SIZE]).unwrap();
+        drop(disk);
+
+        let image = std::fs::read(&path).unwrap();
+        assert_eq!(image[2 * 26 * 128], 0x11);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+pub mod bios;
+pub mod disk;
+
+use std::io::Write;
+
+use i8080::blocks::{self, BlockCache};
+use i8080::io::IO;
+use super::altair::sio::Serial;
+use self::disk::{Disk, SECTOR_SIZE};
+
+/* A CP/M 2.2 computer: 64K of RAM, a console and four 8" drives. The hardware is whatever is
+   simplest to drive from the generated BIOS (see bios.rs) - a console on two ports and a disk
+   controller that is told a drive, track, sector and DMA address and then to read or write. */
+
+pub const NAME: &str = "cpm";
+pub const DESCRIPTION: &str = "CP/M 2.2 computer with 64K RAM and four 8\" drives";
+
+pub mod port {
+    /// Reads 0xFF when a key is waiting, 0 otherwise.
+    pub const CONSOLE_STATUS: u8 = 0x00;
+    pub const CONSOLE_DATA: u8 = 0x01;
+    /// Selects a drive; reads 0 if the selected drive has a disk in it.
+    pub const DRIVE: u8 = 0x0A;
+    pub const TRACK: u8 = 0x0B;
+    pub const SECTOR: u8 = 0x0C;
+    pub const COMMAND: u8 = 0x0D;
+    /// 0 if the last command succeeded, otherwise a `DiskError` status.
+    pub const STATUS: u8 = 0x0E;
+    pub const DMA_LOW: u8 = 0x0F;
+    pub const DMA_HIGH: u8 = 0x10;
+    /// Writing 0 cold boots, 1 warm boots: the CCP and BDOS are reloaded and page zero set up.
+    pub const LOADER: u8 = 0x20;
+
+    pub const READ: u8 = 0;
+    pub const WRITE: u8 = 1;
+}
+
+/* The CCP and BDOS are read from the two system tracks, starting after the cold start loader in
+   track 0 sector 1. They're 0x1600 bytes long and assembled for a fixed address, which can be read
+   back out of the CCP's first two instructions: jumps to 0x035C and 0x0358 past its start. */
+
+const SYSTEM_SIZE: u16 = 0x1600;
+const BDOS_ENTRY: u16 = 0x0806;
+const CCP_SIGNATURE: [(usize, u16); 2] = [(0, 0x035C), (3, 0x0358)];
+
+const ILLEGAL_DRIVE: u8 = 1;
+const ILLEGAL_COMMAND: u8 = 0xFF;
+
+pub struct Cpm {
+    pub cpu: i8080::cpu::CPU,
+    io: CpmIO,
+    ccp: u16,
+    bios: u16,
+
+    pub instructions: u64,
+    pub cycles: u64,
+
+    /// When set, a line describing the CPU state is written before every instruction.
+    pub trace: Option<Box<dyn Write>>,
+    /// When set, and not tracing, code runs from a cache of decoded blocks.
+    pub blocks: Option<BlockCache>,
+}
+
+impl Cpm {
+    pub const CLOCK_HZ: u64 = 2_000_000;
+
+    /// Puts `disks` in drives A to D and cold boots from drive A.
+    pub fn new(disks: Vec<Option<Disk>>) -> Result<Self, String> {
+        let mut io = CpmIO::new();
+        for (drive, disk) in disks.into_iter().enumerate().take(4) {
+            io.drives[drive] = disk;
+        }
+
+        let mut machine = Self {
+            cpu: i8080::cpu::CPU::new(),
+            io,
+            ccp: 0,
+            bios: 0,
+            instructions: 0,
+            cycles: 0,
+            trace: None,
+            blocks: None,
+        };
+
+        let system = machine.read_system()?;
+        let ccp = CCP_SIGNATURE.iter().map(|&(offset, distance)| {
+            (u16::from(system[offset + 1]) | u16::from(system[offset + 2]) << 8).wrapping_sub(distance)
+        }).collect::<Vec<u16>>();
+        let bios = ccp[0].wrapping_add(SYSTEM_SIZE);
+        if system[0] != 0xc3 || system[3] != 0xc3 || ccp[0] != ccp[1] || ccp[0] & 0xFF != 0
+            || u32::from(bios) + u32::from(bios::SIZE) > 0x10000 {
+            return Err(String::from("The disk in drive A is not a CP/M 2.2 system disk"));
+        }
+        machine.ccp = ccp[0];
+        machine.bios = bios;
+
+        machine.cpu.memory.load(bios as usize, &bios::build(bios, machine.ccp));
+        machine.cpu.reg.pc = bios;
+        Ok(machine)
+    }
+
+    /// Where the CCP, BDOS and BIOS were loaded.
+    pub fn layout(&self) -> (u16, u16, u16) {
+        (self.ccp, self.ccp + BDOS_ENTRY - 6, self.bios)
+    }
+
+    /// Runs for at least `cycles` clock cycles.
+    pub fn run(&mut self, cycles: u64) -> Result<(), String> {
+        let mut cycles_spent = 0;
+        while cycles_spent < cycles {
+            if let Some(trace) = self.trace.as_mut() {
+                if let Err(e) = writeln!(trace, "{}", self.cpu.trace_line(self.cycles)) {
+                    eprintln!("Stopping trace: {}", e);
+                    self.trace = None;
+                }
+            }
+
+            let blocks = if self.trace.is_none() { self.blocks.as_mut() } else { None };
+            let ran = blocks::run(blocks, &mut self.cpu, &mut self.io, cycles - cycles_spent).map_err(|e| e.to_string())?;
+            cycles_spent += ran.cycles;
+            self.instructions += ran.instructions;
+            self.cycles += ran.cycles;
+
+            // the controller works on memory, which only the machine can get at
+            if let Some(request) = self.io.request.take() {
+                self.serve(request)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serve(&mut self, request: Request) -> Result<(), String> {
+        let io = &mut self.io;
+        let dma = io.dma as usize;
+        if let Request::Boot { cold } = request {
+            let system = self.read_system()?;
+            let memory = &mut self.cpu.memory;
+            memory.load(self.ccp as usize, &system);
+            if cold {
+                memory.load(0x0003, &[0x00, 0x00]);
+            }
+            let (wboot, bdos) = (self.bios + 3, self.ccp + BDOS_ENTRY);
+            memory.load(0x0000, &[0xc3, wboot as u8, (wboot >> 8) as u8]);
+            memory.load(0x0005, &[0xc3, bdos as u8, (bdos >> 8) as u8]);
+            self.io.dma = 0x0080;
+            return Ok(());
+        }
+
+        let (track, sector) = (io.track, io.sector);
+        let disk = match io.drives.get_mut(io.drive as usize) {
+            Some(Some(disk)) => disk,
+            _ => {
+                io.status = ILLEGAL_DRIVE;
+                return Ok(());
+            },
+        };
+        let memory = &mut self.cpu.memory;
+        let result = if request == Request::Read {
+            let mut data = [0; SECTOR_SIZE];
+            let result = disk.read_sector(track, sector, &mut data);
+            if result.is_ok() {
+                for (i, &byte) in data.iter().enumerate() {
+                    memory[(dma + i) as u16] = byte;
+                }
+            }
+            result
+        } else {
+            let data: Vec<u8> = (0..SECTOR_SIZE).map(|i| memory[(dma + i) as u16]).collect();
+            disk.write_sector(track, sector, &data)
+        };
+        io.status = result.map(|()| 0).unwrap_or_else(|e| e.status());
+        Ok(())
+    }
+
+    fn read_system(&mut self) -> Result<Vec<u8>, String> {
+        let disk = self.io.drives[0].as_mut().ok_or("There is no disk in drive A to boot from")?;
+        let mut system = vec![0; SYSTEM_SIZE as usize];
+        let (mut track, mut sector) = (0, 2);
+        for chunk in system.chunks_mut(SECTOR_SIZE) {
+            disk.read_sector(track, sector, chunk)
+                .map_err(|e| format!("Could not read the system from drive A: {}", e))?;
+            sector += 1;
+            if sector > disk::SECTORS_PER_TRACK {
+                track += 1;
+                sector = 1;
+            }
+        }
+        Ok(system)
+    }
+
+    /// Characters typed at the console.
+    pub fn type_keys(&mut self, bytes: &[u8]) {
+        self.io.console.receive(bytes);
+    }
+
+    pub fn keys_pending(&self) -> usize {
+        self.io.console.pending()
+    }
+
+    /// Characters written to the console since the last call.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.io.console.take_sent()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Request {
+    Read,
+    Write,
+    Boot { cold: bool },
+}
+
+pub struct CpmIO {
+    console: Serial,
+    drives: [Option<Disk>; 4],
+    drive: u8,
+    track: u8,
+    sector: u8,
+    dma: u16,
+    status: u8,
+    request: Option<Request>,
+}
+
+impl CpmIO {
+    pub fn new() -> Self {
+        Self {
+            console: Serial::new(),
+            drives: [None, None, None, None],
+            drive: 0,
+            track: 0,
+            sector: 1,
+            dma: 0x0080,
+            status: 0,
+            request: None,
+        }
+    }
+}
+
+impl Default for CpmIO {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IO for CpmIO {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            port::CONSOLE_STATUS => if self.console.pending() > 0 { 0xFF } else { 0x00 },
+            port::CONSOLE_DATA => self.console.read(),
+            port::DRIVE => match self.drives.get(self.drive as usize) {
+                Some(Some(_)) => 0,
+                _ => 0xFF,
+            },
+            port::STATUS => self.status,
+            _ => 0xFF,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            port::CONSOLE_DATA => self.console.write(value & 0x7F),
+            port::DRIVE => self.drive = value,
+            port::TRACK => self.track = value,
+            port::SECTOR => self.sector = value,
+            port::DMA_LOW => self.dma = (self.dma & 0xFF00) | u16::from(value),
+            port::DMA_HIGH => self.dma = (self.dma & 0x00FF) | u16::from(value) << 8,
+            port::COMMAND => match value {
+                port::READ => self.request = Some(Request::Read),
+                port::WRITE => self.request = Some(Request::Write),
+                _ => self.status = ILLEGAL_COMMAND,
+            },
+            port::LOADER => self.request = Some(Request::Boot { cold: value == 0 }),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CCP: u16 = 0xE400;
+    const BIOS: u16 = 0xFA00;
+
+    /* A stand-in for the CCP that drives the BIOS the way the BDOS does: it prints the drive it
+       was started with, reads track 2 sector 1 of drive B (through the sector translation table)
+       into 0x1000, prints its first byte, writes it back to sector 2 and stops in a loop. */
+    fn fake_system() -> Vec<u8> {
+        let mut system = vec![0; SYSTEM_SIZE as usize];
+        let start = 0x035C;
+        system[0..6].copy_from_slice(&[0xc3, 0x5C, 0xE7, 0xc3, 0x58, 0xE7]);
+        let call = |entry: u16| [0xcd, (BIOS + 3 * entry) as u8, ((BIOS + 3 * entry) >> 8) as u8];
+        let mut code = Vec::new();
+        code.extend(&[0x79, 0xc6, b'A', 0x4f]); // MOV A,C; ADI 'A'; MOV C,A
+        code.extend(&call(4)); // CONOUT
+        code.extend(&[0x0e, 0x01]); // MVI C,1
+        code.extend(&call(9)); // SELDSK
+        code.extend(&[0x01, 0x02, 0x00]); // LXI B,2
+        code.extend(&call(10)); // SETTRK
+        code.extend(&[0x01, 0x00, 0x00, 0x11, 0x40, 0xFB]); // LXI B,0; LXI D,XLT
+        code.extend(&call(16)); // SECTRAN
+        code.extend(&[0x4d]); // MOV C,L
+        code.extend(&call(11)); // SETSEC
+        code.extend(&[0x01, 0x00, 0x10]); // LXI B,1000
+        code.extend(&call(12)); // SETDMA
+        code.extend(&call(13)); // READ
+        code.extend(&[0x3a, 0x00, 0x10, 0x4f]); // LDA 1000; MOV C,A
+        code.extend(&call(4)); // CONOUT
+        code.extend(&[0x0e, 0x02]); // MVI C,2
+        code.extend(&call(11)); // SETSEC
+        code.extend(&call(14)); // WRITE
+        let end = CCP + start as u16 + code.len() as u16;
+        code.extend(&[0xc3, end as u8, (end >> 8) as u8]); // JMP end
+        system[start..start + code.len()].copy_from_slice(&code);
+        system
+    }
+
+    fn system_disk(system: &[u8]) -> Disk {
+        let mut disk = Disk::blank();
+        let (mut track, mut sector) = (0, 2);
+        for chunk in system.chunks(SECTOR_SIZE) {
+            disk.write_sector(track, sector, chunk).unwrap();
+            sector += 1;
+            if sector > 26 {
+                track += 1;
+                sector = 1;
+            }
+        }
+        disk
+    }
+
+    #[test]
+    fn test_boot_and_bios_calls() {
+        let mut data_disk = Disk::blank();
+        data_disk.write_sector(2, 1, &[b'Z'; SECTOR_SIZE]).unwrap();
+
+        let mut cpm = Cpm::new(vec![Some(system_disk(&fake_system())), Some(data_disk)]).unwrap();
+        assert_eq!(cpm.layout(), (CCP, 0xEC00, BIOS));
+        cpm.run(5_000).unwrap();
+        assert_eq!(cpm.take_output(), b"AZ");
+
+        // page zero was set up by the cold boot, and a warm boot starts on the current drive
+        assert_eq!(cpm.cpu.memory.view(0, 7), &[0xc3, 0x03, 0xFA, 0, 0, 0xc3, 0x06, 0xEC]);
+        cpm.cpu.memory[0x0004] = 1;
+        cpm.cpu.reg.pc = 0;
+        cpm.run(5_000).unwrap();
+        assert_eq!(cpm.take_output(), b"BZ");
+
+        let mut data = [0; SECTOR_SIZE];
+        cpm.io.drives[1].as_mut().unwrap().read_sector(2, 2, &mut data).unwrap();
+        assert_eq!(data[0], b'Z');
+    }
+
+    #[test]
+    fn test_missing_drive_and_bad_sector() {
+        let mut cpm = Cpm::new(vec![Some(system_disk(&fake_system()))]).unwrap();
+        cpm.io.output(port::DRIVE, 2);
+        assert_eq!(cpm.io.input(port::DRIVE), 0xFF);
+        cpm.io.output(port::COMMAND, port::READ);
+        let request = cpm.io.request.take().unwrap();
+        cpm.serve(request).unwrap();
+        assert_eq!(cpm.io.input(port::STATUS), ILLEGAL_DRIVE);
+
+        cpm.io.output(port::DRIVE, 0);
+        cpm.io.output(port::SECTOR, 30);
+        cpm.io.output(port::COMMAND, port::READ);
+        let request = cpm.io.request.take().unwrap();
+        cpm.serve(request).unwrap();
+        assert_eq!(cpm.io.input(port::STATUS), 3);
+    }
+
+    #[test]
+    fn test_not_a_system_disk() {
+        assert!(Cpm::new(vec![Some(Disk::blank())]).is_err());
+        assert!(Cpm::new(vec![None, Some(Disk::blank())]).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+//! Machines built around the `i8080` crate's CPU: the Midway/Taito 8080 board that Space
+//! Invaders and its relatives run on, a MITS Altair 8800 with a serial card, and a CP/M 2.2
+//! computer with disk drives. Each runs headless, leaving the window, keyboard and sound to the
+//! frontend, and `video` turns the Midway board's VRAM into pixels.
+//!
+//! `roms` loads and checks a game's ROM set, from a directory, a zip or a single image.
+
+pub mod altair;
+pub mod cpm;
+pub mod midway;
+pub mod pic8259;
+pub mod roms;
+pub mod video;
+
+/// Every machine that can be picked with `--machine`.
+pub fn names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = midway::games::ALL.iter().map(|game| game.name).collect();
+    names.push(altair::NAME);
+    names.push(cpm::NAME);
+    names
+}
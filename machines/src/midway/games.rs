@@ -0,0 +1,310 @@
+use crate::roms::Chip;
+use crate::video::overlay::Overlay;
+use super::{Analog, AnalogInput, Dip, Encoding, Input, InputBit, MachineDef, Sound};
+use super::shifter::ShifterWiring;
+
+/* Game definitions for the Midway / Taito 8080 board. ROM names, offsets and CRCs follow MAME's
+   sets so that its ROM zips can be used as they are. */
+
+pub const ALL: [&MachineDef; 7] = [&INVADERS, &INVADPT2, &LRESCUE, &BALLBOMB, &GUNFIGHT, &SEAWOLF, &ZZZAP];
+
+pub fn find(name: &str) -> Option<&'static MachineDef> {
+    ALL.iter().find(|game| game.name == name).cloned()
+}
+
+/* Nearly every game on the board kept Space Invaders' control panel wiring: coin and starts on
+   port 1 alongside player one's controls, player two's controls on port 2 next to the DIPs. */
+
+const STANDARD_INPUTS: [InputBit; 10] = [
+    InputBit { input: Input::Coin, port: 1, bit: 0, active_low: false },
+    InputBit { input: Input::P2Start, port: 1, bit: 1, active_low: false },
+    InputBit { input: Input::P1Start, port: 1, bit: 2, active_low: false },
+    InputBit { input: Input::P1Fire, port: 1, bit: 4, active_low: false },
+    InputBit { input: Input::P1Left, port: 1, bit: 5, active_low: false },
+    InputBit { input: Input::P1Right, port: 1, bit: 6, active_low: false },
+    InputBit { input: Input::Tilt, port: 2, bit: 2, active_low: false },
+    InputBit { input: Input::P2Fire, port: 2, bit: 4, active_low: false },
+    InputBit { input: Input::P2Left, port: 2, bit: 5, active_low: false },
+    InputBit { input: Input::P2Right, port: 2, bit: 6, active_low: false },
+];
+
+// port 1 bit 3 is tied high on the board
+const STANDARD_PORTS: [(u8, u8); 2] = [(1, 0b0000_1000), (2, 0b0000_0000)];
+
+const LIVES_3_TO_6: Dip = Dip {
+    name: "lives",
+    port: 2,
+    mask: 0b0000_0011,
+    settings: &[("3", 0), ("4", 1), ("5", 2), ("6", 3)],
+    default: "3",
+};
+
+const INVADERS_SOUNDS: [Sound; 10] = [
+    Sound { port: 3, bit: 0, name: "ufo" },
+    Sound { port: 3, bit: 1, name: "shot" },
+    Sound { port: 3, bit: 2, name: "player death" },
+    Sound { port: 3, bit: 3, name: "invader death" },
+    Sound { port: 3, bit: 4, name: "extra life" },
+    Sound { port: 5, bit: 0, name: "fleet 1" },
+    Sound { port: 5, bit: 1, name: "fleet 2" },
+    Sound { port: 5, bit: 2, name: "fleet 3" },
+    Sound { port: 5, bit: 3, name: "fleet 4" },
+    Sound { port: 5, bit: 4, name: "ufo hit" },
+];
+
+pub const INVADERS: MachineDef = MachineDef {
+    name: "invaders",
+    description: "Space Invaders (Midway, 1978)",
+    roms: &[
+        Chip { name: "invaders.h", offset: 0x0000, size: 0x800, crc32: 0x734f_5ad8 },
+        Chip { name: "invaders.g", offset: 0x0800, size: 0x800, crc32: 0x6bfa_ca4a },
+        Chip { name: "invaders.f", offset: 0x1000, size: 0x800, crc32: 0x0cce_ad96 },
+        Chip { name: "invaders.e", offset: 0x1800, size: 0x800, crc32: 0x14e5_38b0 },
+    ],
+    port_defaults: &STANDARD_PORTS,
+    inputs: &STANDARD_INPUTS,
+    analog: &[],
+    dips: &[
+        LIVES_3_TO_6,
+        Dip { name: "bonus", port: 2, mask: 0b0000_1000, settings: &[("1500", 0), ("1000", 0b1000)], default: "1500" },
+        Dip { name: "coin-info", port: 2, mask: 0b1000_0000, settings: &[("on", 0), ("off", 0b1000_0000)], default: "on" },
+    ],
+    sounds: &INVADERS_SOUNDS,
+    shifter: ShifterWiring::STANDARD,
+    mirrored_inputs: false,
+    overlay: Overlay::Midway,
+};
+
+pub const INVADPT2: MachineDef = MachineDef {
+    name: "invadpt2",
+    description: "Space Invaders Part II (Taito, 1980)",
+    roms: &[
+        Chip { name: "pv01", offset: 0x0000, size: 0x800, crc32: 0x7288_a511 },
+        Chip { name: "pv02", offset: 0x0800, size: 0x800, crc32: 0x097d_d8d5 },
+        Chip { name: "pv03", offset: 0x1000, size: 0x800, crc32: 0x1766_337e },
+        Chip { name: "pv04", offset: 0x1800, size: 0x800, crc32: 0x8f0e_62e0 },
+        Chip { name: "pv05", offset: 0x4000, size: 0x800, crc32: 0x19b5_05e9 },
+    ],
+    port_defaults: &STANDARD_PORTS,
+    inputs: &STANDARD_INPUTS,
+    analog: &[],
+    dips: &[
+        Dip { name: "lives", port: 2, mask: 0b0000_0001, settings: &[("3", 0), ("4", 1)], default: "3" },
+        Dip { name: "name-reset", port: 2, mask: 0b0000_1000, settings: &[("off", 0), ("on", 0b1000)], default: "off" },
+    ],
+    sounds: &INVADERS_SOUNDS,
+    shifter: ShifterWiring::STANDARD,
+    mirrored_inputs: false,
+    overlay: Overlay::BlackAndWhite,
+};
+
+pub const LRESCUE: MachineDef = MachineDef {
+    name: "lrescue",
+    description: "Lunar Rescue (Taito, 1979)",
+    roms: &[
+        Chip { name: "lrescue.1", offset: 0x0000, size: 0x800, crc32: 0x2bbc_4778 },
+        Chip { name: "lrescue.2", offset: 0x0800, size: 0x800, crc32: 0x49e7_9706 },
+        Chip { name: "lrescue.3", offset: 0x1000, size: 0x800, crc32: 0x1ac9_69be },
+        Chip { name: "lrescue.4", offset: 0x1800, size: 0x800, crc32: 0x782f_ee3c },
+        Chip { name: "lrescue.5", offset: 0x4000, size: 0x800, crc32: 0x58fd_e8bc },
+        Chip { name: "lrescue.6", offset: 0x4800, size: 0x800, crc32: 0xbfb0_f65d },
+    ],
+    port_defaults: &STANDARD_PORTS,
+    inputs: &STANDARD_INPUTS,
+    analog: &[],
+    dips: &[LIVES_3_TO_6],
+    sounds: &[
+        Sound { port: 3, bit: 0, name: "thrust" },
+        Sound { port: 3, bit: 1, name: "shot" },
+        Sound { port: 3, bit: 2, name: "death" },
+        Sound { port: 3, bit: 3, name: "alien hit" },
+        Sound { port: 3, bit: 4, name: "bonus" },
+        Sound { port: 5, bit: 0, name: "rescue" },
+        Sound { port: 5, bit: 1, name: "docking" },
+        Sound { port: 5, bit: 2, name: "landing" },
+    ],
+    shifter: ShifterWiring::STANDARD,
+    mirrored_inputs: false,
+    overlay: Overlay::BlackAndWhite,
+};
+
+pub const BALLBOMB: MachineDef = MachineDef {
+    name: "ballbomb",
+    description: "Balloon Bomber (Taito, 1980)",
+    roms: &[
+        Chip { name: "tn01", offset: 0x0000, size: 0x800, crc32: 0x5515_85b5 },
+        Chip { name: "tn02", offset: 0x0800, size: 0x800, crc32: 0x7e1f_734f },
+        Chip { name: "tn03", offset: 0x1000, size: 0x800, crc32: 0xd93e_20bc },
+        Chip { name: "tn04", offset: 0x1800, size: 0x800, crc32: 0xd068_9a22 },
+        Chip { name: "tn05-1", offset: 0x4000, size: 0x800, crc32: 0x5d74_a50c },
+    ],
+    port_defaults: &STANDARD_PORTS,
+    inputs: &STANDARD_INPUTS,
+    analog: &[],
+    dips: &[LIVES_3_TO_6],
+    sounds: &[
+        Sound { port: 3, bit: 1, name: "shot" },
+        Sound { port: 3, bit: 2, name: "death" },
+        Sound { port: 3, bit: 3, name: "balloon pop" },
+        Sound { port: 5, bit: 0, name: "bomb" },
+    ],
+    shifter: ShifterWiring::STANDARD,
+    mirrored_inputs: false,
+    overlay: Overlay::BlackAndWhite,
+};
+
+/* The games that came before Space Invaders only decode two address bits of an input port, read
+   their controls active low and use analog controls, so they share none of the wiring above. The
+   port layouts follow MAME's mw8080bw driver. */
+
+pub const GUNFIGHT: MachineDef = MachineDef {
+    name: "gunfight",
+    description: "Gun Fight (Midway, 1975)",
+    roms: &[
+        Chip { name: "7609h.bin", offset: 0x0000, size: 0x400, crc32: 0x0b11_7d73 },
+        Chip { name: "7609g.bin", offset: 0x0400, size: 0x400, crc32: 0x57bc_3159 },
+        Chip { name: "7609f.bin", offset: 0x0800, size: 0x400, crc32: 0x8049_a6bd },
+        Chip { name: "7609e.bin", offset: 0x0c00, size: 0x400, crc32: 0x7732_64e2 },
+    ],
+    port_defaults: &[(0, 0xFF), (1, 0xFF), (2, 0b1100_0000)],
+    inputs: &[
+        InputBit { input: Input::P1Up, port: 0, bit: 0, active_low: true },
+        InputBit { input: Input::P1Down, port: 0, bit: 1, active_low: true },
+        InputBit { input: Input::P1Left, port: 0, bit: 2, active_low: true },
+        InputBit { input: Input::P1Right, port: 0, bit: 3, active_low: true },
+        InputBit { input: Input::P1Fire, port: 0, bit: 7, active_low: true },
+        InputBit { input: Input::P2Up, port: 1, bit: 0, active_low: true },
+        InputBit { input: Input::P2Down, port: 1, bit: 1, active_low: true },
+        InputBit { input: Input::P2Left, port: 1, bit: 2, active_low: true },
+        InputBit { input: Input::P2Right, port: 1, bit: 3, active_low: true },
+        InputBit { input: Input::P2Fire, port: 1, bit: 7, active_low: true },
+        InputBit { input: Input::Coin, port: 2, bit: 6, active_low: true },
+        InputBit { input: Input::P1Start, port: 2, bit: 7, active_low: true },
+    ],
+    // the gun barrel is a seven position lever next to the joystick
+    analog: &[
+        AnalogInput {
+            control: Analog::P1Aim, port: 0, mask: 0b0111_0000, positions: 7, encoding: Encoding::Binary,
+            keys: (Input::P1AimDown, Input::P1AimUp), rest: 0.5, springs_back: false,
+        },
+        AnalogInput {
+            control: Analog::P2Aim, port: 1, mask: 0b0111_0000, positions: 7, encoding: Encoding::Binary,
+            keys: (Input::P2AimDown, Input::P2AimUp), rest: 0.5, springs_back: false,
+        },
+    ],
+    dips: &[
+        Dip { name: "coinage", port: 2, mask: 0b0000_0011, settings: &[("1", 0), ("2", 1), ("3", 2), ("4", 3)], default: "1" },
+        Dip { name: "time", port: 2, mask: 0b0000_1100, settings: &[("60", 0), ("70", 4), ("80", 8), ("90", 12)], default: "60" },
+    ],
+    sounds: &[],
+    shifter: ShifterWiring::STANDARD,
+    mirrored_inputs: true,
+    overlay: Overlay::BlackAndWhite,
+};
+
+pub const SEAWOLF: MachineDef = MachineDef {
+    name: "seawolf",
+    description: "Sea Wolf (Midway, 1976)",
+    roms: &[
+        Chip { name: "sw0041.h", offset: 0x0000, size: 0x400, crc32: 0x8f59_7323 },
+        Chip { name: "sw0042.g", offset: 0x0400, size: 0x400, crc32: 0xdb98_0974 },
+        Chip { name: "sw0043.f", offset: 0x0800, size: 0x400, crc32: 0xe6ff_a008 },
+        Chip { name: "sw0044.e", offset: 0x0c00, size: 0x400, crc32: 0xc355_7d6a },
+    ],
+    port_defaults: &[(1, 0b0010_0000), (2, 0b1100_0000)],
+    inputs: &[
+        InputBit { input: Input::P1Fire, port: 1, bit: 5, active_low: true },
+        InputBit { input: Input::Coin, port: 2, bit: 6, active_low: true },
+        InputBit { input: Input::P1Start, port: 2, bit: 7, active_low: true },
+    ],
+    // an optical encoder on the periscope, read as a 5-bit Gray code
+    analog: &[AnalogInput {
+        control: Analog::Periscope, port: 1, mask: 0b0001_1111, positions: 32, encoding: Encoding::Gray,
+        keys: (Input::P1Left, Input::P1Right), rest: 0.5, springs_back: false,
+    }],
+    dips: &[
+        Dip { name: "time", port: 1, mask: 0b1100_0000, settings: &[("61", 0), ("71", 0x40), ("81", 0x80), ("91", 0xC0)], default: "61" },
+        Dip { name: "coinage", port: 2, mask: 0b0000_0011, settings: &[("1", 0), ("2", 1), ("3", 2), ("4", 3)], default: "1" },
+    ],
+    sounds: &[
+        Sound { port: 5, bit: 0, name: "ship hit" },
+        Sound { port: 5, bit: 1, name: "torpedo" },
+        Sound { port: 5, bit: 2, name: "dive" },
+        Sound { port: 5, bit: 3, name: "sonar" },
+        Sound { port: 5, bit: 4, name: "mine hit" },
+    ],
+    shifter: ShifterWiring {
+        count_port: 3,
+        data_port: 4,
+        result_port: 3,
+        reversed_result_port: Some(0),
+    },
+    mirrored_inputs: true,
+    overlay: Overlay::BlackAndWhite,
+};
+
+pub const ZZZAP: MachineDef = MachineDef {
+    name: "280zzzap",
+    description: "280-ZZZAP (Midway, 1976)",
+    roms: &[
+        Chip { name: "zzzaph", offset: 0x0000, size: 0x400, crc32: 0x1fa8_6e1c },
+        Chip { name: "zzzapg", offset: 0x0400, size: 0x400, crc32: 0x9639_bc6b },
+        Chip { name: "zzzapf", offset: 0x0800, size: 0x400, crc32: 0xadc6_ede1 },
+        Chip { name: "zzzape", offset: 0x0c00, size: 0x400, crc32: 0x4724_93d6 },
+        Chip { name: "zzzapd", offset: 0x1000, size: 0x400, crc32: 0x4c24_0ee1 },
+        Chip { name: "zzzapc", offset: 0x1400, size: 0x400, crc32: 0x6e85_aeaf },
+    ],
+    port_defaults: &[(2, 0)],
+    inputs: &[
+        // the shifter is a two position lever, held in high gear while fire is down
+        InputBit { input: Input::P1Fire, port: 0, bit: 4, active_low: false },
+        InputBit { input: Input::Coin, port: 2, bit: 6, active_low: false },
+        InputBit { input: Input::P1Start, port: 2, bit: 7, active_low: false },
+    ],
+    analog: &[
+        AnalogInput {
+            control: Analog::Pedal, port: 0, mask: 0b0000_1111, positions: 16, encoding: Encoding::Binary,
+            keys: (Input::P1Down, Input::P1Up), rest: 0.0, springs_back: true,
+        },
+        AnalogInput {
+            control: Analog::Steering, port: 1, mask: 0b1111_1111, positions: 256, encoding: Encoding::Binary,
+            keys: (Input::P1Left, Input::P1Right), rest: 0.5, springs_back: true,
+        },
+    ],
+    dips: &[
+        Dip { name: "coinage", port: 2, mask: 0b0000_0011, settings: &[("1", 0), ("2", 1), ("3", 2), ("4", 3)], default: "1" },
+        Dip { name: "time", port: 2, mask: 0b0000_1100, settings: &[("60", 0), ("80", 4), ("99", 8), ("50", 12)], default: "80" },
+    ],
+    sounds: &[],
+    shifter: ShifterWiring {
+        count_port: 4,
+        data_port: 3,
+        result_port: 3,
+        reversed_result_port: None,
+    },
+    mirrored_inputs: true,
+    overlay: Overlay::BlackAndWhite,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions_are_consistent() {
+        for game in ALL.iter() {
+            assert_eq!(find(game.name).map(|g| g.name), Some(game.name));
+            for dip in game.dips {
+                assert!(dip.settings.iter().any(|&(name, _)| name == dip.default), "{} {}", game.name, dip.name);
+            }
+            for analog in game.analog {
+                let levels = u32::from(analog.mask >> analog.mask.trailing_zeros()) + 1;
+                assert!(u32::from(analog.positions) <= levels, "{} {:?}", game.name, analog.control);
+            }
+            for chip in game.roms {
+                assert!(chip.offset + chip.size <= 0x2000 || chip.offset >= 0x4000, "{} overlaps RAM", chip.name);
+            }
+        }
+        assert!(find("pacman").is_none());
+    }
+}
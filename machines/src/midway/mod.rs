@@ -0,0 +1,559 @@
+pub mod games;
+pub mod shifter;
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use i8080::blocks::{self, BlockCache};
+use i8080::io::IO;
+use crate::roms::Chip;
+use crate::video::overlay::Overlay;
+use self::shifter::{ShiftRegister, ShifterWiring};
+
+/* The Midway / Taito 8080 board: an 8080 at ~2 MHz, 7 KB of RAM with 1bpp video at 0x2400, a
+   shift register for fast sprite drawing and a handful of latched input and output ports. Many
+   games run on it with only the ROMs, the port wiring and the DIP switches changed, so a game is
+   described by a `MachineDef` rather than by code. */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Input {
+    Coin,
+    P1Start,
+    P2Start,
+    P1Fire,
+    P1Left,
+    P1Right,
+    P1Up,
+    P1Down,
+    P1AimUp,
+    P1AimDown,
+    P2Fire,
+    P2Left,
+    P2Right,
+    P2Up,
+    P2Down,
+    P2AimUp,
+    P2AimDown,
+    Tilt,
+}
+
+impl Input {
+    pub const ALL: [Input; 18] = [
+        Input::Coin,
+        Input::P1Start,
+        Input::P2Start,
+        Input::P1Fire,
+        Input::P1Left,
+        Input::P1Right,
+        Input::P1Up,
+        Input::P1Down,
+        Input::P1AimUp,
+        Input::P1AimDown,
+        Input::P2Fire,
+        Input::P2Left,
+        Input::P2Right,
+        Input::P2Up,
+        Input::P2Down,
+        Input::P2AimUp,
+        Input::P2AimDown,
+        Input::Tilt,
+    ];
+
+    /// The input with a name like `coin` or `p1-fire`, for frontends that aren't keyboards.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|input| input.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Input::Coin => "coin",
+            Input::P1Start => "p1-start",
+            Input::P2Start => "p2-start",
+            Input::P1Fire => "p1-fire",
+            Input::P1Left => "p1-left",
+            Input::P1Right => "p1-right",
+            Input::P1Up => "p1-up",
+            Input::P1Down => "p1-down",
+            Input::P1AimUp => "p1-aim-up",
+            Input::P1AimDown => "p1-aim-down",
+            Input::P2Fire => "p2-fire",
+            Input::P2Left => "p2-left",
+            Input::P2Right => "p2-right",
+            Input::P2Up => "p2-up",
+            Input::P2Down => "p2-down",
+            Input::P2AimUp => "p2-aim-up",
+            Input::P2AimDown => "p2-aim-down",
+            Input::Tilt => "tilt",
+        }
+    }
+}
+
+/// Where a digital control is wired: pressing it sets (or, if active low, clears) the bit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InputBit {
+    pub input: Input,
+    pub port: u8,
+    pub bit: u8,
+    pub active_low: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Analog {
+    P1Aim,
+    P2Aim,
+    Periscope,
+    Steering,
+    Pedal,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Encoding {
+    Binary,
+    Gray,
+}
+
+/* Paddles, wheels and pedals are potentiometers or optical encoders that the board reads as a
+   number in a few bits of an input port. Keyboards have no such thing, so each control also names
+   the pair of digital inputs that drive it down and up while held; controls with a `rest` position
+   spring back to it when neither is held, like a steering wheel or a pedal. */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnalogInput {
+    pub control: Analog,
+    pub port: u8,
+    pub mask: u8,
+    pub positions: u16,
+    pub encoding: Encoding,
+    pub keys: (Input, Input),
+    /// Where the control starts and, if it springs back, where it returns, from 0.0 to 1.0.
+    pub rest: f64,
+    pub springs_back: bool,
+}
+
+impl AnalogInput {
+    /// The bits the board sees with the control at `value`, from 0.0 to 1.0.
+    pub fn encode(&self, value: f64) -> u8 {
+        let position = (value.clamp(0.0, 1.0) * f64::from(self.positions - 1)).round() as u16;
+        let code = match self.encoding {
+            Encoding::Binary => position,
+            Encoding::Gray => position ^ (position >> 1),
+        };
+        ((code << self.mask.trailing_zeros()) as u8) & self.mask
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dip {
+    pub name: &'static str,
+    pub port: u8,
+    pub mask: u8,
+    /// Each setting's name and the value it puts in the masked bits.
+    pub settings: &'static [(&'static str, u8)],
+    pub default: &'static str,
+}
+
+/// A bit on a latched output port that switches one of the discrete sound circuits on or off.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sound {
+    pub port: u8,
+    pub bit: u8,
+    pub name: &'static str,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SoundEvent {
+    pub name: &'static str,
+    pub on: bool,
+}
+
+pub struct MachineDef {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub roms: &'static [Chip],
+    /// The value each input port reads with no controls pressed and DIP switches at zero.
+    pub port_defaults: &'static [(u8, u8)],
+    pub inputs: &'static [InputBit],
+    pub analog: &'static [AnalogInput],
+    pub dips: &'static [Dip],
+    pub sounds: &'static [Sound],
+    pub shifter: ShifterWiring,
+    /// Whether ports 4-7 read the same as ports 0-3, as on boards that only decode two address bits.
+    pub mirrored_inputs: bool,
+    pub overlay: Overlay,
+}
+
+impl MachineDef {
+    pub fn dip(&self, name: &str) -> Option<&Dip> {
+        self.dips.iter().find(|dip| dip.name == name)
+    }
+}
+
+/* The machine itself, with no notion of a window or a clock - frontends drive it a frame at a
+   time and read the picture back out of VRAM. */
+
+pub struct Midway8080 {
+    pub def: &'static MachineDef,
+    pub cpu: i8080::cpu::CPU,
+    io: MidwayIO,
+    held: HashSet<Input>,
+    analog: Vec<f64>,
+
+    pub instructions: u64,
+    pub cycles: u64,
+    pub frames: u64,
+
+    /// When set, a line describing the CPU state is written before every instruction.
+    pub trace: Option<Box<dyn Write>>,
+    /// When set, and not tracing, code runs from a cache of decoded blocks.
+    pub blocks: Option<BlockCache>,
+}
+
+impl Midway8080 {
+    /// A 19.968 MHz crystal divided by 10.
+    pub const CLOCK_HZ: u64 = 1_996_800;
+    const CYCLES_PER_FRAME: u64 = Self::CLOCK_HZ / 60;
+    pub const SCREEN_WIDTH: usize = 224;
+    pub const SCREEN_HEIGHT: usize = 256;
+    /// How far a key-driven analog control moves each frame - end to end in half a second.
+    const ANALOG_RATE: f64 = 1.0 / 30.0;
+
+    pub fn new(def: &'static MachineDef, rom: &[u8]) -> Self {
+        let mut cpu = i8080::cpu::CPU::new();
+        cpu.memory.load(0x000, rom);
+
+        let mut machine = Self {
+            def,
+            cpu,
+            io:  MidwayIO::new(def),
+            held: HashSet::new(),
+            analog: def.analog.iter().map(|analog| analog.rest).collect(),
+            instructions: 0,
+            cycles: 0,
+            frames: 0,
+            trace: None,
+            blocks: None,
+        };
+        for i in 0..def.analog.len() {
+            machine.write_analog(i);
+        }
+        machine
+    }
+
+    pub fn step(&mut self) -> Result<(), String> {
+        self.update_analog();
+        self.half_step(true)?;
+        self.half_step(false)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn half_step(&mut self, top_half: bool) -> Result<(), String> {
+        let mut cycles_spent = 0;
+        while cycles_spent < Self::CYCLES_PER_FRAME / 2 {
+            if let Some(trace) = self.trace.as_mut() {
+                if let Err(e) = writeln!(trace, "{}", self.cpu.trace_line(self.cycles)) {
+                    eprintln!("Stopping trace: {}", e);
+                    self.trace = None;
+                }
+            }
+
+            let blocks = if self.trace.is_none() { self.blocks.as_mut() } else { None };
+            let ran = blocks::run(blocks, &mut self.cpu, &mut self.io, Self::CYCLES_PER_FRAME / 2 - cycles_spent).map_err(|e| e.to_string())?;
+            cycles_spent += ran.cycles;
+            self.instructions += ran.instructions;
+            self.cycles += ran.cycles;
+        }
+
+        self.cpu.interrupt(if top_half { 1 } else { 2 });
+        Ok(())
+    }
+
+    pub fn vram(&self) -> &[u8] {
+        self.cpu.memory.view(0x2400, 0x3FFF)
+    }
+
+    pub fn set_input(&mut self, input: Input, pressed: bool) {
+        if pressed {
+            self.held.insert(input);
+        } else {
+            self.held.remove(&input);
+        }
+        for wiring in self.def.inputs.iter().filter(|wiring| wiring.input == input) {
+            set_key(&mut self.io.ports[wiring.port as usize], wiring.bit, pressed != wiring.active_low);
+        }
+    }
+
+    /// Puts an analog control at `value`, from 0.0 to 1.0, e.g. from a mouse or a gamepad axis.
+    pub fn set_analog(&mut self, control: Analog, value: f64) {
+        for i in 0..self.def.analog.len() {
+            if self.def.analog[i].control == control {
+                self.analog[i] = value.clamp(0.0, 1.0);
+                self.write_analog(i);
+            }
+        }
+    }
+
+    pub fn analog(&self, control: Analog) -> Option<f64> {
+        self.def.analog.iter().position(|analog| analog.control == control).map(|i| self.analog[i])
+    }
+
+    fn update_analog(&mut self) {
+        for (i, analog) in self.def.analog.iter().enumerate() {
+            let (down, up) = analog.keys;
+            let value = &mut self.analog[i];
+            match (self.held.contains(&down), self.held.contains(&up)) {
+                (true, false) => *value = (*value - Self::ANALOG_RATE).max(0.0),
+                (false, true) => *value = (*value + Self::ANALOG_RATE).min(1.0),
+                _ if analog.springs_back => {
+                    let distance = analog.rest - *value;
+                    *value += distance.clamp(-Self::ANALOG_RATE, Self::ANALOG_RATE);
+                },
+                _ => {},
+            }
+        }
+        for i in 0..self.def.analog.len() {
+            self.write_analog(i);
+        }
+    }
+
+    fn write_analog(&mut self, i: usize) {
+        let analog = &self.def.analog[i];
+        let port = &mut self.io.ports[analog.port as usize];
+        *port = (*port & !analog.mask) | analog.encode(self.analog[i]);
+    }
+
+    pub fn set_dip(&mut self, name: &str, setting: &str) -> Result<(), String> {
+        let dip = self.def.dip(name).ok_or_else(|| {
+            let names: Vec<&str> = self.def.dips.iter().map(|dip| dip.name).collect();
+            format!("{} has no DIP switch '{}', it has: {}", self.def.name, name, names.join(", "))
+        })?;
+        self.io.set_dip(dip, setting)
+    }
+
+    /// Sounds switched on or off since the last call, oldest first.
+    pub fn take_sound_events(&mut self) -> Vec<SoundEvent> {
+        std::mem::take(&mut self.io.sound_events)
+    }
+}
+
+pub struct MidwayIO {
+    def: &'static MachineDef,
+    shifter: ShiftRegister,
+    ports: [u8; 8],
+    latches: [u8; 8],
+    sound_events: Vec<SoundEvent>,
+}
+
+impl MidwayIO {
+    pub fn new(def: &'static MachineDef) -> Self {
+        let mut io = Self {
+            def,
+            shifter: ShiftRegister::new(),
+            ports: [0; 8],
+            latches: [0; 8],
+            sound_events: Vec::new(),
+        };
+        for &(port, value) in def.port_defaults {
+            io.ports[port as usize] = value;
+        }
+        for dip in def.dips {
+            io.set_dip(dip, dip.default).expect("DIP switch default must be one of its settings");
+        }
+        io
+    }
+
+    fn set_dip(&mut self, dip: &Dip, setting: &str) -> Result<(), String> {
+        let &(_, value) = dip.settings.iter().find(|&&(name, _)| name == setting).ok_or_else(|| {
+            let names: Vec<&str> = dip.settings.iter().map(|&(name, _)| name).collect();
+            format!("DIP switch '{}' can't be '{}', expected one of: {}", dip.name, setting, names.join(", "))
+        })?;
+        let port = &mut self.ports[dip.port as usize];
+        *port = (*port & !dip.mask) | (value & dip.mask);
+        Ok(())
+    }
+}
+
+pub fn set_key(port: &mut u8, bit: u8, on: bool) {
+    if on {
+        *port |= 1 << bit
+    } else {
+        *port &= !(1 << bit)
+    }
+}
+
+impl IO for MidwayIO {
+    fn input(&mut self, port: u8) -> u8 {
+        let wiring = &self.def.shifter;
+        let port = if self.def.mirrored_inputs { port & 0b11 } else { port };
+        match port {
+            _ if port == wiring.result_port => self.shifter.result(),
+            _ if Some(port) == wiring.reversed_result_port => self.shifter.reversed_result(),
+            0..=7 => self.ports[port as usize],
+            _ => 0,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        let wiring = &self.def.shifter;
+        match port {
+            _ if port == wiring.count_port => self.shifter.write_count(value),
+            _ if port == wiring.data_port => self.shifter.write_data(value),
+            0..=7 => {
+                let changed = self.latches[port as usize] ^ value;
+                self.latches[port as usize] = value;
+                for sound in self.def.sounds.iter().filter(|s| s.port == port && changed & (1 << s.bit) != 0) {
+                    self.sound_events.push(SoundEvent { name: sound.name, on: value & (1 << sound.bit) != 0 });
+                }
+            }
+            // the watchdog and anything unconnected
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::games::{INVADERS, LRESCUE, SEAWOLF, ZZZAP};
+    use crate::roms;
+    use i8080::register::Reg16::{BC, DE, HL, PC, PSW, SP};
+    use std::path::Path;
+
+    #[test]
+    fn test_inputs_follow_wiring() {
+        let mut machine = Midway8080::new(&INVADERS, &[]);
+        assert_eq!(machine.io.input(1), 0b0000_1000);
+
+        machine.set_input(Input::Coin, true);
+        machine.set_input(Input::P1Right, true);
+        assert_eq!(machine.io.input(1), 0b0100_1001);
+
+        machine.set_input(Input::Coin, false);
+        assert_eq!(machine.io.input(1), 0b0100_1000);
+
+        machine.set_input(Input::P2Fire, true);
+        assert_eq!(machine.io.input(2) & 0b0001_0000, 0b0001_0000);
+    }
+
+    #[test]
+    fn test_input_names() {
+        for &input in Input::ALL.iter() {
+            assert_eq!(Input::from_name(input.name()), Some(input));
+        }
+        assert_eq!(Input::from_name("P1-Fire"), Some(Input::P1Fire));
+        assert_eq!(Input::from_name("jump"), None);
+    }
+
+    #[test]
+    fn test_dips() {
+        let mut machine = Midway8080::new(&INVADERS, &[]);
+        assert_eq!(machine.io.input(2) & 0b11, 0);
+
+        machine.set_dip("lives", "6").unwrap();
+        assert_eq!(machine.io.input(2) & 0b11, 0b11);
+
+        assert!(machine.set_dip("lives", "9").is_err());
+        assert!(machine.set_dip("difficulty", "hard").is_err());
+
+        let mut machine = Midway8080::new(&LRESCUE, &[]);
+        assert_eq!(machine.io.input(2) & 0b11, 0);
+    }
+
+    #[test]
+    fn test_shift_register() {
+        let mut io = MidwayIO::new(&INVADERS);
+        io.output(4, 0xAB);
+        io.output(4, 0xCD);
+        io.output(2, 0);
+        assert_eq!(io.input(3), 0xCD);
+        io.output(2, 4);
+        assert_eq!(io.input(3), 0xDA);
+    }
+
+    #[test]
+    fn test_shifter_wiring() {
+        let mut io = MidwayIO::new(&SEAWOLF);
+        io.output(4, 0x00);
+        io.output(4, 0x01);
+        io.output(3, 0);
+        assert_eq!(io.input(3), 0x01);
+        assert_eq!(io.input(0), 0x80);
+        // mirrored
+        assert_eq!(io.input(7), 0x01);
+
+        let mut io = MidwayIO::new(&ZZZAP);
+        io.output(3, 0xAB);
+        io.output(3, 0xCD);
+        io.output(4, 4);
+        assert_eq!(io.input(3), 0xDA);
+    }
+
+    #[test]
+    fn test_analog_encoding() {
+        let periscope = SEAWOLF.analog[0];
+        assert_eq!(periscope.encode(0.0), 0);
+        assert_eq!(periscope.encode(1.0), 0b1_0000);
+        assert_eq!(periscope.encode(3.0 / 31.0), 0b0_0010);
+        assert_eq!(periscope.encode(-1.0), 0);
+    }
+
+    #[test]
+    fn test_keys_drive_analog_controls() {
+        let mut machine = Midway8080::new(&ZZZAP, &[]);
+        assert_eq!(machine.analog(Analog::Steering), Some(0.5));
+        assert_eq!(machine.analog(Analog::Periscope), None);
+
+        machine.set_input(Input::P1Left, true);
+        for _ in 0..10 {
+            machine.update_analog();
+        }
+        let steering = machine.analog(Analog::Steering).unwrap();
+        assert!(steering < 0.2, "{}", steering);
+        assert!(machine.io.input(1) < 0x40);
+
+        // the wheel springs back to the centre once let go
+        machine.set_input(Input::P1Left, false);
+        for _ in 0..30 {
+            machine.update_analog();
+        }
+        assert_eq!(machine.analog(Analog::Steering), Some(0.5));
+        assert_eq!(machine.io.input(1), 0x80);
+
+        machine.set_analog(Analog::Pedal, 1.0);
+        assert_eq!(machine.io.input(0) & 0x0F, 0x0F);
+    }
+
+    #[test]
+    fn test_sound_latches() {
+        let mut machine = Midway8080::new(&INVADERS, &[]);
+        machine.io.output(3, 0b0000_0010);
+        machine.io.output(3, 0b0000_0010);
+        machine.io.output(3, 0b0000_0000);
+        machine.io.output(5, 0b0000_0001);
+
+        assert_eq!(machine.take_sound_events(), vec![
+            SoundEvent { name: "shot", on: true },
+            SoundEvent { name: "shot", on: false },
+            SoundEvent { name: "fleet 1", on: true },
+        ]);
+        assert_eq!(machine.take_sound_events(), vec![]);
+    }
+
+    #[test]
+    fn test_blocks_match_the_interpreter() {
+        let rom = roms::load(Path::new("../ROMS"), INVADERS.roms).unwrap();
+        let mut interpreted = Midway8080::new(&INVADERS, &rom);
+        let mut cached = Midway8080::new(&INVADERS, &rom);
+        cached.blocks = Some(BlockCache::new());
+        for frame in 0..300 {
+            interpreted.step().unwrap();
+            cached.step().unwrap();
+            assert_eq!((cached.instructions, cached.cycles), (interpreted.instructions, interpreted.cycles), "frame {}", frame);
+            assert_eq!(cached.take_sound_events(), interpreted.take_sound_events(), "frame {}", frame);
+        }
+        for &pair in [PSW, BC, DE, HL, SP, PC].iter() {
+            assert_eq!(cached.cpu.reg[pair], interpreted.cpu.reg[pair], "{:?}", pair);
+        }
+        assert!(cached.cpu.memory.view(0, 0xffff) == interpreted.cpu.memory.view(0, 0xffff));
+    }
+}
@@ -0,0 +1,70 @@
+/* The MB14241 barrel shifter. The 8080 has no fast multi-bit shift, so the board keeps the last
+   two bytes written to it and returns any 8-bit window of those 16 bits. Which ports it sits on
+   differs from board to board, and some boards can also read the result back bit-reversed on a
+   port of its own. */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShifterWiring {
+    pub count_port: u8,
+    pub data_port: u8,
+    pub result_port: u8,
+    pub reversed_result_port: Option<u8>,
+}
+
+impl ShifterWiring {
+    /// The wiring used by Space Invaders and most of the games that followed it.
+    pub const STANDARD: ShifterWiring = ShifterWiring {
+        count_port: 2,
+        data_port: 4,
+        result_port: 3,
+        reversed_result_port: None,
+    };
+}
+
+#[derive(Debug, Default)]
+pub struct ShiftRegister {
+    data: u16,
+    count: u8,
+}
+
+impl ShiftRegister {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data = (u16::from(value) << 8) | (self.data >> 8);
+    }
+
+    pub fn write_count(&mut self, value: u8) {
+        self.count = value & 0b111;
+    }
+
+    pub fn result(&self) -> u8 {
+        (self.data >> (8 - self.count)) as u8
+    }
+
+    pub fn reversed_result(&self) -> u8 {
+        self.result().reverse_bits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift() {
+        let mut shifter = ShiftRegister::new();
+        shifter.write_data(0xAB);
+        shifter.write_data(0xCD);
+
+        shifter.write_count(0);
+        assert_eq!(shifter.result(), 0xCD);
+        shifter.write_count(4);
+        assert_eq!(shifter.result(), 0xDA);
+        shifter.write_count(7);
+        assert_eq!(shifter.result(), 0b1101_0101);
+        assert_eq!(shifter.reversed_result(), 0b1010_1011);
+    }
+}
@@ -0,0 +1,290 @@
+use i8080::io::InterruptInstruction;
+
+/* The Intel 8259 programmable interrupt controller, for machines with more interrupt sources than
+   the 8080's single INT line. It takes eight request lines, IR0 the most urgent, and answers the
+   CPU's acknowledge with a CALL to that line's handler. Handlers are 4 or 8 bytes apart in a table
+   whose address the program sets during initialisation.
+
+   It sits on two ports, told apart by the A0 address line. Writing A0=0 with bit 4 set starts
+   the initialisation sequence (ICW1, then ICW2 to ICW4 on A0=1); after that A0=1 writes the mask
+   and A0=0 writes commands: end of interrupt, or which register a read of A0=0 returns. Only the
+   fully nested mode 8080 systems use is modelled - no rotating priorities, special mask mode,
+   polling or cascading. */
+
+const ICW1: u8 = 0b0001_0000;
+const ICW1_NEEDS_ICW4: u8 = 0b0000_0001;
+const ICW1_SINGLE: u8 = 0b0000_0010;
+const ICW1_INTERVAL_4: u8 = 0b0000_0100;
+const ICW1_LEVEL_TRIGGERED: u8 = 0b0000_1000;
+const ICW4_AUTO_EOI: u8 = 0b0000_0010;
+const OCW3: u8 = 0b0000_1000;
+const OCW3_READ_REGISTER: u8 = 0b0000_0010;
+const OCW3_READ_ISR: u8 = 0b0000_0001;
+const OCW2_EOI: u8 = 0b0010_0000;
+const OCW2_SPECIFIC: u8 = 0b0100_0000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Expecting {
+    Icw2,
+    Icw3,
+    Icw4,
+    Commands,
+}
+
+#[derive(Debug)]
+pub struct Pic8259 {
+    /// Interrupt request register: lines waiting to be acknowledged.
+    irr: u8,
+    /// In-service register: lines whose handlers haven't sent an end of interrupt yet.
+    isr: u8,
+    /// Interrupt mask register.
+    imr: u8,
+    lines: u8,
+    icw1: u8,
+    vector_base: u16,
+    auto_eoi: bool,
+    read_isr: bool,
+    expecting: Expecting,
+}
+
+impl Pic8259 {
+    pub fn new() -> Self {
+        Self {
+            irr: 0,
+            isr: 0,
+            imr: 0xFF,
+            lines: 0,
+            icw1: 0,
+            vector_base: 0,
+            auto_eoi: false,
+            read_isr: false,
+            expecting: Expecting::Commands,
+        }
+    }
+
+    /// Raises or lowers request line `line`. Edge triggered lines latch a request when they go
+    /// high; level triggered ones request for as long as they are high.
+    pub fn set_line(&mut self, line: u8, active: bool) {
+        let bit = 1 << (line & 7);
+        let rising = active && self.lines & bit == 0;
+        if active {
+            self.lines |= bit;
+        } else {
+            self.lines &= !bit;
+        }
+        if self.icw1 & ICW1_LEVEL_TRIGGERED != 0 {
+            self.irr = (self.irr & !bit) | (self.lines & bit);
+        } else if rising {
+            self.irr |= bit;
+        }
+    }
+
+    pub fn read(&self, a0: bool) -> u8 {
+        match (a0, self.read_isr) {
+            (true, _) => self.imr,
+            (false, true) => self.isr,
+            (false, false) => self.irr,
+        }
+    }
+
+    pub fn write(&mut self, a0: bool, value: u8) {
+        if !a0 && value & ICW1 != 0 {
+            self.icw1 = value;
+            self.vector_base = u16::from(value & self.vector_low_mask());
+            self.irr = 0;
+            self.isr = 0;
+            self.imr = 0;
+            self.auto_eoi = false;
+            self.read_isr = false;
+            self.expecting = Expecting::Icw2;
+            return;
+        }
+
+        self.expecting = match (a0, self.expecting) {
+            (true, Expecting::Icw2) => {
+                self.vector_base = self.vector_base & 0x00FF | u16::from(value) << 8;
+                self.after_icw3()
+            },
+            (true, Expecting::Icw3) => self.after_icw3(),
+            (true, Expecting::Icw4) => {
+                self.auto_eoi = value & ICW4_AUTO_EOI != 0;
+                Expecting::Commands
+            },
+            (true, _) => {
+                self.imr = value;
+                Expecting::Commands
+            },
+            (false, _) if value & OCW3 != 0 => {
+                if value & OCW3_READ_REGISTER != 0 {
+                    self.read_isr = value & OCW3_READ_ISR != 0;
+                }
+                Expecting::Commands
+            },
+            (false, _) => {
+                if value & OCW2_EOI != 0 {
+                    let bit = if value & OCW2_SPECIFIC != 0 {
+                        1 << (value & 7)
+                    } else {
+                        self.isr & self.isr.wrapping_neg()
+                    };
+                    self.isr &= !bit;
+                }
+                Expecting::Commands
+            },
+        };
+    }
+
+    fn after_icw3(&self) -> Expecting {
+        if self.expecting == Expecting::Icw2 && self.icw1 & ICW1_SINGLE == 0 {
+            Expecting::Icw3
+        } else if self.icw1 & ICW1_NEEDS_ICW4 != 0 {
+            Expecting::Icw4
+        } else {
+            Expecting::Commands
+        }
+    }
+
+    /// The bits of ICW1 that go into the low byte of the vector table address.
+    fn vector_low_mask(&self) -> u8 {
+        if self.icw1 & ICW1_INTERVAL_4 != 0 { 0b1110_0000 } else { 0b1100_0000 }
+    }
+
+    /// The most urgent line that is requesting, unmasked, and more urgent than any being serviced.
+    fn highest_request(&self) -> Option<u8> {
+        let requests = self.irr & !self.imr;
+        if requests == 0 {
+            return None;
+        }
+        let line = requests.trailing_zeros() as u8;
+        let in_service = self.isr.trailing_zeros() as u8;
+        if line < in_service { Some(line) } else { None }
+    }
+
+    pub fn interrupt_pending(&self) -> bool {
+        self.expecting == Expecting::Commands && self.highest_request().is_some()
+    }
+
+    /// Answers the CPU's interrupt acknowledge with a CALL to the handler for the most urgent
+    /// request, if there is one.
+    pub fn acknowledge(&mut self) -> Option<InterruptInstruction> {
+        if !self.interrupt_pending() {
+            return None;
+        }
+        let line = self.highest_request()?;
+        let bit = 1 << line;
+        if self.icw1 & ICW1_LEVEL_TRIGGERED == 0 {
+            self.irr &= !bit;
+        }
+        if !self.auto_eoi {
+            self.isr |= bit;
+        }
+        let interval = if self.icw1 & ICW1_INTERVAL_4 != 0 { 4 } else { 8 };
+        Some(InterruptInstruction::Call(self.vector_base + u16::from(line) * interval))
+    }
+}
+
+impl Default for Pic8259 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i8080::cpu::CPU;
+    use i8080::io::IO;
+    use i8080::register::Reg16::{PC, SP};
+
+    /// The usual set up: a single 8259 with its table at 0x1000, 4 bytes apart, edge triggered.
+    fn initialised() -> Pic8259 {
+        let mut pic = Pic8259::new();
+        pic.write(false, ICW1 | ICW1_INTERVAL_4 | ICW1_SINGLE);
+        pic.write(true, 0x10);
+        pic.write(true, 0x00);
+        pic
+    }
+
+    #[test]
+    fn test_priority_and_end_of_interrupt() {
+        let mut pic = initialised();
+        pic.set_line(5, true);
+        pic.set_line(2, true);
+        assert_eq!(pic.read(false), 0b0010_0100);
+
+        assert_eq!(pic.acknowledge(), Some(InterruptInstruction::Call(0x1008)));
+        // line 5 waits for line 2's handler to finish
+        assert_eq!(pic.acknowledge(), None);
+        pic.write(false, OCW3 | OCW3_READ_REGISTER | OCW3_READ_ISR);
+        assert_eq!(pic.read(false), 0b0000_0100);
+
+        // but a more urgent line gets in
+        pic.set_line(0, true);
+        assert_eq!(pic.acknowledge(), Some(InterruptInstruction::Call(0x1000)));
+
+        pic.write(false, OCW2_EOI);
+        pic.write(false, OCW2_EOI);
+        assert_eq!(pic.acknowledge(), Some(InterruptInstruction::Call(0x1014)));
+        pic.write(false, OCW2_EOI | OCW2_SPECIFIC | 5);
+        assert_eq!(pic.read(false), 0);
+    }
+
+    #[test]
+    fn test_masks_and_triggering() {
+        let mut pic = initialised();
+        pic.write(true, 0b0000_1000);
+        pic.set_line(3, true);
+        assert!(!pic.interrupt_pending());
+        pic.write(true, 0);
+        assert!(pic.interrupt_pending());
+        pic.acknowledge();
+        pic.write(false, OCW2_EOI);
+        // edge triggered: still high, but already taken
+        assert!(!pic.interrupt_pending());
+
+        // 8 byte intervals, level triggered
+        let mut pic = Pic8259::new();
+        pic.write(false, ICW1 | ICW1_LEVEL_TRIGGERED | ICW1_SINGLE | 0b0100_0000);
+        pic.write(true, 0x20);
+        pic.set_line(3, true);
+        assert_eq!(pic.acknowledge(), Some(InterruptInstruction::Call(0x2058)));
+        pic.write(false, OCW2_EOI);
+        assert!(pic.interrupt_pending());
+        pic.set_line(3, false);
+        assert!(!pic.interrupt_pending());
+    }
+
+    struct PicIO(Pic8259);
+
+    impl IO for PicIO {
+        fn input(&mut self, port: u8) -> u8 {
+            self.0.read(port & 1 != 0)
+        }
+
+        fn output(&mut self, port: u8, value: u8) {
+            self.0.write(port & 1 != 0, value)
+        }
+
+        fn acknowledge_interrupt(&mut self) -> Option<InterruptInstruction> {
+            self.0.acknowledge()
+        }
+    }
+
+    #[test]
+    fn test_cpu_takes_calls_from_the_controller() {
+        let mut cpu = CPU::new();
+        let mut io = PicIO(initialised());
+        // EI; HLT
+        cpu.memory.load(0, &[0xfb, 0x76]);
+        cpu.reg[SP] = 0x2000;
+        cpu.tick(&mut io);
+        cpu.tick(&mut io);
+        assert!(cpu.halted);
+
+        io.0.set_line(6, true);
+        assert_eq!(cpu.tick(&mut io), 17);
+        assert_eq!(cpu.reg[PC], 0x1018);
+        assert!(!cpu.halted);
+        assert_eq!(cpu.memory.view(0x1ffe, 0x1fff), &[0x02, 0x00]);
+    }
+}
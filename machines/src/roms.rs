@@ -0,0 +1,264 @@
+use std::fmt;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/* Arcade boards hold their program in several small EPROMs, and ROM dumps come either as one file
+   per chip (the MAME layout), a zip of those files, or one concatenated image. Whichever form is
+   given, every chip is checked against its known size and CRC32 before being placed at its
+   offset in the image handed to the CPU.
+
+   Some boards leave a gap in the address space between chips. A concatenated image of one of
+   those may have the chips back to back, in the order they're listed, or padded out so that
+   each sits at its offset. */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Chip {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+    pub crc32: u32,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RomError {
+    Io(PathBuf, std::io::Error),
+    Zip(PathBuf, String),
+    Missing(&'static str),
+    WrongSize { name: String, expected: usize, actual: usize },
+    BadChecksum { name: &'static str, expected: u32, actual: u32 },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            RomError::Io(path, e) => write!(f, "Could not read {}: {}", path.display(), e),
+            RomError::Zip(path, e) => write!(f, "Could not read zip {}: {}", path.display(), e),
+            RomError::Missing(name) => write!(f, "ROM {} is missing from the set", name),
+            RomError::WrongSize { name, expected, actual } => write!(
+                f, "ROM {} is {} bytes, expected {} bytes", name, actual, expected),
+            RomError::BadChecksum { name, expected, actual } => write!(
+                f, "ROM {} has CRC32 {:08x}, expected {:08x} - it may be a bad dump or a different version",
+                name, actual, expected),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+/// Loads `chips` from a directory of chip files, a zip of them, or a single concatenated image,
+/// returning an image large enough to hold every chip at its offset.
+pub fn load(path: &Path, chips: &[Chip]) -> Result<Vec<u8>, RomError> {
+    if path.is_dir() {
+        assemble(chips, |chip| {
+            let chip_path = path.join(chip.name);
+            if !chip_path.exists() {
+                return Err(RomError::Missing(chip.name));
+            }
+            fs::read(&chip_path).map_err(|e| RomError::Io(chip_path, e))
+        })
+    } else {
+        let bytes = fs::read(path).map_err(|e| RomError::Io(path.to_path_buf(), e))?;
+        load_bytes(path, bytes, chips)
+    }
+}
+
+/// Loads `chips` from a zip of them or a single concatenated image already in memory, for when
+/// there's no file system to read from.
+pub fn from_bytes(bytes: Vec<u8>, chips: &[Chip]) -> Result<Vec<u8>, RomError> {
+    load_bytes(Path::new("image"), bytes, chips)
+}
+
+fn load_bytes(path: &Path, bytes: Vec<u8>, chips: &[Chip]) -> Result<Vec<u8>, RomError> {
+    if bytes.starts_with(b"PK\x03\x04") {
+        load_zip(path, bytes, chips)
+    } else {
+        load_image(path, &bytes, chips)
+    }
+}
+
+fn load_zip(path: &Path, bytes: Vec<u8>, chips: &[Chip]) -> Result<Vec<u8>, RomError> {
+    let zip_error = |e: zip::result::ZipError| RomError::Zip(path.to_path_buf(), e.to_string());
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(zip_error)?;
+
+    assemble(chips, |chip| {
+        // some zips keep the files in a folder, so only the file name has to match
+        let index = (0..archive.len()).find(|&i| {
+            archive.by_index(i).map(|file| file.name().rsplit('/').next() == Some(chip.name)).unwrap_or(false)
+        });
+        let mut file = match index {
+            Some(index) => archive.by_index(index).map_err(zip_error)?,
+            None => return Err(RomError::Missing(chip.name)),
+        };
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).map_err(|e| RomError::Io(path.join(chip.name), e))?;
+        Ok(data)
+    })
+}
+
+fn load_image(path: &Path, bytes: &[u8], chips: &[Chip]) -> Result<Vec<u8>, RomError> {
+    let back_to_back: usize = chips.iter().map(|chip| chip.size).sum();
+    if bytes.len() == back_to_back {
+        let mut start = 0;
+        assemble(chips, |chip| {
+            let data = bytes[start..(start + chip.size)].to_vec();
+            start += chip.size;
+            Ok(data)
+        })
+    } else if bytes.len() == image_size(chips) {
+        assemble(chips, |chip| Ok(bytes[chip.offset..(chip.offset + chip.size)].to_vec()))
+    } else {
+        Err(RomError::WrongSize {
+            name: path.display().to_string(),
+            expected: back_to_back,
+            actual: bytes.len(),
+        })
+    }
+}
+
+fn assemble<F>(chips: &[Chip], mut read: F) -> Result<Vec<u8>, RomError>
+    where F: FnMut(&Chip) -> Result<Vec<u8>, RomError>
+{
+    let mut image = vec![0; image_size(chips)];
+    for chip in chips {
+        let data = read(chip)?;
+        verify(chip, &data)?;
+        image[chip.offset..(chip.offset + chip.size)].copy_from_slice(&data);
+    }
+    Ok(image)
+}
+
+fn verify(chip: &Chip, data: &[u8]) -> Result<(), RomError> {
+    if data.len() != chip.size {
+        return Err(RomError::WrongSize {
+            name: chip.name.to_string(),
+            expected: chip.size,
+            actual: data.len(),
+        });
+    }
+    let crc32 = crc32fast::hash(data);
+    if crc32 != chip.crc32 {
+        return Err(RomError::BadChecksum { name: chip.name, expected: chip.crc32, actual: crc32 });
+    }
+    Ok(())
+}
+
+fn image_size(chips: &[Chip]) -> usize {
+    chips.iter().map(|chip| chip.offset + chip.size).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::midway::games;
+
+    const INVADERS: &[Chip] = games::INVADERS.roms;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-8080-roms-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_split_set() {
+        let image = load(Path::new("../ROMS"), INVADERS).unwrap();
+        assert_eq!(image, fs::read("../ROMS/invaders").unwrap());
+    }
+
+    #[test]
+    fn test_load_concatenated_image() {
+        let image = load(Path::new("../ROMS/invaders"), INVADERS).unwrap();
+        assert_eq!(image.len(), 0x2000);
+        assert_eq!(&image[0x800..0x1000], &fs::read("../ROMS/invaders.g").unwrap()[..]);
+    }
+
+    #[test]
+    fn test_load_zip() {
+        let dir = scratch_dir("zip");
+        let zip_path = dir.join("invaders.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        for chip in INVADERS.iter().rev() {
+            zip.start_file(format!("invaders/{}", chip.name), zip::write::FileOptions::default()).unwrap();
+            zip.write_all(&fs::read(Path::new("../ROMS").join(chip.name)).unwrap()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let image = load(&zip_path, INVADERS).unwrap();
+        assert_eq!(image, fs::read("../ROMS/invaders").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let image = fs::read("../ROMS/invaders").unwrap();
+        assert_eq!(from_bytes(image.clone(), INVADERS).unwrap(), image);
+
+        let error = from_bytes(image[..0x1000].to_vec(), INVADERS).unwrap_err();
+        assert_eq!(error.to_string(), "ROM image is 4096 bytes, expected 8192 bytes");
+    }
+
+    #[test]
+    fn test_image_with_a_gap() {
+        // two made up chips either side of a gap, like the sets with code at 0x4000
+        let (low, high) = (vec![0x11; 0x800], vec![0x22; 0x800]);
+        let chips = [
+            Chip { name: "low", offset: 0x0000, size: 0x800, crc32: crc32fast::hash(&low) },
+            Chip { name: "high", offset: 0x4000, size: 0x800, crc32: crc32fast::hash(&high) },
+        ];
+        let mut padded = vec![0; 0x4800];
+        padded[..0x800].copy_from_slice(&low);
+        padded[0x4000..].copy_from_slice(&high);
+
+        let back_to_back = [low, high].concat();
+        assert_eq!(from_bytes(back_to_back.clone(), &chips).unwrap(), padded);
+        assert_eq!(from_bytes(padded.clone(), &chips).unwrap(), padded);
+
+        let error = from_bytes(back_to_back[..0x800].to_vec(), &chips).unwrap_err();
+        assert_eq!(error.to_string(), "ROM image is 2048 bytes, expected 4096 bytes");
+    }
+
+    #[test]
+    fn test_missing_chip() {
+        let dir = scratch_dir("missing");
+        fs::copy("../ROMS/invaders.h", dir.join("invaders.h")).unwrap();
+
+        match load(&dir, INVADERS) {
+            Err(RomError::Missing("invaders.g")) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bad_checksum_and_size() {
+        let dir = scratch_dir("bad");
+        for chip in INVADERS.iter() {
+            fs::copy(Path::new("../ROMS").join(chip.name), dir.join(chip.name)).unwrap();
+        }
+        let mut data = fs::read(dir.join("invaders.f")).unwrap();
+        data[0] ^= 0xFF;
+        fs::write(dir.join("invaders.f"), &data).unwrap();
+
+        match load(&dir, INVADERS) {
+            Err(RomError::BadChecksum { name: "invaders.f", expected: 0x0cce_ad96, .. }) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+
+        fs::write(dir.join("invaders.f"), &data[..0x400]).unwrap();
+        match load(&dir, INVADERS) {
+            Err(RomError::WrongSize { expected: 0x800, actual: 0x400, .. }) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wrongly_sized_image() {
+        let error = load(Path::new("../ROMS/cpudiag.bin"), INVADERS).unwrap_err();
+        assert_eq!(error.to_string(), "ROM ../ROMS/cpudiag.bin is 1453 bytes, expected 8192 bytes");
+    }
+}
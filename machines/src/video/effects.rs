@@ -0,0 +1,183 @@
+use super::framebuffer::Framebuffer;
+use super::overlay::Rgb;
+
+/* Post-processing that imitates the cabinet CRT. Everything here works on the CPU-side
+   framebuffer, so screenshots and recordings get exactly what the window shows. */
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct EffectOptions {
+    pub phosphor: bool,
+    pub scanlines: bool,
+    pub bloom: bool,
+}
+
+impl EffectOptions {
+    /// Parses a comma separated list such as `phosphor,bloom`. `crt` turns everything on.
+    pub fn from_list(list: &str) -> Result<Self, String> {
+        let mut options = Self::default();
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match name {
+                "phosphor" => options.phosphor = true,
+                "scanlines" => options.scanlines = true,
+                "bloom" => options.bloom = true,
+                "crt" => options = Self { phosphor: true, scanlines: true, bloom: true },
+                "none" => options = Self::default(),
+                _ => return Err(format!("Unknown effect '{}', expected phosphor, scanlines, bloom, crt or none", name)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn any(&self) -> bool {
+        self.phosphor || self.scanlines || self.bloom
+    }
+}
+
+pub struct Effects {
+    pub options: EffectOptions,
+    previous: Vec<Rgb>,
+}
+
+impl Effects {
+    /// How much of the previous frame's brightness survives into the next one.
+    const PHOSPHOR_DECAY: f32 = 0.55;
+    /// Brightness kept on the darkened lines.
+    const SCANLINE_LEVEL: f32 = 0.7;
+    const BLOOM_RADIUS: usize = 2;
+    const BLOOM_STRENGTH: f32 = 0.6;
+
+    pub fn new(options: EffectOptions) -> Self {
+        Self {
+            options,
+            previous: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        if self.options.bloom {
+            bloom(framebuffer);
+        }
+
+        if self.options.phosphor {
+            if self.previous.len() == framebuffer.pixels.len() {
+                for (px, old) in framebuffer.pixels.iter_mut().zip(self.previous.iter()) {
+                    *px = max(*px, scale(*old, Self::PHOSPHOR_DECAY));
+                }
+            }
+            // taken before the scanlines so the darkening doesn't compound frame on frame
+            self.previous.clone_from(&framebuffer.pixels);
+        }
+
+        if self.options.scanlines {
+            // the monitor is on its side, so its scanlines run down the upright picture
+            for row in framebuffer.pixels.chunks_mut(framebuffer.width) {
+                for px in row.iter_mut().skip(1).step_by(2) {
+                    *px = scale(*px, Self::SCANLINE_LEVEL);
+                }
+            }
+        }
+    }
+}
+
+fn bloom(framebuffer: &mut Framebuffer) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let horizontal = box_blur(&framebuffer.pixels, width, height, 1, width);
+    let blurred = box_blur(&horizontal, width, height, width, 1);
+
+    for (px, glow) in framebuffer.pixels.iter_mut().zip(blurred.iter()) {
+        let glow = scale(*glow, Effects::BLOOM_STRENGTH);
+        *px = (px.0.saturating_add(glow.0), px.1.saturating_add(glow.1), px.2.saturating_add(glow.2));
+    }
+}
+
+/* One pass of a separable box blur. `step` is the distance between neighbouring pixels along the
+   blur direction and `line` the distance between successive lines, so the same code does both
+   the horizontal and vertical passes. */
+fn box_blur(pixels: &[Rgb], width: usize, height: usize, step: usize, line: usize) -> Vec<Rgb> {
+    let (lines, length) = if step == 1 { (height, width) } else { (width, height) };
+    let radius = Effects::BLOOM_RADIUS;
+    let mut out = vec![(0, 0, 0); pixels.len()];
+
+    for l in 0..lines {
+        for i in 0..length {
+            let (lo, hi) = (i.saturating_sub(radius), (i + radius).min(length - 1));
+            let mut sum = (0u32, 0u32, 0u32);
+            for j in lo..=hi {
+                let px = pixels[l * line + j * step];
+                sum = (sum.0 + px.0 as u32, sum.1 + px.1 as u32, sum.2 + px.2 as u32);
+            }
+            let n = (2 * radius + 1) as u32;
+            out[l * line + i * step] = ((sum.0 / n) as u8, (sum.1 / n) as u8, (sum.2 / n) as u8);
+        }
+    }
+    out
+}
+
+fn scale(px: Rgb, factor: f32) -> Rgb {
+    ((px.0 as f32 * factor) as u8, (px.1 as f32 * factor) as u8, (px.2 as f32 * factor) as u8)
+}
+
+fn max(a: Rgb, b: Rgb) -> Rgb {
+    (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgb = (0xFF, 0xFF, 0xFF);
+
+    fn single_pixel(column: usize, row: usize) -> Framebuffer {
+        let mut fb = Framebuffer::new();
+        fb.pixels[row * fb.width + column] = WHITE;
+        fb
+    }
+
+    #[test]
+    fn test_from_list() {
+        assert_eq!(EffectOptions::from_list("").unwrap(), EffectOptions::default());
+        let options = EffectOptions::from_list("phosphor, bloom").unwrap();
+        assert!(options.phosphor && options.bloom && !options.scanlines);
+        assert!(EffectOptions::from_list("crt").unwrap().scanlines);
+        assert!(EffectOptions::from_list("vhs").is_err());
+    }
+
+    #[test]
+    fn test_phosphor_persists_and_decays() {
+        let mut effects = Effects::new(EffectOptions { phosphor: true, ..Default::default() });
+
+        let mut fb = single_pixel(10, 10);
+        effects.apply(&mut fb);
+
+        let mut fb = Framebuffer::new();
+        effects.apply(&mut fb);
+        let ghost = fb.pixel(10, 10);
+        assert!(ghost.0 > 0 && ghost.0 < 0xFF);
+
+        let mut fb = Framebuffer::new();
+        effects.apply(&mut fb);
+        assert!(fb.pixel(10, 10).0 < ghost.0);
+    }
+
+    #[test]
+    fn test_scanlines_darken_alternate_columns() {
+        let mut effects = Effects::new(EffectOptions { scanlines: true, ..Default::default() });
+        let mut fb = single_pixel(0, 0);
+        fb.pixels[1] = WHITE;
+        effects.apply(&mut fb);
+
+        assert_eq!(fb.pixel(0, 0), WHITE);
+        assert!(fb.pixel(1, 0).0 < 0xFF);
+    }
+
+    #[test]
+    fn test_bloom_spreads_light() {
+        let mut effects = Effects::new(EffectOptions { bloom: true, ..Default::default() });
+        let mut fb = single_pixel(50, 50);
+        effects.apply(&mut fb);
+
+        assert_eq!(fb.pixel(50, 50), WHITE);
+        assert!(fb.pixel(51, 51).0 > 0);
+        assert_eq!(fb.pixel(60, 60), (0, 0, 0));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use super::overlay::{Overlay, Rgb};
+
+/* An RGB image of the screen in the upright orientation the player sees, i.e. already rotated
+   90 degrees anticlockwise from the way the hardware scans VRAM out. */
+
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Framebuffer {
+    pub const WIDTH: usize = 224;
+    pub const HEIGHT: usize = 256;
+
+    pub fn new() -> Self {
+        Self {
+            width: Self::WIDTH,
+            height: Self::HEIGHT,
+            pixels: vec![(0, 0, 0); Self::WIDTH * Self::HEIGHT],
+        }
+    }
+
+    /// Expands the 1bpp VRAM at 0x2400..=0x3FFF into colour, tinting lit pixels with `overlay`.
+    pub fn render(&mut self, vram: &[u8], overlay: Overlay) {
+        for (column, line) in vram.chunks(32).take(self.width).enumerate() {
+            for (i, px) in line.iter().enumerate() {
+                for b in 0..8 {
+                    let row = self.height - 1 - (8 * i + b);
+                    self.pixels[row * self.width + column] = if px & (1 << b) != 0 {
+                        overlay.colour(row, column)
+                    } else {
+                        (0, 0, 0)
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn pixel(&self, column: usize, row: usize) -> Rgb {
+        self.pixels[row * self.width + column]
+    }
+
+    /// The pixels as opaque RGBA bytes, row by row, the layout a canvas `ImageData` takes.
+    pub fn rgba(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|&(r, g, b)| [r, g, b, 0xFF]).collect()
+    }
+
+    pub fn write_ppm(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for &(r, g, b) in self.pixels.iter() {
+            file.write_all(&[r, g, b])?;
+        }
+        file.flush()
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/* Dumps every frame it is given as a numbered PPM into a directory, which can be stitched into a
+   video afterwards with e.g. `ffmpeg -i frame-%05d.ppm out.mp4`. */
+
+pub struct Recorder {
+    directory: std::path::PathBuf,
+    frame: u64,
+}
+
+impl Recorder {
+    pub fn start(directory: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            frame: 0,
+        })
+    }
+
+    pub fn record(&mut self, framebuffer: &Framebuffer) -> io::Result<()> {
+        let path = self.directory.join(format!("frame-{:05}.ppm", self.frame));
+        self.frame += 1;
+        framebuffer.write_ppm(&path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_rotates_vram() {
+        let mut vram = vec![0u8; 0x1C00];
+        vram[0] = 0b0000_0001; // first column, bottom pixel
+        vram[32 * 223 + 31] = 0b1000_0000; // last column, top pixel
+
+        let mut fb = Framebuffer::new();
+        fb.render(&vram, Overlay::BlackAndWhite);
+
+        assert_eq!(fb.pixel(0, 255), (0xFF, 0xFF, 0xFF));
+        assert_eq!(fb.pixel(223, 0), (0xFF, 0xFF, 0xFF));
+        assert_eq!(fb.pixel(0, 0), (0, 0, 0));
+        assert_eq!(fb.pixels.iter().filter(|&&p| p != (0, 0, 0)).count(), 2);
+    }
+
+    #[test]
+    fn test_rgba() {
+        let mut vram = vec![0u8; 0x1C00];
+        vram[0] = 0b0000_0001;
+
+        let mut fb = Framebuffer::new();
+        fb.render(&vram, Overlay::BlackAndWhite);
+
+        let rgba = fb.rgba();
+        assert_eq!(rgba.len(), Framebuffer::WIDTH * Framebuffer::HEIGHT * 4);
+        assert_eq!(&rgba[..4], &[0, 0, 0, 0xFF]);
+        let bottom_left = 255 * Framebuffer::WIDTH * 4;
+        assert_eq!(&rgba[bottom_left..bottom_left + 4], &[0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_render_applies_overlay() {
+        let vram = vec![0xFFu8; 0x1C00];
+
+        let mut fb = Framebuffer::new();
+        fb.render(&vram, Overlay::Midway);
+
+        assert_eq!(fb.pixel(100, 40), Overlay::Midway.colour(40, 100));
+        assert_eq!(fb.pixel(100, 200), Overlay::Midway.colour(200, 100));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod overlay;
+pub mod framebuffer;
+pub mod effects;
@@ -0,0 +1,87 @@
+/* The cabinet monitor was black & white - the colour came from strips of cellophane gel stuck
+   over the glass. Rows and columns here are in the upright (rotated) orientation, so row 0 is
+   the top of the screen as the player sees it. */
+
+pub type Rgb = (u8, u8, u8);
+
+const WHITE: Rgb = (0xFF, 0xFF, 0xFF);
+const RED: Rgb = (0xFF, 0x20, 0x20);
+const GREEN: Rgb = (0x20, 0xFF, 0x20);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Overlay {
+    BlackAndWhite,
+    Midway,
+    Tv,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 3] = [Overlay::BlackAndWhite, Overlay::Midway, Overlay::Tv];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bw" | "none" | "original" => Some(Overlay::BlackAndWhite),
+            "midway" | "upright" => Some(Overlay::Midway),
+            "tv" => Some(Overlay::Tv),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Overlay::BlackAndWhite => "bw",
+            Overlay::Midway => "midway",
+            Overlay::Tv => "tv",
+        }
+    }
+
+    /// The colour a lit pixel takes at the given screen position.
+    pub fn colour(self, row: usize, column: usize) -> Rgb {
+        match self {
+            Overlay::BlackAndWhite => WHITE,
+            Overlay::Midway => match row {
+                32..=63 => RED,
+                184..=239 => GREEN,
+                // the strip over the reserve cannons stops short of the credit counter
+                240..=255 if (16..134).contains(&column) => GREEN,
+                _ => WHITE,
+            },
+            Overlay::Tv => match row {
+                16..=71 => RED,
+                192..=255 => GREEN,
+                _ => WHITE,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        for overlay in Overlay::ALL.iter() {
+            assert_eq!(Overlay::from_name(overlay.name()), Some(*overlay));
+        }
+        assert_eq!(Overlay::from_name("Midway"), Some(Overlay::Midway));
+        assert_eq!(Overlay::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn test_midway_bands() {
+        assert_eq!(Overlay::Midway.colour(0, 100), WHITE);
+        assert_eq!(Overlay::Midway.colour(40, 100), RED);
+        assert_eq!(Overlay::Midway.colour(100, 100), WHITE);
+        assert_eq!(Overlay::Midway.colour(200, 0), GREEN);
+        assert_eq!(Overlay::Midway.colour(250, 20), GREEN);
+        assert_eq!(Overlay::Midway.colour(250, 200), WHITE);
+    }
+
+    #[test]
+    fn test_black_and_white_is_plain() {
+        for row in 0..256 {
+            assert_eq!(Overlay::BlackAndWhite.colour(row, 0), WHITE);
+        }
+    }
+}
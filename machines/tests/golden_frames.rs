@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use machines::midway::games::INVADERS;
+use machines::midway::{Input, Midway8080};
+use machines::roms;
+use machines::video::framebuffer::Framebuffer;
+use machines::video::overlay::Overlay;
+
+/* End to end checks that Space Invaders still boots and plays: each script runs the machine from
+   power on, pressing and releasing inputs on given frames, and compares VRAM at checkpoints with
+   frames saved from a good run in tests/golden. A mismatch writes a picture of the difference to
+   golden-diffs in cargo's scratch directory for tests (target/tmp unless the target directory is
+   moved) - white where both are lit, red where only the golden frame is and green where only
+   this run is - along with this run's VRAM.
+
+   After a change that is meant to alter the picture, check the diffs and then rerun with
+   UPDATE_GOLDEN=1 to save the new frames. */
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+const DIFF_DIR: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/golden-diffs");
+const ROMS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../ROMS");
+
+/// An input pressed (true) or released on a frame.
+type Event = (u64, Input, bool);
+
+struct Script {
+    name: &'static str,
+    events: &'static [Event],
+    /// Frames after which VRAM is compared.
+    checkpoints: &'static [u64],
+}
+
+const ATTRACT: Script = Script {
+    name: "attract",
+    events: &[],
+    checkpoints: &[120, 600, 1500],
+};
+
+const COIN_AND_START: Script = Script {
+    name: "start",
+    events: &[
+        (100, Input::Coin, true),
+        (105, Input::Coin, false),
+        (200, Input::P1Start, true),
+        (205, Input::P1Start, false),
+    ],
+    checkpoints: &[180, 400],
+};
+
+const PLAY: Script = Script {
+    name: "play",
+    events: &[
+        (100, Input::Coin, true),
+        (105, Input::Coin, false),
+        (200, Input::P1Start, true),
+        (205, Input::P1Start, false),
+        (600, Input::P1Right, true),
+        (660, Input::P1Right, false),
+        (670, Input::P1Fire, true),
+        (674, Input::P1Fire, false),
+        (720, Input::P1Left, true),
+        (760, Input::P1Left, false),
+        (770, Input::P1Fire, true),
+        (774, Input::P1Fire, false),
+    ],
+    checkpoints: &[660, 690, 800],
+};
+
+fn golden_path(name: &str, frame: u64) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(format!("invaders-{}-{}.vram", name, frame))
+}
+
+/// The two frames overlaid: white in both, red only in the golden one, green only in ours.
+fn diff_image(golden: &[u8], ours: &[u8]) -> Framebuffer {
+    let (mut expected, mut actual) = (Framebuffer::new(), Framebuffer::new());
+    expected.render(golden, Overlay::BlackAndWhite);
+    actual.render(ours, Overlay::BlackAndWhite);
+    let mut diff = Framebuffer::new();
+    for (i, pixel) in diff.pixels.iter_mut().enumerate() {
+        *pixel = match (expected.pixels[i] != (0, 0, 0), actual.pixels[i] != (0, 0, 0)) {
+            (true, true) => (0xff, 0xff, 0xff),
+            (true, false) => (0xff, 0x20, 0x20),
+            (false, true) => (0x20, 0xff, 0x20),
+            (false, false) => (0, 0, 0),
+        };
+    }
+    diff
+}
+
+/// Compares VRAM with a golden frame, returning a description of the mismatch if there is one.
+fn check(name: &str, frame: u64, vram: &[u8]) -> Option<String> {
+    let path = golden_path(name, frame);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(GOLDEN_DIR).unwrap();
+        fs::write(&path, vram).unwrap();
+        return None;
+    }
+    let golden = match fs::read(&path) {
+        Ok(golden) => golden,
+        Err(e) => return Some(format!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", path.display(), e)),
+    };
+    if golden == vram {
+        return None;
+    }
+
+    let pixels: u32 = golden.iter().zip(vram.iter()).map(|(a, b)| (a ^ b).count_ones()).sum();
+    let stem = format!("invaders-{}-{}", name, frame);
+    let diff = Path::new(DIFF_DIR).join(format!("{}.ppm", stem));
+    fs::create_dir_all(DIFF_DIR).unwrap();
+    diff_image(&golden, vram).write_ppm(&diff).unwrap();
+    fs::write(Path::new(DIFF_DIR).join(format!("{}.vram", stem)), vram).unwrap();
+    Some(format!("{} differs from {} in {} pixels, see {}", name, path.display(), pixels, diff.display()))
+}
+
+/// Runs a script from power on, returning every mismatch.
+fn run(script: &Script) -> Vec<String> {
+    let rom = roms::load(Path::new(ROMS), INVADERS.roms).unwrap();
+    let mut machine = Midway8080::new(&INVADERS, &rom);
+    let last = *script.checkpoints.iter().max().unwrap();
+    let mut failures = Vec::new();
+    for frame in 0..last {
+        for &(_, input, pressed) in script.events.iter().filter(|event| event.0 == frame) {
+            machine.set_input(input, pressed);
+        }
+        machine.step().unwrap();
+        machine.take_sound_events();
+        if script.checkpoints.contains(&(frame + 1)) {
+            failures.extend(check(script.name, frame + 1, machine.vram()));
+        }
+    }
+    failures
+}
+
+#[test]
+fn test_attract_mode() {
+    let failures = run(&ATTRACT);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_coin_and_start() {
+    let failures = run(&COIN_AND_START);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_move_and_fire() {
+    let failures = run(&PLAY);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_diff_image() {
+    let mut golden = vec![0; 0x1C00];
+    let mut ours = golden.clone();
+    // the bottom left pixel lit in both, the next one up only in the golden frame
+    golden[0] = 0b11;
+    ours[0] = 0b01;
+    // and one only in ours, a column to the right
+    ours[32] = 0b1;
+    let diff = diff_image(&golden, &ours);
+    assert_eq!(diff.pixel(0, 255), (0xff, 0xff, 0xff));
+    assert_eq!(diff.pixel(0, 254), (0xff, 0x20, 0x20));
+    assert_eq!(diff.pixel(1, 255), (0x20, 0xff, 0x20));
+    assert_eq!(diff.pixel(1, 254), (0, 0, 0));
+}
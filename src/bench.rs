@@ -0,0 +1,315 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use i8080::blocks::{BlockCache, Ran};
+use i8080::cpu::CPU;
+use i8080::io::NullIO;
+use i8080::register::Reg16::{PC, SP};
+use machines::midway::games::INVADERS;
+use machines::midway::Midway8080;
+use machines::roms;
+
+/* Measures how fast the interpreter runs, in the manner of Criterion: each benchmark is warmed up,
+   then timed over several samples of enough iterations to take a measurable time, and reported as
+   the mean and standard deviation of one iteration along with the throughput that gives - host
+   instructions per second, and the emulated clock rate compared with the real chip's. */
+
+const WARM_UP: Duration = Duration::from_millis(500);
+const SAMPLES: usize = 10;
+const SAMPLE_TIME: Duration = Duration::from_millis(200);
+
+/// What one iteration of a benchmark did.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Work {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+/// Runs one iteration of a benchmark.
+pub type Workload = Box<dyn FnMut() -> Work>;
+
+pub struct Benchmark {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The clock rate of the machine being emulated, to compare the emulated one with.
+    pub clock_hz: u64,
+    /// Builds a workload; it is run once per iteration.
+    pub setup: fn() -> Result<Workload, String>,
+}
+
+pub const ALL: [Benchmark; 6] = [
+    Benchmark {
+        name: "cpudiag",
+        description: "the cpudiag CP/M diagnostic from start to finish",
+        clock_hz: 2_000_000,
+        setup: cpudiag,
+    },
+    Benchmark {
+        name: "loop",
+        description: "65536 passes of a loop of arithmetic, memory, stack and branch instructions",
+        clock_hz: 2_000_000,
+        setup: exerciser_loop,
+    },
+    Benchmark {
+        name: "invaders",
+        description: "300 frames of Space Invaders from power on, headless",
+        clock_hz: Midway8080::CLOCK_HZ,
+        setup: invaders,
+    },
+    Benchmark {
+        name: "cpudiag-blocks",
+        description: "cpudiag, from a block cache that starts empty each run",
+        clock_hz: 2_000_000,
+        setup: cpudiag_blocks,
+    },
+    Benchmark {
+        name: "loop-blocks",
+        description: "the loop, from the block cache",
+        clock_hz: 2_000_000,
+        setup: exerciser_loop_blocks,
+    },
+    Benchmark {
+        name: "invaders-blocks",
+        description: "Space Invaders, from the block cache",
+        clock_hz: Midway8080::CLOCK_HZ,
+        setup: invaders_blocks,
+    },
+];
+
+/// Runs instructions until `done`, from the block cache if there is one.
+fn run_until<F: Fn(&CPU) -> bool>(cpu: &mut CPU, mut blocks: Option<&mut BlockCache>, done: F) -> Work {
+    let mut work = Work { instructions: 0, cycles: 0 };
+    while !done(cpu) {
+        let ran = match blocks.as_deref_mut() {
+            Some(blocks) => blocks.run(cpu, &mut NullIO, u64::MAX).unwrap(),
+            None => Ran { instructions: 1, cycles: cpu.tick(&mut NullIO) },
+        };
+        work.cycles += ran.cycles;
+        work.instructions += ran.instructions;
+    }
+    work
+}
+
+fn cpudiag() -> Result<Workload, String> {
+    cpudiag_with(false)
+}
+
+fn cpudiag_blocks() -> Result<Workload, String> {
+    cpudiag_with(true)
+}
+
+/// Runs a CP/M program with BDOS calls that just return, until it jumps to 0.
+fn cpudiag_with(use_blocks: bool) -> Result<Workload, String> {
+    let program = std::fs::read("ROMS/cpudiag.bin").map_err(|e| format!("Could not read ROMS/cpudiag.bin: {}", e))?;
+    Ok(Box::new(move || {
+        let mut cpu = CPU::new();
+        let mut blocks = if use_blocks { Some(BlockCache::new()) } else { None };
+        cpu.memory.load(0x100, &program);
+        // BDOS calls return straight away, and the top of memory is just below it
+        cpu.memory.load(0x0005, &[0xc9, 0x00, 0xff]);
+        cpu.reg[SP] = 0xff00;
+        cpu.reg[PC] = 0x100;
+        run_until(&mut cpu, blocks.as_mut(), |cpu| cpu.reg[PC] == 0)
+    }))
+}
+
+/// A loop of commonly used instructions, counting BC down from 65536 to 0, then HLT.
+const LOOP: &[u8] = &[
+    0x31, 0x00, 0x10,       // LXI SP, $1000
+    0x01, 0x00, 0x00,       // LXI B, $0000
+    0x21, 0x00, 0x20,       // LXI H, $2000
+    0x79,                   // loop: MOV A, C
+    0x86,                   // ADD M
+    0x77,                   // MOV M, A
+    0x2c,                   // INR L
+    0xee, 0x5a,             // XRI $5a
+    0x27,                   // DAA
+    0x1f,                   // RAR
+    0xcd, 0x20, 0x00,       // CALL $0020
+    0x0b,                   // DCX B
+    0x78,                   // MOV A, B
+    0xb1,                   // ORA C
+    0xc2, 0x09, 0x00,       // JNZ loop
+    0x76,                   // HLT
+    0x00, 0x00, 0x00, 0x00, 0x00,
+    0xe5,                   // $0020: PUSH H
+    0x26, 0x20,             // MVI H, $20
+    0xe1,                   // POP H
+    0xc9,                   // RET
+];
+
+fn exerciser_loop() -> Result<Workload, String> {
+    exerciser_loop_with(None)
+}
+
+fn exerciser_loop_blocks() -> Result<Workload, String> {
+    exerciser_loop_with(Some(BlockCache::new()))
+}
+
+/// The cache is kept between iterations, as it would be while a program runs.
+fn exerciser_loop_with(mut blocks: Option<BlockCache>) -> Result<Workload, String> {
+    let mut cpu = CPU::new();
+    Ok(Box::new(move || {
+        cpu.memory.load(0, LOOP);
+        cpu.reg[PC] = 0;
+        cpu.halted = false;
+        run_until(&mut cpu, blocks.as_mut(), |cpu| cpu.halted)
+    }))
+}
+
+fn invaders() -> Result<Workload, String> {
+    invaders_with(false)
+}
+
+fn invaders_blocks() -> Result<Workload, String> {
+    invaders_with(true)
+}
+
+fn invaders_with(use_blocks: bool) -> Result<Workload, String> {
+    let rom = roms::load(Path::new("./ROMS"), INVADERS.roms).map_err(|e| e.to_string())?;
+    Ok(Box::new(move || {
+        let mut machine = Midway8080::new(&INVADERS, &rom);
+        if use_blocks {
+            machine.blocks = Some(BlockCache::new());
+        }
+        for _ in 0..300 {
+            machine.step().unwrap();
+            machine.take_sound_events();
+        }
+        Work { instructions: machine.instructions, cycles: machine.cycles }
+    }))
+}
+
+/// The timings of one benchmark.
+#[derive(Debug)]
+pub struct Measurement {
+    pub work: Work,
+    /// Seconds per iteration, one for each sample.
+    pub samples: Vec<f64>,
+}
+
+impl Measurement {
+    pub fn mean(&self) -> f64 {
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let variance = self.samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / self.samples.len().max(2).saturating_sub(1) as f64;
+        variance.sqrt()
+    }
+
+    pub fn instructions_per_second(&self) -> f64 {
+        self.work.instructions as f64 / self.mean()
+    }
+
+    pub fn emulated_hz(&self) -> f64 {
+        self.work.cycles as f64 / self.mean()
+    }
+}
+
+pub fn measure(benchmark: &Benchmark) -> Result<Measurement, String> {
+    let mut run = (benchmark.setup)()?;
+
+    // warm up, and find how many iterations make a sample
+    let start = Instant::now();
+    let mut iterations = 0u32;
+    let mut work = run();
+    while start.elapsed() < WARM_UP {
+        work = run();
+        iterations += 1;
+    }
+    let per_iteration = start.elapsed().as_secs_f64() / f64::from(iterations.max(1));
+    let iterations = ((SAMPLE_TIME.as_secs_f64() / per_iteration).ceil() as u32).max(1);
+
+    let samples = (0..SAMPLES).map(|_| {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            run();
+        }
+        start.elapsed().as_secs_f64() / f64::from(iterations)
+    }).collect();
+    Ok(Measurement { work, samples })
+}
+
+/// Formats seconds with a unit that suits them.
+fn format_time(seconds: f64) -> String {
+    if seconds >= 1.0 {
+        format!("{:.3} s", seconds)
+    } else if seconds >= 1e-3 {
+        format!("{:.3} ms", seconds * 1e3)
+    } else {
+        format!("{:.3} µs", seconds * 1e6)
+    }
+}
+
+pub fn report(benchmark: &Benchmark, measurement: &Measurement) -> String {
+    let mhz = measurement.emulated_hz() / 1e6;
+    format!(
+        "{:<15} time: {} ± {}   {:.1} M instructions/s   {:.1} MHz emulated, {:.1}x a {} MHz chip",
+        benchmark.name,
+        format_time(measurement.mean()),
+        format_time(measurement.std_dev()),
+        measurement.instructions_per_second() / 1e6,
+        mhz,
+        measurement.emulated_hz() / benchmark.clock_hz as f64,
+        benchmark.clock_hz as f64 / 1e6,
+    )
+}
+
+/// Runs every benchmark, or only the ones named, printing each result as it comes.
+pub fn run(names: &[String]) -> Result<(), String> {
+    if cfg!(debug_assertions) {
+        println!("This is a debug build, run with --release for meaningful numbers");
+    }
+    for name in names.iter() {
+        if !ALL.iter().any(|benchmark| benchmark.name == name) {
+            let names: Vec<&str> = ALL.iter().map(|benchmark| benchmark.name).collect();
+            return Err(format!("Unknown benchmark '{}', expected one of: {}", name, names.join(", ")));
+        }
+    }
+    for benchmark in ALL.iter().filter(|benchmark| names.is_empty() || names.iter().any(|name| name == benchmark.name)) {
+        println!("Benchmarking {}: {}", benchmark.name, benchmark.description);
+        let measurement = measure(benchmark)?;
+        println!("{}", report(benchmark, &measurement));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workloads() {
+        let mut cpudiag = cpudiag().unwrap();
+        let first = cpudiag();
+        assert!(first.instructions > 500, "{:?}", first);
+        assert_eq!(cpudiag(), first);
+
+        // 3 to set up, 16 a pass, then the HLT
+        let mut exerciser = exerciser_loop().unwrap();
+        assert_eq!(exerciser().instructions, 3 + 65536 * 16 + 1);
+        assert_eq!(exerciser().instructions, 3 + 65536 * 16 + 1);
+
+        // the block cache does the same work
+        assert_eq!(cpudiag_blocks().unwrap()(), first);
+        let mut exerciser = exerciser_loop_blocks().unwrap();
+        let work = exerciser();
+        assert_eq!(work, exerciser());
+        assert_eq!(work, exerciser_loop().unwrap()());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let measurement = Measurement {
+            work: Work { instructions: 2_000, cycles: 8_000 },
+            samples: vec![0.001, 0.002, 0.003],
+        };
+        assert!((measurement.mean() - 0.002).abs() < 1e-12);
+        assert!((measurement.std_dev() - 0.001).abs() < 1e-12);
+        assert!((measurement.instructions_per_second() - 1e6).abs() < 1e-3);
+        let report = report(&ALL[1], &measurement);
+        assert_eq!(report, "loop            time: 2.000 ms ± 1.000 ms   1.0 M instructions/s   4.0 MHz emulated, 2.0x a 2 MHz chip");
+    }
+}
@@ -0,0 +1,448 @@
+use std::path::PathBuf;
+
+#[cfg(feature = "sdl")]
+use crate::frontend::sdl::DisplayOptions;
+use crate::frontend::terminal::Glyphs;
+use i8080::i8085::Variant;
+use crate::trace_diff::TraceDiffOptions;
+use machines::video::effects::EffectOptions;
+use machines::video::overlay::Overlay;
+
+/* The usage text is put together from pieces so that a build without the sdl feature doesn't
+   offer the window's options. */
+
+const USAGE_START: &str = "\
+Usage: rust-8080 [OPTIONS] [ROM]
+       rust-8080 test-vectors [--cpu 8080|8085] DIR
+       rust-8080 trace-diff REFERENCE [--instructions N] [--context N] [OPTIONS] [ROM]
+
+ROM is a directory holding the split ROM set, a zip of it, or a concatenated image
+(default: ./ROMS). For the altair it is a program to load into memory, e.g. Altair BASIC, and
+for cpm the disk image for drive A.
+
+Options:
+  --machine NAME        machine to emulate (default: invaders)
+  --list-machines       list the machines that can be emulated
+  --bench [NAME...]     time the interpreter and the block cache on cpudiag, a loop and Space
+                        Invaders (or the ones named), printing instructions per second and the
+                        emulated clock rate
+  --dip NAME=SETTING    set one of the machine's DIP switches, e.g. --dip lives=5
+  --org ADDRESS         altair: where to load the program (default: 0)
+  --start ADDRESS       altair: where to start running (default: the load address)
+  --switches VALUE      altair: front panel sense switches read from port 0xff (default: 0)
+  --disk DRIVE=FILE     cpm: put an 8-inch disk image in drive A, B, C or D
+";
+
+#[cfg(feature = "sdl")]
+const FRONTEND_OPTIONS: &str = "  --terminal            play in the terminal instead of a window
+  --glyphs NAME         terminal glyphs: braille or halfblock (default: braille)
+  --scale N             window scale factor (default: 2)
+  --fullscreen          letterbox the picture to the whole display
+  --unrotated           show the monitor the way it is mounted in the cabinet
+";
+
+#[cfg(not(feature = "sdl"))]
+const FRONTEND_OPTIONS: &str = "  --terminal            play in the terminal, the only frontend in this build
+  --glyphs NAME         terminal glyphs: braille or halfblock (default: braille)
+";
+
+const USAGE_END: &str = "  --overlay NAME        colour overlay: bw, midway or tv (default: the machine's own)
+  --effects LIST        comma separated CRT effects: phosphor, scanlines, bloom, crt
+  --speed X             emulation speed multiplier, 0 for unthrottled (default: 1)
+  --headless FRAMES     run FRAMES frames without a frontend, then exit
+  --screenshot FILE     after a headless run, save the last frame as a PPM
+  --trace FILE          write a line per executed instruction to FILE (- for stderr)
+  --debug               print the CPU state and counters on exit
+  --strict              stop with an error at any undocumented 8080 opcode
+  --blocks              run code from a cache of decoded blocks, faster than the interpreter
+                        (ignored while tracing)
+  -h, --help            show this message
+
+test-vectors runs the JSON single step tests in DIR, a file per opcode, and prints how many of
+each opcode's tests passed. --cpu picks the chip to test (default: 8080).
+
+trace-diff runs the machine with its trace compared, line by line, to REFERENCE, a log of the
+same run from another emulator, and stops at the first instruction where they differ, showing
+--context lines either side (default: 3). It stops after --instructions N instructions, or at the
+end of the reference. With --trace, our own trace is written as well.";
+
+pub fn usage() -> String {
+    [USAGE_START, FRONTEND_OPTIONS, USAGE_END].concat()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frontend {
+    #[cfg(feature = "sdl")]
+    Sdl,
+    Terminal,
+    Headless(u64),
+    TraceDiff(TraceDiffOptions),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub rom: PathBuf,
+    pub machine: String,
+    pub dips: Vec<(String, String)>,
+    pub org: u16,
+    pub start: Option<u16>,
+    pub switches: u8,
+    pub disks: Vec<(usize, PathBuf)>,
+    pub frontend: Frontend,
+    pub overlay: Option<Overlay>,
+    pub effects: EffectOptions,
+    #[cfg(feature = "sdl")]
+    pub display: DisplayOptions,
+    pub glyphs: Glyphs,
+    pub speed: f64,
+    pub screenshot: Option<PathBuf>,
+    pub trace: Option<PathBuf>,
+    pub debug: bool,
+    pub strict: bool,
+    pub blocks: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            rom: PathBuf::from("./ROMS"),
+            machine: String::from("invaders"),
+            dips: Vec::new(),
+            org: 0,
+            start: None,
+            switches: 0,
+            disks: Vec::new(),
+            #[cfg(feature = "sdl")]
+            frontend: Frontend::Sdl,
+            #[cfg(not(feature = "sdl"))]
+            frontend: Frontend::Terminal,
+            overlay: None,
+            effects: EffectOptions::default(),
+            #[cfg(feature = "sdl")]
+            display: DisplayOptions::default(),
+            glyphs: Glyphs::Braille,
+            speed: 1.0,
+            screenshot: None,
+            trace: None,
+            debug: false,
+            strict: false,
+            blocks: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Options),
+    TestVectors { dir: PathBuf, variant: Variant },
+    ListMachines,
+    Bench(Vec<String>),
+    Help,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("test-vectors") => return parse_test_vectors(&args[1..]),
+        Some("trace-diff") => return parse_trace_diff(&args[1..]),
+        _ => {},
+    }
+
+    let mut options = Options::default();
+    let mut rom = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().map(String::as_str).ok_or(format!("{} expects {}", arg, what));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--machine" => options.machine = value("a machine name")?.to_string(),
+            "--list-machines" => return Ok(Command::ListMachines),
+            "--bench" => return Ok(Command::Bench(args.cloned().collect())),
+            "--dip" => {
+                let dip = value("NAME=SETTING")?;
+                let mut parts = dip.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(setting)) if !name.is_empty() => {
+                        options.dips.push((name.to_string(), setting.to_string()));
+                    },
+                    _ => return Err(format!("--dip expects NAME=SETTING, got '{}'", dip)),
+                }
+            },
+            "--org" => options.org = parse_address(value("an address")?).ok_or_else(|| {
+                String::from("--org expects an address from 0 to 0xffff")
+            })?,
+            "--start" => options.start = Some(parse_address(value("an address")?).ok_or_else(|| {
+                String::from("--start expects an address from 0 to 0xffff")
+            })?),
+            "--switches" => {
+                options.switches = match parse_number(value("a value")?) {
+                    Some(switches) if switches <= 0xFF => switches as u8,
+                    _ => return Err(String::from("--switches expects a value from 0 to 0xff")),
+                };
+            },
+            "--disk" => {
+                let disk = value("DRIVE=FILE")?;
+                let mut parts = disk.splitn(2, '=');
+                match (parts.next().map(|d| d.to_ascii_uppercase()), parts.next()) {
+                    (Some(drive), Some(file)) if ["A", "B", "C", "D"].contains(&drive.as_str()) && !file.is_empty() => {
+                        let drive = (drive.as_bytes()[0] - b'A') as usize;
+                        options.disks.retain(|&(d, _)| d != drive);
+                        options.disks.push((drive, PathBuf::from(file)));
+                    },
+                    _ => return Err(format!("--disk expects DRIVE=FILE with a drive from A to D, got '{}'", disk)),
+                }
+            },
+            "--terminal" => options.frontend = Frontend::Terminal,
+            "--glyphs" => {
+                let name = value("braille or halfblock")?;
+                options.glyphs = Glyphs::from_name(name)
+                    .ok_or(format!("Unknown glyphs '{}', expected braille or halfblock", name))?;
+            },
+            "--overlay" => {
+                let name = value("an overlay name")?;
+                options.overlay = Some(Overlay::from_name(name).ok_or_else(|| {
+                    let names: Vec<&str> = Overlay::ALL.iter().map(|o| o.name()).collect();
+                    format!("Unknown overlay '{}', expected one of: {}", name, names.join(", "))
+                })?);
+            },
+            "--effects" => options.effects = EffectOptions::from_list(value("a list of effects")?)?,
+            #[cfg(feature = "sdl")]
+            "--scale" => {
+                options.display.scale = match value("a positive whole number")?.parse() {
+                    Ok(scale) if scale > 0 => scale,
+                    _ => return Err(String::from("--scale expects a positive whole number")),
+                };
+            },
+            #[cfg(feature = "sdl")]
+            "--fullscreen" => options.display.fullscreen = true,
+            #[cfg(feature = "sdl")]
+            "--unrotated" => options.display.rotated = false,
+            "--speed" => {
+                options.speed = match value("a number")?.parse::<f64>() {
+                    Ok(speed) if speed >= 0.0 && speed.is_finite() => speed,
+                    _ => return Err(String::from("--speed expects a number that is 0 or more")),
+                };
+            },
+            "--headless" => {
+                let frames = value("a frame count")?.parse()
+                    .map_err(|_| String::from("--headless expects a whole number of frames"))?;
+                options.frontend = Frontend::Headless(frames);
+            },
+            "--screenshot" => options.screenshot = Some(PathBuf::from(value("a file name")?)),
+            "--trace" => options.trace = Some(PathBuf::from(value("a file name")?)),
+            "--debug" => options.debug = true,
+            "--strict" => options.strict = true,
+            "--blocks" => options.blocks = true,
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            path => {
+                if rom.is_some() {
+                    return Err(format!("Unexpected argument '{}', only one ROM can be given", path));
+                }
+                rom = Some(PathBuf::from(path));
+            },
+        }
+    }
+
+    if options.screenshot.is_some() {
+        if let Frontend::Headless(_) = options.frontend {} else {
+            return Err(String::from("--screenshot only applies to --headless runs"));
+        }
+    }
+
+    if let Some(rom) = rom {
+        options.rom = rom;
+    }
+    Ok(Command::Run(options))
+}
+
+fn parse_test_vectors(args: &[String]) -> Result<Command, String> {
+    let mut variant = Variant::I8080;
+    let mut dir = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--cpu" => variant = match args.next().map(String::as_str) {
+                Some("8080") => Variant::I8080,
+                Some("8085") => Variant::I8085,
+                _ => return Err(String::from("--cpu expects 8080 or 8085")),
+            },
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+            path if dir.is_none() => dir = Some(PathBuf::from(path)),
+            path => return Err(format!("Unexpected argument '{}', only one directory can be given", path)),
+        }
+    }
+
+    let dir = dir.ok_or(String::from("test-vectors expects a directory of tests"))?;
+    Ok(Command::TestVectors { dir, variant })
+}
+
+/// trace-diff takes the reference, its own options, and then anything a normal run takes.
+fn parse_trace_diff(args: &[String]) -> Result<Command, String> {
+    let reference = match args.first() {
+        Some(reference) if !reference.starts_with('-') => PathBuf::from(reference),
+        _ => return Err(String::from("trace-diff expects a reference trace file")),
+    };
+    let mut diff = TraceDiffOptions { reference, instructions: None, context: 3 };
+    let mut rest = Vec::new();
+    let mut args = args[1..].iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--instructions" => diff.instructions = match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) if n > 0 => Some(n),
+                _ => return Err(String::from("--instructions expects a positive whole number")),
+            },
+            "--context" => diff.context = args.next().and_then(|n| n.parse().ok())
+                .ok_or(String::from("--context expects a whole number of lines"))?,
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    match parse(&rest)? {
+        Command::Run(mut options) => {
+            if let Frontend::Headless(_) = options.frontend {
+                return Err(String::from("--headless doesn't apply to trace-diff"));
+            }
+            options.frontend = Frontend::TraceDiff(diff);
+            Ok(Command::Run(options))
+        },
+        command => Ok(command),
+    }
+}
+
+/// Parses a decimal number or, with a `0x` prefix, a hexadecimal one.
+fn parse_number(text: &str) -> Option<u32> {
+    if text.starts_with("0x") || text.starts_with("0X") {
+        u32::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    parse_number(text).filter(|&address| address <= 0xFFFF).map(|address| address as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Command, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse(&args)
+    }
+
+    fn options(args: &str) -> Options {
+        match parse_args(args) {
+            Ok(Command::Run(options)) => options,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(options(""), Options::default());
+    }
+
+    #[test]
+    fn test_rom_and_options() {
+        let options = options("--machine lrescue --overlay midway roms/lrescue.zip --speed 2.5 --debug --strict --blocks --dip lives=5");
+        assert_eq!(options.rom, PathBuf::from("roms/lrescue.zip"));
+        assert_eq!(options.machine, "lrescue");
+        assert_eq!(options.dips, vec![(String::from("lives"), String::from("5"))]);
+        assert_eq!(options.overlay, Some(Overlay::Midway));
+        assert_eq!(options.speed, 2.5);
+        assert!(options.debug);
+        assert!(options.strict);
+        assert!(options.blocks);
+    }
+
+    #[test]
+    fn test_headless() {
+        let options = options("--headless 120 --screenshot out.ppm --trace -");
+        assert_eq!(options.frontend, Frontend::Headless(120));
+        assert_eq!(options.screenshot, Some(PathBuf::from("out.ppm")));
+        assert_eq!(options.trace, Some(PathBuf::from("-")));
+    }
+
+    #[test]
+    fn test_altair() {
+        let options = options("--machine altair basic4k.bin --org 0x100 --start 256 --switches 0xfd");
+        assert_eq!(options.rom, PathBuf::from("basic4k.bin"));
+        assert_eq!(options.org, 0x100);
+        assert_eq!(options.start, Some(0x100));
+        assert_eq!(options.switches, 0xFD);
+
+        assert_eq!(parse_args("--org 0x10000"), Err(String::from("--org expects an address from 0 to 0xffff")));
+        assert_eq!(parse_args("--switches 256"), Err(String::from("--switches expects a value from 0 to 0xff")));
+    }
+
+    #[test]
+    fn test_disks() {
+        let options = options("--machine cpm --disk b=tools.dsk --disk C=work.dsk --disk B=other.dsk");
+        assert_eq!(options.disks, vec![(2, PathBuf::from("work.dsk")), (1, PathBuf::from("other.dsk"))]);
+
+        assert!(parse_args("--disk E=x.dsk").is_err());
+        assert!(parse_args("--disk A=").is_err());
+    }
+
+    #[test]
+    fn test_test_vectors() {
+        assert_eq!(parse_args("test-vectors tests/8080"),
+                   Ok(Command::TestVectors { dir: PathBuf::from("tests/8080"), variant: Variant::I8080 }));
+        assert_eq!(parse_args("test-vectors --cpu 8085 tests/8085"),
+                   Ok(Command::TestVectors { dir: PathBuf::from("tests/8085"), variant: Variant::I8085 }));
+        assert_eq!(parse_args("test-vectors"), Err(String::from("test-vectors expects a directory of tests")));
+        assert_eq!(parse_args("test-vectors --cpu z80 x"), Err(String::from("--cpu expects 8080 or 8085")));
+    }
+
+    #[test]
+    fn test_trace_diff() {
+        let options = options("trace-diff ref.log --machine cpm --instructions 5000 --context 10 disk.dsk");
+        assert_eq!(options.machine, "cpm");
+        assert_eq!(options.rom, PathBuf::from("disk.dsk"));
+        assert_eq!(options.frontend, Frontend::TraceDiff(TraceDiffOptions {
+            reference: PathBuf::from("ref.log"),
+            instructions: Some(5000),
+            context: 10,
+        }));
+
+        assert_eq!(parse_args("trace-diff --machine cpm"), Err(String::from("trace-diff expects a reference trace file")));
+        assert_eq!(parse_args("trace-diff ref.log --instructions 0"),
+                   Err(String::from("--instructions expects a positive whole number")));
+        assert!(parse_args("trace-diff ref.log --headless 10").is_err());
+    }
+
+    #[test]
+    fn test_bench() {
+        assert_eq!(parse_args("--bench"), Ok(Command::Bench(Vec::new())));
+        assert_eq!(parse_args("--bench loop invaders"),
+                   Ok(Command::Bench(vec![String::from("loop"), String::from("invaders")])));
+    }
+
+    #[test]
+    fn test_help() {
+        assert_eq!(parse_args("--overlay tv --help"), Ok(Command::Help));
+
+        // the window's options are only offered when there is a window
+        let usage = usage();
+        assert!(usage.contains("  --glyphs NAME ") && usage.contains("  --overlay NAME "), "{}", usage);
+        for option in ["--scale", "--fullscreen", "--unrotated"].iter() {
+            assert_eq!(usage.contains(option), cfg!(feature = "sdl"), "{}", option);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_args("--bogus"), Err(String::from("Unknown option '--bogus'")));
+        assert_eq!(parse_args("--overlay"), Err(String::from("--overlay expects an overlay name")));
+        assert_eq!(parse_args("--speed -1"), Err(String::from("--speed expects a number that is 0 or more")));
+        assert_eq!(parse_args("--headless lots"), Err(String::from("--headless expects a whole number of frames")));
+        assert_eq!(parse_args("a b"), Err(String::from("Unexpected argument 'b', only one ROM can be given")));
+        assert!(parse_args("--screenshot x.ppm").is_err());
+        assert_eq!(parse_args("--dip lives"), Err(String::from("--dip expects NAME=SETTING, got 'lives'")));
+    }
+}
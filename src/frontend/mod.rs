@@ -0,0 +1,103 @@
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod serial;
+pub mod terminal;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use machines::video::effects::{EffectOptions, Effects};
+use machines::video::framebuffer::{Framebuffer, Recorder};
+use machines::video::overlay::Overlay;
+
+/* Everything between VRAM and a frontend's output that every frontend wants: colouring,
+   post-processing, screenshots and recording. */
+
+pub struct Video {
+    pub overlay: Overlay,
+    pub framebuffer: Framebuffer,
+    effects: Effects,
+    recorder: Option<Recorder>,
+}
+
+impl Video {
+    pub fn new(overlay: Overlay, effects: EffectOptions) -> Self {
+        Self {
+            overlay,
+            framebuffer: Framebuffer::new(),
+            effects: Effects::new(effects),
+            recorder: None,
+        }
+    }
+
+    pub fn update(&mut self, vram: &[u8]) {
+        self.framebuffer.render(vram, self.overlay);
+        self.effects.apply(&mut self.framebuffer);
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(&self.framebuffer) {
+                eprintln!("Stopping recording: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+
+    pub fn screenshot(&self, frame: u64) -> String {
+        let path = format!("screenshot-{:06}.ppm", frame);
+        match self.framebuffer.write_ppm(Path::new(&path)) {
+            Ok(()) => format!("Saved {}", path),
+            Err(e) => format!("Could not save {}: {}", path, e),
+        }
+    }
+
+    pub fn toggle_recording(&mut self, frame: u64) -> String {
+        if self.recorder.take().is_some() {
+            return String::from("Recording stopped");
+        }
+
+        let directory = format!("recording-{:06}", frame);
+        match Recorder::start(Path::new(&directory)) {
+            Ok(recorder) => {
+                self.recorder = Some(recorder);
+                format!("Recording to {}/", directory)
+            },
+            Err(e) => format!("Could not start recording: {}", e),
+        }
+    }
+}
+
+/* Keeps a frontend running at the cabinet's 60 frames a second, scaled by the speed multiplier.
+   A speed of 0 runs as fast as the host allows. */
+
+pub struct Pacer {
+    frame_time: Option<Duration>,
+    next_frame: Instant,
+}
+
+impl Pacer {
+    const FRAMES_PER_SECOND: f64 = 60.0;
+
+    pub fn new(speed: f64) -> Self {
+        Self {
+            frame_time: if speed > 0.0 {
+                Some(Duration::from_secs_f64(1.0 / (Self::FRAMES_PER_SECOND * speed)))
+            } else {
+                None
+            },
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn wait(&mut self) {
+        if let Some(frame_time) = self.frame_time {
+            self.next_frame += frame_time;
+            let now = Instant::now();
+            if self.next_frame > now {
+                std::thread::sleep(self.next_frame - now);
+            } else {
+                // too far behind to catch up, so don't try to
+                self.next_frame = now;
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+
+use sdl2::pixels;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+use machines::midway::{Input, Midway8080};
+use machines::video::framebuffer::Framebuffer;
+use super::{Pacer, Video};
+
+pub fn run(machine: &mut Midway8080, video: &mut Video, display: DisplayOptions, speed: f64) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let mut screen = Screen::new(&sdl_context, machine.def.description, display)?;
+    let mut event_pump = sdl_context.event_pump()?;
+    let mut pacer = Pacer::new(speed);
+
+    loop {
+        machine.step()?;
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} => return Ok(()),
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return Ok(()),
+                Event::KeyDown { keycode: Some(Keycode::F11), repeat: false, .. } => {
+                    println!("{}", video.toggle_recording(machine.frames));
+                },
+                Event::KeyDown { keycode: Some(Keycode::F12), repeat: false, .. } => {
+                    println!("{}", video.screenshot(machine.frames));
+                },
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    if let Some(input) = input_for(keycode) {
+                        machine.set_input(input, true);
+                    }
+                },
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    if let Some(input) = input_for(keycode) {
+                        machine.set_input(input, false);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        // there is no audio output yet
+        machine.take_sound_events();
+
+        video.update(machine.vram());
+        screen.present(&video.framebuffer)?;
+        pacer.wait();
+    }
+}
+
+fn input_for(keycode: Keycode) -> Option<Input> {
+    match keycode {
+        Keycode::C => Some(Input::Coin),
+        Keycode::Num2 => Some(Input::P2Start),
+        Keycode::Num1 => Some(Input::P1Start),
+        Keycode::Space => Some(Input::P1Fire),
+        Keycode::A => Some(Input::P1Left),
+        Keycode::D => Some(Input::P1Right),
+        Keycode::W => Some(Input::P1Up),
+        Keycode::S => Some(Input::P1Down),
+        Keycode::Q => Some(Input::P1AimUp),
+        Keycode::E => Some(Input::P1AimDown),
+
+        Keycode::Return => Some(Input::P2Fire),
+        Keycode::Left => Some(Input::P2Left),
+        Keycode::Right => Some(Input::P2Right),
+        Keycode::Up => Some(Input::P2Up),
+        Keycode::Down => Some(Input::P2Down),
+        Keycode::PageUp => Some(Input::P2AimUp),
+        Keycode::PageDown => Some(Input::P2AimDown),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DisplayOptions {
+    pub scale: u32,
+    pub fullscreen: bool,
+    pub rotated: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            scale: 2,
+            fullscreen: false,
+            rotated: true,
+        }
+    }
+}
+
+/* The framebuffer is streamed into a texture once per frame and the GPU does the scaling. When
+   `rotated` is off the texture is turned back 90 degrees to show the raw scan-out orientation
+   of the monitor lying on its side. */
+
+pub struct Screen {
+    pub canvas: sdl2::render::WindowCanvas,
+    texture: sdl2::render::Texture,
+    options: DisplayOptions,
+}
+
+impl Screen {
+    pub fn new(sdl_context: &sdl2::Sdl, title: &str, options: DisplayOptions) -> Result<Screen, String> {
+        let video = sdl_context.video()?;
+        let (width, height) = Self::displayed_size(options.rotated);
+        let mut builder = video.window(title, width * options.scale, height * options.scale);
+        builder.position_centered().resizable();
+        if options.fullscreen {
+            builder.fullscreen_desktop();
+        }
+        let window = builder.build().map_err(|e| e.to_string())?;
+        let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        let texture = canvas
+            .create_texture_streaming(
+                pixels::PixelFormatEnum::RGB24,
+                Framebuffer::WIDTH as u32,
+                Framebuffer::HEIGHT as u32,
+            )
+            .map_err(|e| e.to_string())?;
+
+        canvas.set_draw_color(pixels::Color::RGB(0,0,0));
+        canvas.clear();
+        canvas.present();
+
+        Ok(Screen {
+            canvas,
+            texture,
+            options,
+        })
+    }
+
+    fn displayed_size(rotated: bool) -> (u32, u32) {
+        if rotated {
+            (Framebuffer::WIDTH as u32, Framebuffer::HEIGHT as u32)
+        } else {
+            (Framebuffer::HEIGHT as u32, Framebuffer::WIDTH as u32)
+        }
+    }
+
+    pub fn present(&mut self, framebuffer: &Framebuffer) -> Result<(), String> {
+        self.texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+            for (y, row) in framebuffer.pixels.chunks(framebuffer.width).enumerate() {
+                let line = &mut buffer[(y * pitch)..(y * pitch + 3 * framebuffer.width)];
+                for (dest, &(r, g, b)) in line.chunks_mut(3).zip(row.iter()) {
+                    dest.copy_from_slice(&[r, g, b]);
+                }
+            }
+        })?;
+
+        let (width, height) = Self::displayed_size(self.options.rotated);
+        let target = letterbox(self.canvas.output_size()?, (width, height));
+
+        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        if self.options.rotated {
+            self.canvas.copy(&self.texture, None, rect(target))?;
+        } else {
+            // copy_ex rotates about the centre of the destination, so hand it the unrotated shape
+            let (x, y, w, h) = target;
+            let unrotated = (x + (w as i32 - h as i32) / 2, y + (h as i32 - w as i32) / 2, h, w);
+            self.canvas.copy_ex(&self.texture, None, rect(unrotated), 90.0, None, false, false)?;
+        }
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+fn rect((x, y, w, h): (i32, i32, u32, u32)) -> sdl2::rect::Rect {
+    sdl2::rect::Rect::new(x, y, w, h)
+}
+
+/// The largest rectangle with the image's aspect ratio that fits the output, centred. Whole
+/// multiples of the image size are preferred so pixels stay square and evenly sized.
+pub fn letterbox(output: (u32, u32), image: (u32, u32)) -> (i32, i32, u32, u32) {
+    let integer = (output.0 / image.0).min(output.1 / image.1);
+    let (w, h) = if integer >= 1 {
+        (image.0 * integer, image.1 * integer)
+    } else {
+        let scale = (output.0 as f64 / image.0 as f64).min(output.1 as f64 / image.1 as f64);
+        ((image.0 as f64 * scale) as u32, (image.1 as f64 * scale) as u32)
+    };
+    (((output.0 - w) / 2) as i32, ((output.1 - h) / 2) as i32, w, h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letterbox_integer_scale() {
+        assert_eq!(letterbox((448, 512), (224, 256)), (0, 0, 448, 512));
+        assert_eq!(letterbox((1920, 1080), (224, 256)), (512, 28, 896, 1024));
+    }
+
+    #[test]
+    fn test_letterbox_shrinks_to_fit() {
+        assert_eq!(letterbox((112, 512), (224, 256)), (0, 192, 112, 128));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use machines::altair::Altair8800;
+use machines::cpm::Cpm;
+use super::Pacer;
+use super::terminal::RawTerminal;
+
+/* Bridges a machine's serial port to stdin/stdout. At an interactive terminal, keys go straight
+   to the program in raw mode, so it does its own echoing and line editing like it would with a
+   real teletype, and Ctrl-] quits. When stdin is a pipe or a file, its contents are typed in a
+   character at a time, waiting for the program to take each one - programs poll the keyboard for
+   Ctrl-C while running and would otherwise swallow what was typed ahead. The machine then runs
+   until the input is used up and the program has gone quiet. */
+
+/// Ctrl-], the usual way out of a serial console; Ctrl-C belongs to the program.
+const QUIT: u8 = 0x1d;
+
+/// How long the program has to be silent after the input runs out before a piped run ends.
+const IDLE_FRAMES: u32 = 60;
+
+/// A machine whose only connection to the user is a serial console.
+pub trait Console {
+    fn type_keys(&mut self, bytes: &[u8]);
+    fn keys_pending(&self) -> usize;
+    /// Runs for a sixtieth of a second.
+    fn run_frame(&mut self) -> Result<(), String>;
+    fn take_output(&mut self) -> Vec<u8>;
+}
+
+impl Console for Altair8800 {
+    fn type_keys(&mut self, bytes: &[u8]) {
+        Altair8800::type_keys(self, bytes)
+    }
+
+    fn keys_pending(&self) -> usize {
+        Altair8800::keys_pending(self)
+    }
+
+    fn run_frame(&mut self) -> Result<(), String> {
+        self.run(Altair8800::CLOCK_HZ / 60)
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        Altair8800::take_output(self)
+    }
+}
+
+impl Console for Cpm {
+    fn type_keys(&mut self, bytes: &[u8]) {
+        Cpm::type_keys(self, bytes)
+    }
+
+    fn keys_pending(&self) -> usize {
+        Cpm::keys_pending(self)
+    }
+
+    fn run_frame(&mut self) -> Result<(), String> {
+        self.run(Cpm::CLOCK_HZ / 60)
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        Cpm::take_output(self)
+    }
+}
+
+enum Keyboard {
+    /// Reading keys from stdin, which goes back to normal when the guard is dropped.
+    Terminal { _raw: RawTerminal },
+    Pipe(Receiver<Vec<u8>>),
+    Finished,
+}
+
+pub fn run<M: Console>(machine: &mut M, speed: f64) -> Result<(), String> {
+    let mut keyboard = if stdin_is_terminal() {
+        let terminal = RawTerminal::enter(false).map_err(|e| format!("Could not set up the terminal: {}", e))?;
+        eprint!("Connected to the serial terminal, press Ctrl-] to quit\r\n");
+        Keyboard::Terminal { _raw: terminal }
+    } else {
+        Keyboard::Pipe(spawn_reader())
+    };
+    let interactive = matches!(keyboard, Keyboard::Terminal { .. });
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut pacer = Pacer::new(speed);
+    let mut idle_frames = 0;
+    let mut typed_ahead = VecDeque::new();
+
+    loop {
+        let mut input_finished = false;
+        let keys = match &keyboard {
+            Keyboard::Terminal { .. } => {
+                let mut buffer = [0; 64];
+                let n = io::stdin().read(&mut buffer).unwrap_or(0);
+                buffer[..n].to_vec()
+            },
+            Keyboard::Pipe(receiver) => match receiver.try_recv() {
+                Ok(bytes) => bytes,
+                Err(TryRecvError::Empty) => Vec::new(),
+                Err(TryRecvError::Disconnected) => {
+                    input_finished = true;
+                    Vec::new()
+                },
+            },
+            Keyboard::Finished => Vec::new(),
+        };
+        if input_finished {
+            keyboard = Keyboard::Finished;
+        }
+        if interactive {
+            if keys.contains(&QUIT) {
+                return Ok(());
+            }
+            machine.type_keys(&translate_input(&keys));
+        } else {
+            typed_ahead.extend(translate_input(&keys));
+            if machine.keys_pending() == 0 {
+                if let Some(key) = typed_ahead.pop_front() {
+                    machine.type_keys(&[key]);
+                }
+            }
+        }
+
+        machine.run_frame()?;
+
+        let output = translate_output(&machine.take_output(), interactive);
+        if output.is_empty() {
+            idle_frames += 1;
+        } else {
+            idle_frames = 0;
+            stdout.write_all(&output).map_err(|e| e.to_string())?;
+            stdout.flush().map_err(|e| e.to_string())?;
+        }
+
+        if let Keyboard::Finished = keyboard {
+            if typed_ahead.is_empty() && machine.keys_pending() == 0 && idle_frames >= IDLE_FRAMES {
+                return Ok(());
+            }
+        }
+
+        pacer.wait();
+    }
+}
+
+fn spawn_reader() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = [0; 256];
+        loop {
+            match io::stdin().read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => if sender.send(buffer[..n].to_vec()).is_err() {
+                    break;
+                },
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(unix)]
+fn stdin_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdin_is_terminal() -> bool {
+    false
+}
+
+/// Programs of the time expect a carriage return at the end of a line, not a line feed.
+fn translate_input(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().map(|&b| if b == b'\n' { b'\r' } else { b }).collect()
+}
+
+/// Drops the NULs sent as padding for slow teletypes and, when not at a terminal, the carriage
+/// returns, leaving plain lines of text.
+fn translate_output(bytes: &[u8], interactive: bool) -> Vec<u8> {
+    bytes.iter().cloned().filter(|&b| b != 0 && (interactive || b != b'\r')).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(translate_input(b"PRINT 1\n"), b"PRINT 1\r");
+        assert_eq!(translate_output(b"OK\r\n\0\0", true), b"OK\r\n");
+        assert_eq!(translate_output(b"OK\r\n\0\0", false), b"OK\n");
+    }
+}
@@ -0,0 +1,346 @@
+use std::io;
+use std::io::prelude::*;
+
+use machines::midway::{Input, Midway8080};
+use machines::video::framebuffer::Framebuffer;
+use machines::video::overlay::Rgb;
+use super::{Pacer, Video};
+
+/* A frontend for when there is no display, only a (24-bit colour) terminal. The framebuffer is
+   packed into braille cells (2x4 pixels, 112x64 characters) or half blocks (1x2 pixels, 224x128
+   characters) and redrawn in place every frame. */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Glyphs {
+    Braille,
+    HalfBlock,
+}
+
+impl Glyphs {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "braille" => Some(Glyphs::Braille),
+            "halfblock" | "half-block" => Some(Glyphs::HalfBlock),
+            _ => None,
+        }
+    }
+}
+
+/// Terminals only report key presses, so a press holds the input down for this many frames.
+/// Key repeat then keeps it held for as long as the key is.
+const HOLD_FRAMES: u32 = 8;
+
+pub fn run(machine: &mut Midway8080, video: &mut Video, glyphs: Glyphs, speed: f64) -> Result<(), String> {
+    let _terminal = RawTerminal::enter(true).map_err(|e| format!("Could not set up the terminal: {}", e))?;
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut held: Vec<(Input, u32)> = Vec::new();
+    let mut message = String::new();
+    let mut sound = "";
+    let mut pacer = Pacer::new(speed);
+
+    loop {
+        for key in read_keys() {
+            match key {
+                Key::Quit => return Ok(()),
+                Key::Screenshot => message = video.screenshot(machine.frames),
+                Key::Record => message = video.toggle_recording(machine.frames),
+                Key::Input(input) => {
+                    machine.set_input(input, true);
+                    held.retain(|&(i, _)| i != input);
+                    held.push((input, HOLD_FRAMES));
+                },
+            }
+        }
+
+        machine.step()?;
+
+        for (input, frames) in held.iter_mut() {
+            *frames -= 1;
+            if *frames == 0 {
+                machine.set_input(*input, false);
+            }
+        }
+        held.retain(|&(_, frames)| frames > 0);
+
+        if let Some(event) = machine.take_sound_events().into_iter().rev().find(|event| event.on) {
+            sound = event.name;
+        }
+
+        video.update(machine.vram());
+
+        let mut out = String::from("\x1b[H");
+        out += &render(&video.framebuffer, glyphs);
+        out += &format!(
+            "\x1b[0m\x1b[Kframe {} | {} instructions | pc={:04x} | sound: {:<14} | c coin, 1/2 start, a/d/space move & fire, \
+             arrows/enter p2, p screenshot, r record, q quit | {}\r\n",
+            machine.frames,
+            machine.instructions,
+            machine.cpu.reg.pc,
+            sound,
+            message,
+        );
+        stdout.write_all(out.as_bytes()).map_err(|e| e.to_string())?;
+        stdout.flush().map_err(|e| e.to_string())?;
+
+        pacer.wait();
+    }
+}
+
+/// Draws the framebuffer as lines of text separated by `\r\n`, using 24-bit ANSI colours.
+pub fn render(framebuffer: &Framebuffer, glyphs: Glyphs) -> String {
+    match glyphs {
+        Glyphs::Braille => render_braille(framebuffer),
+        Glyphs::HalfBlock => render_half_blocks(framebuffer),
+    }
+}
+
+fn render_braille(framebuffer: &Framebuffer) -> String {
+    // dot numbering of the braille block, indexed by [row][column] within the cell
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    let mut out = String::new();
+    let mut current: Option<Rgb> = None;
+    for cell_row in 0..(framebuffer.height / 4) {
+        for cell_column in 0..(framebuffer.width / 2) {
+            let mut bits = 0;
+            let mut colour = (0, 0, 0);
+            for (dy, dots) in DOTS.iter().enumerate() {
+                for (dx, dot) in dots.iter().enumerate() {
+                    let px = framebuffer.pixel(2 * cell_column + dx, 4 * cell_row + dy);
+                    if px != (0, 0, 0) {
+                        bits |= dot;
+                        if brightness(px) > brightness(colour) {
+                            colour = px;
+                        }
+                    }
+                }
+            }
+
+            if bits == 0 {
+                out.push(' ');
+                continue;
+            }
+            if current != Some(colour) {
+                out += &format!("\x1b[38;2;{};{};{}m", colour.0, colour.1, colour.2);
+                current = Some(colour);
+            }
+            out.push(std::char::from_u32(0x2800 + bits).unwrap());
+        }
+        out += "\r\n";
+    }
+    out
+}
+
+fn render_half_blocks(framebuffer: &Framebuffer) -> String {
+    let mut out = String::new();
+    let mut current: Option<(Rgb, Rgb)> = None;
+    for cell_row in 0..(framebuffer.height / 2) {
+        for column in 0..framebuffer.width {
+            let top = framebuffer.pixel(column, 2 * cell_row);
+            let bottom = framebuffer.pixel(column, 2 * cell_row + 1);
+            if current != Some((top, bottom)) {
+                out += &format!(
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    top.0, top.1, top.2, bottom.0, bottom.1, bottom.2,
+                );
+                current = Some((top, bottom));
+            }
+            out.push('▀');
+        }
+        out += "\x1b[0m\r\n";
+        current = None;
+    }
+    out
+}
+
+fn brightness(px: Rgb) -> u32 {
+    px.0 as u32 + px.1 as u32 + px.2 as u32
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Quit,
+    Screenshot,
+    Record,
+    Input(Input),
+}
+
+fn read_keys() -> Vec<Key> {
+    let mut buffer = [0; 64];
+    match io::stdin().read(&mut buffer) {
+        Ok(n) => parse_keys(&buffer[..n]),
+        Err(_) => Vec::new(),
+    }
+}
+
+/* Keys other than letters arrive as escape sequences: ESC [ then any parameters and a final
+   byte from @ to ~ (CSI), or ESC O and one byte (SS3). Arrow keys end in A to D either way, with
+   parameters when a modifier is held. Alt and a key sends ESC and the key. So the escape key
+   itself is only an ESC with nothing after it in the same read. */
+
+/// Where the escape sequence starting at `start` ends, and its final byte.
+fn escape_sequence(bytes: &[u8], start: usize) -> (usize, Option<u8>) {
+    match bytes.get(start + 1) {
+        Some(b'[') => match bytes[start + 2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+            Some(n) => (start + 2 + n, Some(bytes[start + 2 + n])),
+            None => (bytes.len() - 1, None),
+        },
+        Some(b'O') if start + 2 < bytes.len() => (start + 2, Some(bytes[start + 2])),
+        _ => ((start + 1).min(bytes.len() - 1), None),
+    }
+}
+
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let key = match bytes[i] {
+            0x1b if i + 1 == bytes.len() => Some(Key::Quit),
+            0x1b => {
+                let (end, last) = escape_sequence(bytes, i);
+                i = end;
+                match last {
+                    Some(b'A') => Some(Key::Input(Input::P2Up)),
+                    Some(b'B') => Some(Key::Input(Input::P2Down)),
+                    Some(b'C') => Some(Key::Input(Input::P2Right)),
+                    Some(b'D') => Some(Key::Input(Input::P2Left)),
+                    _ => None,
+                }
+            },
+            0x03 | b'q' => Some(Key::Quit),
+            b'c' => Some(Key::Input(Input::Coin)),
+            b'1' => Some(Key::Input(Input::P1Start)),
+            b'2' => Some(Key::Input(Input::P2Start)),
+            b' ' => Some(Key::Input(Input::P1Fire)),
+            b'a' => Some(Key::Input(Input::P1Left)),
+            b'd' => Some(Key::Input(Input::P1Right)),
+            b'w' => Some(Key::Input(Input::P1Up)),
+            b's' => Some(Key::Input(Input::P1Down)),
+            b'z' => Some(Key::Input(Input::P1AimUp)),
+            b'x' => Some(Key::Input(Input::P1AimDown)),
+            b'\r' | b'\n' => Some(Key::Input(Input::P2Fire)),
+            b'p' => Some(Key::Screenshot),
+            b'r' => Some(Key::Record),
+            _ => None,
+        };
+        keys.extend(key);
+        i += 1;
+    }
+    keys
+}
+
+/* Puts the terminal into raw, non-blocking mode, optionally on the alternate screen, and puts
+   everything back when dropped - including when unwinding from a panic. */
+
+pub struct RawTerminal {
+    #[cfg(unix)]
+    original: libc::termios,
+    alternate_screen: bool,
+}
+
+impl RawTerminal {
+    #[cfg(unix)]
+    pub fn enter(alternate_screen: bool) -> io::Result<Self> {
+        unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            if alternate_screen {
+                print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+                io::stdout().flush()?;
+            }
+            Ok(Self { original, alternate_screen })
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn enter(_alternate_screen: bool) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Other, "the terminal frontend needs a unix terminal"))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if self.alternate_screen {
+            print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+            let _ = io::stdout().flush();
+        }
+        #[cfg(unix)]
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgb = (0xFF, 0xFF, 0xFF);
+
+    #[test]
+    fn test_render_braille() {
+        let mut fb = Framebuffer::new();
+        fb.pixels[0] = WHITE; // top left dot of the first cell
+        fb.pixels[3 * fb.width + 1] = WHITE; // bottom right dot of the first cell
+
+        let text = render(&fb, Glyphs::Braille);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+
+        assert_eq!(lines.len(), 64 + 1);
+        assert_eq!(lines[0], format!("\x1b[38;2;255;255;255m\u{2881}{}", " ".repeat(111)));
+        assert_eq!(lines[1], " ".repeat(112));
+    }
+
+    #[test]
+    fn test_render_half_blocks() {
+        let mut fb = Framebuffer::new();
+        fb.pixels[fb.width] = (0x20, 0xFF, 0x20); // bottom half of the first cell
+
+        let text = render(&fb, Glyphs::HalfBlock);
+        let first_line = text.split("\r\n").next().unwrap();
+
+        assert!(first_line.starts_with("\x1b[38;2;0;0;0;48;2;32;255;32m▀\x1b[38;2;0;0;0;48;2;0;0;0m▀"));
+        assert_eq!(first_line.matches('▀').count(), 224);
+        assert_eq!(text.matches("\r\n").count(), 128);
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(parse_keys(b"c1 "), vec![
+            Key::Input(Input::Coin),
+            Key::Input(Input::P1Start),
+            Key::Input(Input::P1Fire),
+        ]);
+        assert_eq!(parse_keys(b"\x1b[D\x1b[C\x1b[Aq"), vec![
+            Key::Input(Input::P2Left),
+            Key::Input(Input::P2Right),
+            Key::Input(Input::P2Up),
+            Key::Quit,
+        ]);
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Quit]);
+        assert_eq!(parse_keys(b"c\x1b"), vec![Key::Input(Input::Coin), Key::Quit]);
+    }
+
+    #[test]
+    fn test_parse_escape_sequences() {
+        // Ctrl+Up and an arrow in application mode are still arrows, and nothing else is pressed
+        assert_eq!(parse_keys(b"\x1b[1;5A\x1bOD"), vec![Key::Input(Input::P2Up), Key::Input(Input::P2Left)]);
+        // F1, F5, Alt+1 and Alt+q don't quit or press anything
+        assert_eq!(parse_keys(b"\x1bOP\x1b[15~\x1b1\x1bq"), vec![]);
+        // a sequence cut off at the end of a read is dropped
+        assert_eq!(parse_keys(b"c\x1b[1;"), vec![Key::Input(Input::Coin)]);
+        assert_eq!(parse_keys(b"c\x1bO"), vec![Key::Input(Input::Coin)]);
+    }
+}
@@ -1,5 +0,0 @@
-
-pub mod memory;
-pub mod cpu;
-pub mod disassembler;
-pub mod register;
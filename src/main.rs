@@ -1,33 +1,214 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-
-extern crate sdl2;
-
-mod invaders;
-mod i8080;
+mod bench;
+mod cli;
+mod frontend;
+mod trace_diff;
+mod vectors;
 
 use std::io::prelude::*;
 use std::fs::File;
 
-static CYCLES_PER_FRAME: usize = 2_000_000 / 120;
+use cli::{Command, Frontend, Options};
+use i8080::blocks::BlockCache;
+use trace_diff::TraceDiff;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match cli::parse(&args) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::ListMachines) => {
+            for game in machines::midway::games::ALL.iter() {
+                println!("{:<10} {}", game.name, game.description);
+            }
+            println!("{:<10} {}", machines::altair::NAME, machines::altair::DESCRIPTION);
+            println!("{:<10} {}", machines::cpm::NAME, machines::cpm::DESCRIPTION);
+            return;
+        },
+        Ok(Command::Bench(names)) => {
+            if let Err(e) = bench::run(&names) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        },
+        Ok(Command::Help) => {
+            println!("{}", cli::usage());
+            return;
+        },
+        Ok(Command::TestVectors { dir, variant }) => {
+            match vectors::run_directory(&dir, variant) {
+                Ok(results) => {
+                    println!("{}", vectors::summary(&results));
+                    if results.iter().any(|result| result.failed > 0) {
+                        std::process::exit(1);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                },
+            }
+            return;
+        },
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::usage());
+            std::process::exit(2);
+        },
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    if options.machine == machines::altair::NAME {
+        return run_altair(options);
+    }
+    if options.machine == machines::cpm::NAME {
+        return run_cpm(options);
+    }
+
+    let def = machines::midway::games::find(&options.machine).ok_or_else(|| {
+        format!("Unknown machine '{}', available machines: {}", options.machine, machines::names().join(", "))
+    })?;
+    let rom_bytes = machines::roms::load(&options.rom, def.roms).map_err(|e| e.to_string())?;
+
+    let mut machine = machines::midway::Midway8080::new(def, &rom_bytes);
+    machine.cpu.strict = options.strict;
+    if options.blocks {
+        machine.blocks = Some(BlockCache::new());
+    }
+    for (name, setting) in options.dips.iter() {
+        machine.set_dip(name, setting)?;
+    }
+    let mut video = frontend::Video::new(options.overlay.unwrap_or(def.overlay), options.effects);
+
+    if let Some(path) = &options.trace {
+        machine.trace = Some(open_trace(path)?);
+    }
+
+    let result = match options.frontend {
+        #[cfg(feature = "sdl")]
+        Frontend::Sdl => frontend::sdl::run(&mut machine, &mut video, options.display, options.speed),
+        Frontend::Terminal => frontend::terminal::run(&mut machine, &mut video, options.glyphs, options.speed),
+        Frontend::Headless(frames) => (0..frames).try_for_each(|_| {
+            machine.step()?;
+            machine.take_sound_events();
+            Ok(())
+        }).and_then(|()| {
+            video.update(machine.vram());
+            println!("Ran {} frames: {} instructions, {} cycles", machine.frames, machine.instructions, machine.cycles);
+            match &options.screenshot {
+                Some(path) => video.framebuffer.write_ppm(path)
+                    .map_err(|e| format!("Could not save {}: {}", path.display(), e)),
+                None => Ok(()),
+            }
+        }),
+        Frontend::TraceDiff(diff) => TraceDiff::open(&diff, machine.trace.take()).and_then(|comparison| {
+            machine.trace = Some(Box::new(comparison.clone()));
+            comparison.run(|| machine.step())
+        }),
+    };
+
+    if let Some(trace) = machine.trace.as_mut() {
+        trace.flush().map_err(|e| format!("Could not write trace: {}", e))?;
+    }
+    if options.debug {
+        eprintln!("{} frames, {} instructions, {} cycles", machine.frames, machine.instructions, machine.cycles);
+        eprintln!("{:?}", machine.cpu);
+    }
+    result
+}
+
+fn run_altair(options: Options) -> Result<(), String> {
+    if options.rom.is_dir() {
+        return Err(format!("The altair needs a program to load, {} is a directory", options.rom.display()));
+    }
+    let program = std::fs::read(&options.rom)
+        .map_err(|e| format!("Could not read {}: {}", options.rom.display(), e))?;
+
+    let mut machine = machines::altair::Altair8800::new();
+    machine.cpu.strict = options.strict;
+    if options.blocks {
+        machine.blocks = Some(BlockCache::new());
+    }
+    machine.load(options.org, &program)?;
+    machine.examine(options.start.unwrap_or(options.org));
+    machine.set_sense_switches(options.switches);
+    if let Some(path) = &options.trace {
+        machine.trace = Some(open_trace(path)?);
+    }
 
-    let rom = File::open("./ROMS/invaders").unwrap();
-    let rom_bytes: Vec<u8> = rom.bytes().map(|x| x.unwrap()).collect();
+    let result = match options.frontend {
+        Frontend::Headless(frames) => {
+            machine.run(frames * machines::altair::Altair8800::CLOCK_HZ / 60).and_then(|()| {
+                std::io::stdout().write_all(&machine.take_output()).map_err(|e| e.to_string())
+            })
+        },
+        Frontend::TraceDiff(diff) => TraceDiff::open(&diff, machine.trace.take()).and_then(|comparison| {
+            machine.trace = Some(Box::new(comparison.clone()));
+            comparison.run(|| machine.run(1000))
+        }),
+        _ => frontend::serial::run(&mut machine, options.speed),
+    };
 
-    let mut invaders = invaders::SpaceInvaders::new(&rom_bytes);
-    invaders.cpu.memory.load(0x000, &rom_bytes);
-    
-    loop {
-        invaders.step();
+    if let Some(trace) = machine.trace.as_mut() {
+        trace.flush().map_err(|e| format!("Could not write trace: {}", e))?;
+    }
+    if options.debug {
+        eprintln!("{} instructions, {} cycles", machine.instructions, machine.cycles);
+        eprintln!("{:?}", machine.cpu);
     }
+    result
 }
 
-fn pause() {
-    use std::io::{stdin, stdout};
-    let mut stdout = stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
-    stdout.flush().unwrap();
-    stdin().read(&mut [0]).unwrap();
-}
\ No newline at end of file
+fn run_cpm(options: Options) -> Result<(), String> {
+    let mut drives: Vec<Option<machines::cpm::disk::Disk>> = vec![None, None, None, None];
+    let mut disks = options.disks.clone();
+    if !options.disks.iter().any(|&(drive, _)| drive == 0) && !options.rom.is_dir() {
+        disks.push((0, options.rom.clone()));
+    }
+    for (drive, path) in disks {
+        drives[drive] = Some(machines::cpm::disk::Disk::open(&path)?);
+    }
+
+    let mut machine = machines::cpm::Cpm::new(drives)?;
+    machine.cpu.strict = options.strict;
+    if options.blocks {
+        machine.blocks = Some(BlockCache::new());
+    }
+    if let Some(path) = &options.trace {
+        machine.trace = Some(open_trace(path)?);
+    }
+
+    let result = match options.frontend {
+        Frontend::Headless(frames) => machine.run(frames * machines::cpm::Cpm::CLOCK_HZ / 60).and_then(|()| {
+            std::io::stdout().write_all(&machine.take_output()).map_err(|e| e.to_string())
+        }),
+        Frontend::TraceDiff(diff) => TraceDiff::open(&diff, machine.trace.take()).and_then(|comparison| {
+            machine.trace = Some(Box::new(comparison.clone()));
+            comparison.run(|| machine.run(1000))
+        }),
+        _ => frontend::serial::run(&mut machine, options.speed),
+    };
+
+    if let Some(trace) = machine.trace.as_mut() {
+        trace.flush().map_err(|e| format!("Could not write trace: {}", e))?;
+    }
+    if options.debug {
+        eprintln!("{} instructions, {} cycles", machine.instructions, machine.cycles);
+        eprintln!("{:?}", machine.cpu);
+    }
+    result
+}
+
+fn open_trace(path: &std::path::Path) -> Result<Box<dyn Write>, String> {
+    Ok(if path.to_str() == Some("-") {
+        Box::new(std::io::stderr())
+    } else {
+        let file = File::create(path).map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        Box::new(std::io::BufWriter::new(file))
+    })
+}
@@ -0,0 +1,335 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/* Compares a run against another emulator's log of the same program, a line per instruction.
+   Lines are read as NAME:VALUE fields, with anything after a semicolon ignored, which is the
+   format the machines' --trace writes:
+
+     PC:0100 OP:3e A:00 F:02 B:00 C:00 D:00 E:00 H:00 L:00 SP:ff00 CYC:0 ; MVI A, $12
+
+   Only the fields the reference has are compared, so a log without cycle counts or opcodes still
+   lines up, and values are compared without their leading zeros. Blank lines and lines starting
+   with # in the reference are skipped. The comparison sits where the machines write their trace,
+   and the machine runs until the traces diverge, the reference ends, or the instruction limit is
+   reached. */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceDiffOptions {
+    pub reference: PathBuf,
+    /// Stop after this many instructions, rather than at the end of the reference.
+    pub instructions: Option<u64>,
+    /// How many lines to show either side of a divergence.
+    pub context: usize,
+}
+
+/// The NAME:VALUE fields of a trace line.
+fn fields(line: &str) -> Vec<(String, String)> {
+    line.split(';').next().unwrap_or("").split_whitespace().filter_map(|field| {
+        let mut parts = field.splitn(2, ':');
+        Some((parts.next()?.to_ascii_uppercase(), parts.next()?.to_ascii_lowercase()))
+    }).collect()
+}
+
+/// What differs between one of our lines and the reference's, for the fields the reference has.
+pub fn differences(ours: &str, reference: &str) -> Vec<String> {
+    let ours = fields(ours);
+    fields(reference).iter().filter_map(|(name, expected)| {
+        match ours.iter().find(|(ours_name, _)| ours_name == name) {
+            Some((_, actual)) if actual.trim_start_matches('0') == expected.trim_start_matches('0') => None,
+            Some((_, actual)) => Some(format!("{} is {}, reference has {}", name, actual, expected)),
+            None => Some(format!("no {} field, reference has {}", name, expected)),
+        }
+    }).collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Stopped at the instruction limit with every line matching.
+    Matched(u64),
+    /// The reference ran out after this many matching lines.
+    ReferenceEnded(u64),
+    Diverged {
+        instruction: u64,
+        differences: Vec<String>,
+        /// The lines around the divergence, starting from instruction `first`.
+        first: u64,
+        ours: Vec<String>,
+        reference: Vec<String>,
+    },
+}
+
+struct Comparison {
+    reference: Box<dyn BufRead>,
+    limit: Option<u64>,
+    context: usize,
+    compared: u64,
+    ours: VecDeque<String>,
+    theirs: VecDeque<String>,
+    /// The differences at a divergence, and how many more of our lines to collect after it.
+    diverged: Option<(Vec<String>, usize)>,
+    partial: Vec<u8>,
+    tee: Option<Box<dyn Write>>,
+    error: Option<String>,
+    outcome: Option<Outcome>,
+}
+
+impl Comparison {
+    fn next_reference_line(&mut self) -> Option<String> {
+        loop {
+            let mut line = String::new();
+            match self.reference.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let line = line.trim_end();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        return Some(line.to_string());
+                    }
+                },
+                Err(e) => {
+                    self.error = Some(format!("Could not read the reference trace: {}", e));
+                    return None;
+                },
+            }
+        }
+    }
+
+    fn remember(lines: &mut VecDeque<String>, line: String, keep: usize) {
+        lines.push_back(line);
+        while lines.len() > keep {
+            lines.pop_front();
+        }
+    }
+
+    fn compare(&mut self, line: String) {
+        if self.outcome.is_some() || self.error.is_some() {
+            return;
+        }
+        if let Some((_, remaining)) = self.diverged.as_mut() {
+            self.ours.push_back(line);
+            *remaining -= 1;
+            if *remaining == 0 {
+                self.conclude();
+            }
+            return;
+        }
+
+        let reference = match self.next_reference_line() {
+            Some(reference) => reference,
+            None => {
+                self.outcome = Some(Outcome::ReferenceEnded(self.compared));
+                return;
+            },
+        };
+        self.compared += 1;
+        let differences = differences(&line, &reference);
+        if differences.is_empty() {
+            Self::remember(&mut self.ours, line, self.context);
+            Self::remember(&mut self.theirs, reference, self.context);
+            if Some(self.compared) == self.limit {
+                self.outcome = Some(Outcome::Matched(self.compared));
+            }
+        } else {
+            self.ours.push_back(line);
+            self.theirs.push_back(reference);
+            self.diverged = Some((differences, self.context));
+            if self.context == 0 {
+                self.conclude();
+            }
+        }
+    }
+
+    /// Records the divergence, with the reference's lines after it to match ours.
+    fn conclude(&mut self) {
+        let differences = match self.diverged.take() {
+            Some((differences, _)) => differences,
+            None => return,
+        };
+        let before = self.theirs.len() as u64 - 1;
+        while self.theirs.len() < self.ours.len() {
+            match self.next_reference_line() {
+                Some(line) => self.theirs.push_back(line),
+                None => break,
+            }
+        }
+        self.outcome = Some(Outcome::Diverged {
+            instruction: self.compared,
+            differences,
+            first: self.compared - before,
+            ours: self.ours.drain(..).collect(),
+            reference: self.theirs.drain(..).collect(),
+        });
+    }
+}
+
+/// A trace writer that compares each line against the reference as it is written. Clones share
+/// the comparison, so one can be given to the machine and the other kept to check on it.
+#[derive(Clone)]
+pub struct TraceDiff(Rc<RefCell<Comparison>>);
+
+impl TraceDiff {
+    /// Opens the reference. Lines are also passed on to `tee`, if given, to keep our own trace.
+    pub fn open(options: &TraceDiffOptions, tee: Option<Box<dyn Write>>) -> Result<Self, String> {
+        let file = File::open(&options.reference)
+            .map_err(|e| format!("Could not open {}: {}", options.reference.display(), e))?;
+        Ok(Self::new(Box::new(BufReader::new(file)), options, tee))
+    }
+
+    fn new(reference: Box<dyn BufRead>, options: &TraceDiffOptions, tee: Option<Box<dyn Write>>) -> Self {
+        TraceDiff(Rc::new(RefCell::new(Comparison {
+            reference,
+            limit: options.instructions,
+            context: options.context,
+            compared: 0,
+            ours: VecDeque::new(),
+            theirs: VecDeque::new(),
+            diverged: None,
+            partial: Vec::new(),
+            tee,
+            error: None,
+            outcome: None,
+        })))
+    }
+
+    pub fn finished(&self) -> bool {
+        let comparison = self.0.borrow();
+        comparison.outcome.is_some() || comparison.error.is_some()
+    }
+
+    /// Calls `step` until the comparison is finished, then prints the result. A divergence is an
+    /// error, so the process exits with a failure.
+    pub fn run<F: FnMut() -> Result<(), String>>(&self, mut step: F) -> Result<(), String> {
+        while !self.finished() {
+            step()?;
+        }
+        let mut comparison = self.0.borrow_mut();
+        comparison.conclude();
+        if let Some(e) = comparison.error.take() {
+            return Err(e);
+        }
+        match comparison.outcome.as_ref() {
+            Some(Outcome::Matched(n)) => println!("Traces match for {} instructions", n),
+            Some(Outcome::ReferenceEnded(n)) => println!("Traces match for all {} instructions of the reference", n),
+            Some(Outcome::Diverged { instruction, differences, first, ours, reference }) => {
+                println!("Traces diverge at instruction {}: {}", instruction, differences.join(", "));
+                for (title, lines) in [("ours", ours), ("reference", reference)].iter() {
+                    println!("{}:", title);
+                    for (i, line) in lines.iter().enumerate() {
+                        let n = first + i as u64;
+                        println!("{} {:>8}  {}", if n == *instruction { '>' } else { ' ' }, n, line);
+                    }
+                }
+                return Err(format!("traces diverge at instruction {}", instruction));
+            },
+            None => {},
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn outcome(&self) -> Option<Outcome> {
+        self.0.borrow_mut().outcome.take()
+    }
+}
+
+impl Write for TraceDiff {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut comparison = self.0.borrow_mut();
+        if let Some(tee) = comparison.tee.as_mut() {
+            tee.write_all(buf)?;
+        }
+        comparison.partial.extend_from_slice(buf);
+        while let Some(end) = comparison.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = comparison.partial.drain(..=end).collect();
+            comparison.compare(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.borrow_mut().tee.as_mut() {
+            Some(tee) => tee.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFERENCE: &str = "\
+# another emulator's log
+PC:0000 A:00 F:02 SP:0000
+PC:0002 A:12 F:02 SP:0000
+
+PC:0003 A:13 F:02 SP:0000
+PC:0004 A:14 F:02 SP:0000
+PC:0005 A:15 F:02 SP:0000
+PC:0006 A:16 F:02 SP:0000
+";
+
+    fn ours(a_at_5: u8) -> Vec<String> {
+        (0..7u8).map(|i| {
+            let a = if i == 4 { a_at_5 } else { [0x00, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17][i as usize] };
+            let pc = if i == 0 { 0 } else { i + 1 };
+            format!("PC:{:04x} OP:3c A:{:02x} F:02 B:00 SP:0000 CYC:{} ; INR A", pc, a, i * 5)
+        }).collect()
+    }
+
+    fn compare(lines: &[String], instructions: Option<u64>, context: usize) -> Option<Outcome> {
+        let options = TraceDiffOptions { reference: PathBuf::new(), instructions, context };
+        let mut diff = TraceDiff::new(Box::new(REFERENCE.as_bytes()), &options, None);
+        for line in lines.iter() {
+            writeln!(diff, "{}", line).unwrap();
+        }
+        diff.0.borrow_mut().conclude();
+        diff.outcome()
+    }
+
+    #[test]
+    fn test_fields() {
+        assert!(differences("PC:0100 A:0F CYC:7 ; MVI A, $0F", "pc:100 a:0f").is_empty());
+        assert_eq!(differences("PC:0100 A:0F", "PC:0100 A:10 SP:ff00"),
+                   vec![String::from("A is 0f, reference has 10"), String::from("no SP field, reference has ff00")]);
+        assert!(differences("A:00", "A:0").is_empty());
+    }
+
+    #[test]
+    fn test_matching() {
+        assert_eq!(compare(&ours(0x15), None, 2), Some(Outcome::ReferenceEnded(6)));
+        assert_eq!(compare(&ours(0x15), Some(3), 2), Some(Outcome::Matched(3)));
+    }
+
+    #[test]
+    fn test_divergence_with_context() {
+        let lines = ours(0x99);
+        match compare(&lines, None, 2) {
+            Some(Outcome::Diverged { instruction, differences, first, ours, reference }) => {
+                assert_eq!(instruction, 5);
+                assert_eq!(differences, vec![String::from("A is 99, reference has 15")]);
+                assert_eq!(first, 3);
+                assert_eq!(ours, &lines[2..7]);
+                assert_eq!(reference, vec!["PC:0003 A:13 F:02 SP:0000", "PC:0004 A:14 F:02 SP:0000",
+                                           "PC:0005 A:15 F:02 SP:0000", "PC:0006 A:16 F:02 SP:0000"]);
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lines_split_across_writes() {
+        let options = TraceDiffOptions { reference: PathBuf::new(), instructions: None, context: 0 };
+        let mut diff = TraceDiff::new(Box::new(REFERENCE.as_bytes()), &options, None);
+        diff.write_all(b"PC:0000 A:0").unwrap();
+        diff.write_all(b"0 F:02 SP:0000\nPC:0002 A:11 F:02 SP:0000\n").unwrap();
+        assert!(diff.finished());
+        match diff.outcome() {
+            Some(Outcome::Diverged { instruction: 2, .. }) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
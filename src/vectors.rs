@@ -0,0 +1,354 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use i8080::cpu::CPU;
+use i8080::i8085::Variant;
+use i8080::io::IO;
+use i8080::register::Reg16::{PC, SP};
+use i8080::register::Reg8::{self, A, B, C, D, E, F, H, L};
+
+/* Runs the single step test suites published for emulator authors: a JSON file per opcode, named
+   after it (3e.json), holding an array of tests like
+
+     { "name": "3e 0000",
+       "initial": { "pc": 256, "sp": 65280, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2,
+                    "h": 0, "l": 0, "ram": [[256, 62], [257, 18]] },
+       "final":   { "pc": 258, ..., "a": 18, ..., "ram": [[256, 62], [257, 18]] },
+       "cycles":  [[256, 62, "r-m"], ...],
+       "ports":   [[16, 170, "r"]] }
+
+   F is the byte PUSH PSW would push. Each test loads its initial state, runs one instruction, and
+   checks every register, the flags, each RAM byte listed in the final state and the port reads
+   and writes. Every page is watched for writes, so storing to a byte the final state doesn't list
+   fails the test too. Each entry of "cycles" is one clock, so its length is the instruction's
+   cycle count. "inte" and "ports" are optional; reads must come from the ports listed with "r",
+   in order, and get their values, and every "w" entry must be written. */
+
+/// The results for one opcode's file.
+#[derive(Debug, PartialEq)]
+pub struct OpcodeResult {
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+    /// The first failing test's name and what differed.
+    pub first_failure: Option<String>,
+}
+
+/// Replays the port reads a test expects, and records the ports read and the writes.
+struct VectorIO {
+    reads: VecDeque<u8>,
+    ports_read: Vec<u8>,
+    writes: Vec<(u8, u8)>,
+}
+
+impl IO for VectorIO {
+    fn input(&mut self, port: u8) -> u8 {
+        self.ports_read.push(port);
+        self.reads.pop_front().unwrap_or(0xFF)
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.writes.push((port, value));
+    }
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value, String> {
+    value.get(key).ok_or(format!("missing \"{}\"", key))
+}
+
+fn number(value: &Value, key: &str, max: u64) -> Result<u64, String> {
+    match field(value, key)?.as_u64() {
+        Some(n) if n <= max => Ok(n),
+        _ => Err(format!("\"{}\" should be a number from 0 to {}", key, max)),
+    }
+}
+
+fn flag(value: &Value, key: &str) -> Result<Option<bool>, String> {
+    match value.get(key) {
+        None => Ok(None),
+        Some(Value::Bool(set)) => Ok(Some(*set)),
+        Some(n) => n.as_u64().map(|n| Some(n != 0)).ok_or(format!("\"{}\" should be true or false", key)),
+    }
+}
+
+/// The [address, value] pairs of a "ram" list, or the [port, value, "r" or "w"] triples of a
+/// "ports" list.
+fn entries(value: &Value, key: &str) -> Result<Vec<(u16, u8, String)>, String> {
+    let list = match value.get(key) {
+        None => return Ok(Vec::new()),
+        Some(list) => list.as_array().ok_or(format!("\"{}\" should be a list", key))?,
+    };
+    list.iter().map(|entry| {
+        let entry = entry.as_array().filter(|entry| entry.len() >= 2).ok_or(format!("bad \"{}\" entry {}", key, entry))?;
+        let address = entry[0].as_u64().filter(|&a| a <= 0xFFFF).ok_or(format!("bad \"{}\" address {}", key, entry[0]))?;
+        let byte = entry[1].as_u64().filter(|&b| b <= 0xFF).ok_or(format!("bad \"{}\" value {}", key, entry[1]))?;
+        let kind = entry.get(2).and_then(Value::as_str).unwrap_or("").to_string();
+        Ok((address as u16, byte as u8, kind))
+    }).collect()
+}
+
+const REGISTERS: [(&str, Reg8); 8] =
+    [("a", A), ("b", B), ("c", C), ("d", D), ("e", E), ("f", F), ("h", H), ("l", L)];
+
+/// Runs one test on `cpu`, returning what differed from the final state, or an error if the test
+/// itself can't be read. The RAM the test used or the CPU wrote is cleared again afterwards so the
+/// CPU can be reused without clearing all 64K; `cpu` should come from `watched_cpu`.
+fn run_test(cpu: &mut CPU, test: &Value) -> Result<Vec<String>, String> {
+    let initial = field(test, "initial")?;
+    let expected = field(test, "final")?;
+    let initial_ram = entries(initial, "ram")?;
+    let final_ram = entries(expected, "ram")?;
+    let ports = entries(test, "ports")?;
+
+    for &(name, reg) in REGISTERS.iter() {
+        cpu.reg[reg] = number(initial, name, 0xFF)? as u8;
+    }
+    cpu.set_flags_from_pushed(cpu.reg[F]);
+    cpu.reg[PC] = number(initial, "pc", 0xFFFF)? as u16;
+    cpu.reg[SP] = number(initial, "sp", 0xFFFF)? as u16;
+    cpu.interrupts_enabled = flag(initial, "inte")?.unwrap_or(false);
+    cpu.ei_delay = false;
+    cpu.halted = false;
+    cpu.interrupt_request = None;
+    for &(address, value, _) in initial_ram.iter() {
+        cpu.memory.memory[address as usize] = value;
+    }
+
+    let mut io = VectorIO {
+        reads: ports.iter().filter(|(_, _, kind)| kind == "r").map(|&(_, value, _)| value).collect(),
+        ports_read: Vec::new(),
+        writes: Vec::new(),
+    };
+    let cycles = cpu.tick(&mut io);
+
+    let mut differences = Vec::new();
+    for &(name, reg) in REGISTERS.iter() {
+        let actual = if reg == F { cpu.flags_as_pushed() } else { cpu.reg[reg] };
+        let wanted = number(expected, name, 0xFF)? as u8;
+        if actual != wanted {
+            differences.push(format!("{} is {:02x}, expected {:02x}", name, actual, wanted));
+        }
+    }
+    for &(name, reg) in [("pc", PC), ("sp", SP)].iter() {
+        let wanted = number(expected, name, 0xFFFF)? as u16;
+        if cpu.reg[reg] != wanted {
+            differences.push(format!("{} is {:04x}, expected {:04x}", name, cpu.reg[reg], wanted));
+        }
+    }
+    if let Some(inte) = flag(expected, "inte")? {
+        if cpu.interrupts_enabled != inte {
+            differences.push(format!("inte is {}, expected {}", cpu.interrupts_enabled, inte));
+        }
+    }
+    for &(address, value, _) in final_ram.iter() {
+        if cpu.memory[address] != value {
+            differences.push(format!("({:04x}) is {:02x}, expected {:02x}", address, cpu.memory[address], value));
+        }
+    }
+    // a byte in a written page that the final state doesn't list should still hold its initial value
+    let mut touched: BTreeSet<u16> = initial_ram.iter().chain(final_ram.iter()).map(|&(address, _, _)| address).collect();
+    let written: Vec<u8> = cpu.memory.take_written_code().collect();
+    for page in written {
+        let start = u16::from(page) << 8;
+        for address in start..=start | 0xff {
+            let initial = initial_ram.iter().rev().find(|entry| entry.0 == address).map_or(0, |entry| entry.1);
+            if cpu.memory[address] != initial && !final_ram.iter().any(|entry| entry.0 == address) {
+                differences.push(format!("({:04x}) was written with {:02x}", address, cpu.memory[address]));
+            }
+            touched.insert(address);
+        }
+        cpu.memory.mark_code(page);
+    }
+    let reads: Vec<u8> = ports.iter().filter(|(_, _, kind)| kind == "r").map(|&(port, _, _)| port as u8).collect();
+    if io.ports_read != reads {
+        differences.push(format!("read ports {:02x?}, expected {:02x?}", io.ports_read, reads));
+    }
+    let writes: Vec<(u8, u8)> = ports.iter().filter(|(_, _, kind)| kind == "w")
+        .map(|&(port, value, _)| (port as u8, value)).collect();
+    if io.writes != writes {
+        differences.push(format!("wrote {:02x?} to ports, expected {:02x?}", io.writes, writes));
+    }
+    if let Some(clocks) = test.get("cycles").and_then(Value::as_array) {
+        if cycles != clocks.len() as u64 {
+            differences.push(format!("took {} cycles, expected {}", cycles, clocks.len()));
+        }
+    }
+
+    for &address in touched.iter() {
+        cpu.memory.memory[address as usize] = 0;
+    }
+    Ok(differences)
+}
+
+/// A CPU with every page watched, so `run_test` can find the bytes an instruction stored.
+fn watched_cpu(variant: Variant) -> CPU {
+    let mut cpu = CPU::with_variant(variant);
+    for page in 0..=0xff {
+        cpu.memory.mark_code(page);
+    }
+    cpu
+}
+
+/// Runs every test in one opcode's file.
+pub fn run_file(path: &Path, variant: Variant) -> Result<OpcodeResult, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let tests: Value = serde_json::from_str(&text).map_err(|e| format!("{} is not valid JSON: {}", path.display(), e))?;
+    let tests = tests.as_array().ok_or(format!("{} should hold a list of tests", path.display()))?;
+
+    let mut result = OpcodeResult {
+        name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default(),
+        passed: 0,
+        failed: 0,
+        first_failure: None,
+    };
+    let mut cpu = watched_cpu(variant);
+    for (i, test) in tests.iter().enumerate() {
+        let name = test.get("name").and_then(Value::as_str).map(String::from).unwrap_or(format!("#{}", i));
+        let differences = run_test(&mut cpu, test)
+            .map_err(|e| format!("{}: test {}: {}", path.display(), name, e))?;
+        if differences.is_empty() {
+            result.passed += 1;
+        } else {
+            result.failed += 1;
+            if result.first_failure.is_none() {
+                result.first_failure = Some(format!("{}: {}", name, differences.join(", ")));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Runs every .json file in `dir`, in name order.
+pub fn run_directory(dir: &Path, variant: Variant) -> Result<Vec<OpcodeResult>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Could not read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    if paths.is_empty() {
+        return Err(format!("No .json test files in {}", dir.display()));
+    }
+    paths.sort();
+    paths.iter().map(|path| run_file(path, variant)).collect()
+}
+
+/// A line per opcode and a total, for printing.
+pub fn summary(results: &[OpcodeResult]) -> String {
+    let mut lines: Vec<String> = results.iter().map(|result| {
+        let status = if result.failed == 0 { "pass" } else { "FAIL" };
+        let mut line = format!("{:<8} {} {:>6}/{}", result.name, status, result.passed, result.passed + result.failed);
+        if let Some(failure) = &result.first_failure {
+            line.push_str(&format!(" first failure {}", failure));
+        }
+        line
+    }).collect();
+    let failing = results.iter().filter(|result| result.failed > 0).count();
+    lines.push(format!("{} of {} opcodes passed", results.len() - failing, results.len()));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-8080-vectors-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const MVI_A: &str = r#"[
+        { "name": "3e 0001",
+          "initial": { "pc": 256, "sp": 0, "a": 0, "b": 1, "c": 2, "d": 3, "e": 4, "f": 2, "h": 5, "l": 6,
+                       "ram": [[256, 62], [257, 18]] },
+          "final": { "pc": 258, "sp": 0, "a": 18, "b": 1, "c": 2, "d": 3, "e": 4, "f": 2, "h": 5, "l": 6,
+                     "ram": [[256, 62], [257, 18]] },
+          "cycles": [[256, 62, "r-m"], [256, 62, "r-m"], [256, 62, "r-m"], [256, 62, "r-m"],
+                     [257, 18, "r-m"], [257, 18, "r-m"], [257, 18, "r-m"]] },
+        { "name": "3e 0002",
+          "initial": { "pc": 0, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                       "ram": [[0, 62], [1, 52]] },
+          "final": { "pc": 2, "sp": 0, "a": 53, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                     "ram": [[0, 62], [1, 52]] } }
+    ]"#;
+
+    const IN_OUT: &str = r#"[
+        { "name": "db 0001",
+          "initial": { "pc": 0, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                       "ram": [[0, 219], [1, 16], [2, 211], [3, 17]] },
+          "final": { "pc": 2, "sp": 0, "a": 170, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                     "ram": [] },
+          "ports": [[16, 170, "r"]] }
+    ]"#;
+
+    #[test]
+    fn test_run_directory() {
+        let dir = scratch_dir("run");
+        fs::write(dir.join("3e.json"), MVI_A).unwrap();
+        fs::write(dir.join("db.json"), IN_OUT).unwrap();
+        fs::write(dir.join("README.txt"), "not a test").unwrap();
+
+        let results = run_directory(&dir, Variant::I8080).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], OpcodeResult {
+            name: String::from("3e"),
+            passed: 1,
+            failed: 1,
+            first_failure: Some(String::from("3e 0002: a is 34, expected 35")),
+        });
+        assert_eq!((results[1].passed, results[1].failed), (1, 0));
+
+        let summary = summary(&results);
+        assert!(summary.contains("3e       FAIL      1/2 first failure 3e 0002"), "{}", summary);
+        assert!(summary.ends_with("1 of 2 opcodes passed"), "{}", summary);
+    }
+
+    const STRAY: &str = r#"[
+        { "name": "32 0001",
+          "initial": { "pc": 0, "sp": 0, "a": 18, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                       "ram": [[0, 50], [1, 86], [2, 52]] },
+          "final": { "pc": 3, "sp": 0, "a": 18, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                     "ram": [[0, 50], [1, 86], [2, 52]] } },
+        { "name": "3a 0001",
+          "initial": { "pc": 0, "sp": 0, "a": 18, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                       "ram": [[0, 58], [1, 86], [2, 52]] },
+          "final": { "pc": 3, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                     "ram": [[0, 58], [1, 86], [2, 52]] } },
+        { "name": "db 0001",
+          "initial": { "pc": 0, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                       "ram": [[0, 219], [1, 16]] },
+          "final": { "pc": 2, "sp": 0, "a": 170, "b": 0, "c": 0, "d": 0, "e": 0, "f": 2, "h": 0, "l": 0,
+                     "ram": [[0, 219], [1, 16]] },
+          "ports": [[17, 170, "r"]] }
+    ]"#;
+
+    #[test]
+    fn test_stray_stores_and_reads() {
+        let dir = scratch_dir("stray");
+        let path = dir.join("stray.json");
+        fs::write(&path, STRAY).unwrap();
+        let mut cpu = watched_cpu(Variant::I8080);
+        let tests: Value = serde_json::from_str(STRAY).unwrap();
+
+        // a store the final state doesn't list fails, and is cleared before the next test loads it
+        assert_eq!(run_test(&mut cpu, &tests[0]).unwrap(), vec![String::from("(3456) was written with 12")]);
+        assert_eq!(run_test(&mut cpu, &tests[1]).unwrap(), Vec::<String>::new());
+        assert_eq!(run_test(&mut cpu, &tests[2]).unwrap(), vec![String::from("read ports [10], expected [11]")]);
+        assert_eq!(run_file(&path, Variant::I8080).unwrap().failed, 2);
+    }
+
+    #[test]
+    fn test_malformed_files() {
+        let dir = scratch_dir("malformed");
+        fs::write(dir.join("00.json"), "[{ \"name\": \"00 0001\", \"initial\": {} }]").unwrap();
+        let error = run_directory(&dir, Variant::I8080).unwrap_err();
+        assert!(error.contains("00 0001: missing \"final\""), "{}", error);
+
+        fs::write(dir.join("00.json"), "{").unwrap();
+        assert!(run_directory(&dir, Variant::I8080).unwrap_err().contains("not valid JSON"));
+        assert!(run_directory(&dir.join("missing"), Variant::I8080).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Space Invaders for the web: the headless machine from the `machines` crate, exposed to
+//! JavaScript through wasm-bindgen. The page owns the clock, the keyboard and the audio; it calls
+//! `runFrame` 60 times a second, draws `framebuffer()` into a canvas and plays whatever
+//! `takeSoundEvents()` switched on.
+//!
+//! ```text
+//! wasm-pack build web --target web
+//! wasm-pack test web --node
+//! ```
+
+use machines::midway::games::INVADERS;
+use machines::midway::{self, Input, Midway8080};
+use machines::roms;
+use machines::video::framebuffer::Framebuffer;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Invaders {
+    machine: Midway8080,
+    framebuffer: Framebuffer,
+}
+
+#[wasm_bindgen]
+impl Invaders {
+    /// Powers on with a ROM set given as the 8 KB concatenated image or a zip of the four chips.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: Vec<u8>) -> Result<Invaders, JsError> {
+        let rom = roms::from_bytes(rom, INVADERS.roms)?;
+        Ok(Invaders {
+            machine: Midway8080::new(&INVADERS, &rom),
+            framebuffer: Framebuffer::new(),
+        })
+    }
+
+    /// Runs a 60th of a second.
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        self.machine.step().map_err(|e| JsError::new(&e))
+    }
+
+    /// The screen as RGBA bytes, `width` by `height` the way up the player sees it, in the
+    /// colours of the cabinet's overlay. Goes straight into an `ImageData`.
+    pub fn framebuffer(&mut self) -> Vec<u8> {
+        self.framebuffer.render(self.machine.vram(), INVADERS.overlay);
+        self.framebuffer.rgba()
+    }
+
+    pub fn width() -> usize {
+        Framebuffer::WIDTH
+    }
+
+    pub fn height() -> usize {
+        Framebuffer::HEIGHT
+    }
+
+    /// Presses or releases a control by name: `coin`, `p1-start`, `p2-start`, `p1-left`,
+    /// `p1-right`, `p1-fire`, the same for `p2`, and `tilt`.
+    #[wasm_bindgen(js_name = setInput)]
+    pub fn set_input(&mut self, name: &str, pressed: bool) -> Result<(), JsError> {
+        let input = Input::from_name(name).ok_or_else(|| JsError::new(&format!("Unknown input '{}'", name)))?;
+        self.machine.set_input(input, pressed);
+        Ok(())
+    }
+
+    /// Sounds switched on or off since the last call, oldest first.
+    #[wasm_bindgen(js_name = takeSoundEvents)]
+    pub fn take_sound_events(&mut self) -> Vec<SoundEvent> {
+        self.machine.take_sound_events().into_iter().map(SoundEvent::from).collect()
+    }
+}
+
+/// One of the board's sound circuits, e.g. `shot` or `ufo`, switching on or off.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundEvent {
+    name: &'static str,
+    on: bool,
+}
+
+#[wasm_bindgen]
+impl SoundEvent {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn on(&self) -> bool {
+        self.on
+    }
+}
+
+impl From<midway::SoundEvent> for SoundEvent {
+    fn from(event: midway::SoundEvent) -> Self {
+        SoundEvent { name: event.name, on: event.on }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /* Run on the host as plain tests, and in node with `wasm-pack test web --node`. */
+
+    const ROM: &[u8] = include_bytes!("../../ROMS/invaders");
+
+    fn lit(rgba: &[u8]) -> usize {
+        rgba.chunks(4).filter(|pixel| pixel[..3] != [0, 0, 0]).count()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_attract_mode() {
+        let mut invaders = Invaders::new(ROM.to_vec()).unwrap();
+        assert_eq!(lit(&invaders.framebuffer()), 0);
+        for _ in 0..120 {
+            invaders.run_frame().unwrap();
+        }
+        let rgba = invaders.framebuffer();
+        assert_eq!(rgba.len(), Invaders::width() * Invaders::height() * 4);
+
+        let mut expected = Framebuffer::new();
+        expected.render(include_bytes!("../../machines/tests/golden/invaders-attract-120.vram"), INVADERS.overlay);
+        assert!(rgba == expected.rgba(), "frame 120 differs from the golden frame");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_inputs_and_sound() {
+        let mut invaders = Invaders::new(ROM.to_vec()).unwrap();
+        let presses = [(100, "coin"), (200, "p1-start"), (670, "p1-fire")];
+        let mut events = Vec::new();
+        for frame in 0..690 {
+            for &(_, name) in presses.iter().filter(|&&(at, _)| at == frame) {
+                invaders.set_input(name, true).unwrap();
+            }
+            for &(_, name) in presses.iter().filter(|&&(at, _)| at + 5 == frame) {
+                invaders.set_input(name, false).unwrap();
+            }
+            invaders.run_frame().unwrap();
+            events.extend(invaders.take_sound_events());
+        }
+        assert!(events.iter().any(|event| event.name() == "shot" && event.on()), "{:?}", events);
+    }
+}